@@ -0,0 +1,121 @@
+use crate::compiler::time_constraint_compiler::TimeConstraintCompiler;
+use crate::error::ScheduleError;
+use crate::extractor::schedule_extractor::{ScheduleExtractor, ScheduleStrategy};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+// The strategies tried on every zone of the federation for candidate schedules. Earliest and
+// latest give the morning- and evening-heavy extremes of an ordering, the others points
+// between them.
+const CANDIDATE_STRATEGIES: [ScheduleStrategy; 5] = [
+    ScheduleStrategy::Earliest,
+    ScheduleStrategy::Latest,
+    ScheduleStrategy::Centered,
+    ScheduleStrategy::Justified,
+    ScheduleStrategy::MaximumSpread,
+];
+
+// One of several feasible schedules, labelled by the order its instances come in
+#[derive(Debug, Clone, PartialEq)]
+pub struct Alternative {
+    // The clocks in time order, e.g. "Gabapentin_1 < Antepsin_1 = Pro-Kolin_1 < ..."
+    pub ordering: String,
+    pub schedule: HashMap<String, i32>,
+}
+
+// Up to `count` feasible schedules, as different from one another as possible. The first is the
+// schedule `strategy` extracts; the candidates for the rest are every zone of the federation
+// (one per ordering the disjunction search kept, see `set_max_zones`) extracted with each of
+// the strategies above. They are picked farthest first: each time, the candidate whose total
+// distance in minutes from the nearest schedule already picked is largest, preferring orderings
+// not picked yet. Fewer than `count` come back if there aren't that many distinct schedules.
+pub fn alternatives(
+    compiler: &TimeConstraintCompiler,
+    count: usize,
+    strategy: ScheduleStrategy,
+) -> Result<Vec<Alternative>, ScheduleError> {
+    if count == 0 {
+        return Ok(Vec::new());
+    }
+
+    let first = compiler.finalize_schedule(strategy)?;
+
+    let mut candidates: Vec<HashMap<String, i32>> = Vec::new();
+    for zone in &compiler.federation {
+        let zone = compiler.zone_with_targets(zone);
+        let extractor = ScheduleExtractor::new(&zone, &compiler.clocks);
+        for strategy in CANDIDATE_STRATEGIES {
+            // A strategy that can't place every clock in this zone just adds no candidate
+            if let Ok(schedule) = extractor.extract_schedule(strategy) {
+                if !candidates.contains(&schedule) {
+                    candidates.push(schedule);
+                }
+            }
+        }
+    }
+
+    let mut chosen = vec![Alternative {
+        ordering: ordering(&first),
+        schedule: first,
+    }];
+
+    while chosen.len() < count {
+        let farthest = candidates
+            .iter()
+            .enumerate()
+            .map(|(index, schedule)| {
+                let label = ordering(schedule);
+                let new_ordering = chosen.iter().all(|picked| picked.ordering != label);
+                let nearest = chosen
+                    .iter()
+                    .map(|picked| distance(&picked.schedule, schedule))
+                    .min()
+                    .unwrap_or(0);
+                (new_ordering, nearest, index, label)
+            })
+            .filter(|&(_, nearest, _, _)| nearest > 0)
+            // Ties go to the earlier candidate, from the earlier zone
+            .max_by_key(|&(new_ordering, nearest, index, _)| {
+                (new_ordering, nearest, Reverse(index))
+            });
+
+        let Some((_, _, index, label)) = farthest else {
+            break;
+        };
+        chosen.push(Alternative {
+            ordering: label,
+            schedule: candidates.remove(index),
+        });
+    }
+
+    Ok(chosen)
+}
+
+// The clocks of a schedule in time order, "<" between times and "=" between clocks at the same
+// time (by id)
+pub fn ordering(schedule: &HashMap<String, i32>) -> String {
+    let mut entries: Vec<(i32, &String)> = schedule
+        .iter()
+        .map(|(clock_id, &minutes)| (minutes, clock_id))
+        .collect();
+    entries.sort();
+
+    let mut label = String::new();
+    for (index, &(minutes, clock_id)) in entries.iter().enumerate() {
+        if index > 0 {
+            label.push_str(if entries[index - 1].0 == minutes { " = " } else { " < " });
+        }
+        label.push_str(clock_id);
+    }
+    label
+}
+
+// Total minutes between the times two schedules give the same clocks
+fn distance(a: &HashMap<String, i32>, b: &HashMap<String, i32>) -> i64 {
+    a.iter()
+        .map(|(clock_id, &minutes)| {
+            b.get(clock_id)
+                .map_or(0, |&other| (minutes as i64 - other as i64).abs())
+        })
+        .sum()
+}
@@ -3,5 +3,10 @@ use clock_zones::Variable;
 pub struct ClockInfo {
     pub entity_name: String,
     pub instance: usize,
+    // Day of the horizon (1-based) the clock is allocated to, or None if it may fall on any day
+    pub day: Option<usize>,
     pub variable: Variable,
+    // Preferred time (minutes from the start of the horizon) from an anchor window, which
+    // extraction places the clock as close to as the zone allows
+    pub target: Option<i64>,
 }
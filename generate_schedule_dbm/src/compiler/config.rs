@@ -0,0 +1,153 @@
+use crate::error::ScheduleError;
+use crate::extractor::schedule_extractor::ScheduleStrategy;
+use crate::types::frequency::Weekday;
+use std::collections::HashMap;
+
+// Compiler settings that don't depend on the entities being scheduled
+#[derive(Debug, Clone)]
+pub struct CompilerConfig {
+    // Schedulable part of the day in minutes from midnight (start, end)
+    pub day_window: (i64, i64),
+    // Windows for every entity of a category, replacing the day window for them
+    pub category_windows: HashMap<String, (i64, i64)>,
+    // Windows for single entities, taking precedence over their category's window
+    pub entity_windows: HashMap<String, (i64, i64)>,
+    // Number of days to schedule; clock times are minutes from midnight of day 1
+    pub horizon_days: usize,
+    // Weekday of day 1, which places weekday frequencies ("Mon/Wed/Fri") in the horizon
+    pub first_weekday: Weekday,
+    // Whether the schedule repeats: the horizon wraps around, so the first instance after the
+    // horizon follows the last one inside it
+    pub cyclic: bool,
+    // Maximum number of zones to keep in the federation
+    pub max_zones: usize,
+    // How many zone operations the disjunction search may take before giving up
+    pub search_budget: usize,
+    // Preferred times of day per entity, for the preference-weighted strategy
+    pub preferences: HashMap<String, Preference>,
+}
+
+// Times of day an entity would rather be scheduled at, taken in order by each day's instances,
+// and how much a minute away from them costs
+#[derive(Debug, Clone, PartialEq)]
+pub struct Preference {
+    pub times: Vec<i64>,
+    pub weight: u32,
+}
+
+// Enough for the disjunction search on schedules of a week or more; a search needing more is
+// almost always lost in orderings that fail deep down
+pub const DEFAULT_SEARCH_BUDGET: usize = 200_000;
+
+// The weight of an anchor window when no preference is given for its entity
+pub const ANCHOR_WEIGHT: u32 = 1;
+
+impl Default for CompilerConfig {
+    fn default() -> Self {
+        CompilerConfig {
+            day_window: (0, 1440),
+            category_windows: HashMap::new(),
+            entity_windows: HashMap::new(),
+            horizon_days: 1,
+            first_weekday: Weekday::Mon,
+            cyclic: false,
+            max_zones: 16,
+            search_budget: DEFAULT_SEARCH_BUDGET,
+            preferences: HashMap::new(),
+        }
+    }
+}
+
+impl CompilerConfig {
+    // The window an entity is scheduled in: its own, else its category's, else the day window
+    pub fn window_for(&self, entity: &str, category: &str) -> (i64, i64) {
+        self.entity_windows
+            .get(entity)
+            .or_else(|| self.category_windows.get(category))
+            .copied()
+            .unwrap_or(self.day_window)
+    }
+
+    // Check that every window lies within the day and doesn't end before it starts
+    pub fn validate(&self) -> Result<(), ScheduleError> {
+        let windows = std::iter::once(("day window".to_string(), self.day_window))
+            .chain(
+                self.category_windows
+                    .iter()
+                    .map(|(category, &window)| (format!("{} window", category), window)),
+            )
+            .chain(
+                self.entity_windows
+                    .iter()
+                    .map(|(entity, &window)| (format!("{} window", entity), window)),
+            );
+
+        for (name, (start, end)) in windows {
+            if start < 0 || end > 1440 || end <= start {
+                return Err(ScheduleError::parse(
+                    format_window(start, end),
+                    format!(
+                        "Invalid window {}: must end after it starts, within 00:00-24:00",
+                        format_window(start, end)
+                    ),
+                )
+                .in_field(name));
+            }
+        }
+
+        for (entity, preference) in &self.preferences {
+            let field = format!("{} preference", entity);
+            if let Some(&time) = preference.times.iter().find(|&&time| !(0..=1440).contains(&time)) {
+                return Err(ScheduleError::parse(
+                    time.to_string(),
+                    "Preferred time must be within 00:00-24:00",
+                )
+                .in_field(field));
+            }
+            if preference.weight == 0 {
+                return Err(ScheduleError::parse("0", "Preference weight must be at least 1")
+                    .in_field(field));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// Overrides given on the command line (or by a caller) on top of a table or spec
+#[derive(Debug, Clone, Default)]
+pub struct ScheduleOptions {
+    pub strategy: Option<ScheduleStrategy>,
+    // Start and end of the day window in minutes from midnight
+    pub day_start: Option<i64>,
+    pub day_end: Option<i64>,
+    pub days: Option<usize>,
+    // Turns cyclic mode on (a spec may also turn it on)
+    pub cyclic: bool,
+}
+
+impl ScheduleOptions {
+    pub fn apply_to(&self, config: &mut CompilerConfig) {
+        if let Some(start) = self.day_start {
+            config.day_window.0 = start;
+        }
+        if let Some(end) = self.day_end {
+            config.day_window.1 = end;
+        }
+        if let Some(days) = self.days {
+            config.horizon_days = days.max(1);
+        }
+        config.cyclic |= self.cyclic;
+    }
+}
+
+// "HH:MM-HH:MM" for a window in minutes from midnight
+pub fn format_window(start: i64, end: i64) -> String {
+    format!(
+        "{:02}:{:02}-{:02}:{:02}",
+        start / 60,
+        start % 60,
+        end / 60,
+        end % 60
+    )
+}
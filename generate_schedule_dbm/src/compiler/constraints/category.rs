@@ -1,10 +1,15 @@
+use crate::compiler::constraints::entity::{describe_gap, push_gap};
 use crate::compiler::debugging::{debug_error, debug_print};
+use crate::compiler::federation::{DiffConstraint, Disjunction};
+use crate::compiler::infeasibility::ConstraintOrigin;
+use crate::compiler::reference_resolution::name_key;
 use crate::compiler::time_constraint_compiler::TimeConstraintCompiler;
+use crate::error::ScheduleError;
 use crate::types::constraints::ConstraintType;
-use clock_zones::{Constraint, Variable};
+use clock_zones::Variable;
 use std::collections::HashMap;
 
-pub fn apply_category_constraints(compiler: &mut TimeConstraintCompiler) -> Result<(), String> {
+pub fn apply_category_constraints(compiler: &mut TimeConstraintCompiler) -> Result<(), ScheduleError> {
     // Skip if there are no category constraints
     if compiler.category_constraints.is_none()
         || compiler.category_constraints.as_ref().unwrap().is_empty()
@@ -15,12 +20,13 @@ pub fn apply_category_constraints(compiler: &mut TimeConstraintCompiler) -> Resu
         return Ok(());
     }
 
-    // Create a mapping of categories to entity clocks for efficient lookup
+    // Create a mapping of categories to entity clocks for efficient lookup, keyed like
+    // references so that a category is matched regardless of case
     let mut category_entity_clocks: HashMap<String, Vec<Variable>> = HashMap::new();
 
     // First, group all entity clocks by their category
     for (entity_name, entity) in &compiler.entities {
-        let category = entity.category.clone();
+        let category = name_key(&entity.category);
 
         // Get all clocks for this entity
         let entity_clocks: Vec<Variable> = compiler
@@ -37,32 +43,43 @@ pub fn apply_category_constraints(compiler: &mut TimeConstraintCompiler) -> Resu
             .extend(entity_clocks);
     }
 
+    // In clock order (by entity name, then instance), so the disjunctions come in the same
+    // order on every run
+    for clocks in category_entity_clocks.values_mut() {
+        clocks.sort_by_key(|var| var.number());
+    }
+
     // Collect all constraint operations we need to perform
     let mut constraint_operations = Vec::new();
+    let mut disjunctions = Vec::new();
+    let mut origins = Vec::new();
 
     // Process each category constraint
     if let Some(category_constraints) = &compiler.category_constraints {
         for constraint in category_constraints {
+            let origin = origins.len();
+            origins.push(ConstraintOrigin::Category {
+                expression: constraint.to_string(),
+            });
             let from_category = &constraint.from_category;
             let to_category = &constraint.to_category;
 
             // Get clocks for both categories
-            let from_clocks = category_entity_clocks.get(from_category);
-            let to_clocks = category_entity_clocks.get(to_category);
+            let from_clocks = category_entity_clocks.get(&name_key(from_category));
+            let to_clocks = category_entity_clocks.get(&name_key(to_category));
 
             match (from_clocks, to_clocks) {
                 (Some(from_vars), Some(to_vars)) => {
-                    // Calculate time in minutes
-                    let time_in_minutes =
-                        constraint.time_unit.to_minutes(constraint.time_value) as i64;
+                    let (min_gap, max_gap) = constraint.gap_minutes();
 
                     match &constraint.constraint_type {
-                        ConstraintType::Before => {
-                            // Apply before constraints: from_category entities must be before to_category entities
+                        ConstraintType::Before | ConstraintType::After => {
+                            // Apply before/after constraints: from_category entities must be
+                            // before/after to_category entities
                             for &from_var in from_vars {
                                 for &to_var in to_vars {
-                                    // Skip if same variable
-                                    if from_var == to_var {
+                                    // Skip if same variable, or on unrelated days
+                                    if from_var == to_var || !compiler.clocks_related(from_var, to_var, 0) {
                                         continue;
                                     }
 
@@ -71,29 +88,46 @@ pub fn apply_category_constraints(compiler: &mut TimeConstraintCompiler) -> Resu
                                     let to_name =
                                         compiler.find_clock_name(to_var).unwrap_or_default();
 
-                                    constraint_operations.push((
-                                        from_var,
-                                        to_var,
-                                        time_in_minutes,
-                                        format!(
-                                            "{} (category {}) must be ≥{}h{}m before {} (category {})",
+                                    // The gap runs from the earlier clock to the later one
+                                    let (later, earlier) = match constraint.constraint_type {
+                                        ConstraintType::Before => (to_var, from_var),
+                                        _ => (from_var, to_var),
+                                    };
+                                    push_gap(
+                                        &mut constraint_operations,
+                                        origin,
+                                        (later, earlier),
+                                        (min_gap, max_gap),
+                                        0,
+                                        &format!(
+                                            "{} (category {}) must be {} {} {} (category {})",
                                             from_name,
                                             from_category,
-                                            time_in_minutes / 60,
-                                            time_in_minutes % 60,
+                                            describe_gap(min_gap, max_gap),
+                                            constraint.constraint_type,
                                             to_name,
                                             to_category
                                         ),
-                                    ));
+                                    );
                                 }
                             }
                         }
-                        ConstraintType::After => {
-                            // Apply after constraints: from_category entities must be after to_category entities
+                        ConstraintType::ApartFrom => {
+                            // Apply apart from constraints: minimum separation in either order,
+                            // resolved by the federation search after all passes. An upper bound
+                            // alone ("≤30m apart from") is convex and goes in the zone.
+                            // Within one category ("med apart from med") each pair is taken once,
+                            // as apart is symmetric.
+                            let span = if max_gap.is_some() { 0 } else { 1 };
+                            let same_category = name_key(from_category) == name_key(to_category);
                             for &from_var in from_vars {
                                 for &to_var in to_vars {
-                                    // Skip if same variable
-                                    if from_var == to_var {
+                                    // Skip if same variable or pair already taken, or on
+                                    // unrelated days
+                                    if from_var == to_var
+                                        || (same_category && to_var.number() < from_var.number())
+                                        || !compiler.clocks_related(from_var, to_var, span)
+                                    {
                                         continue;
                                     }
 
@@ -101,78 +135,88 @@ pub fn apply_category_constraints(compiler: &mut TimeConstraintCompiler) -> Resu
                                         compiler.find_clock_name(from_var).unwrap_or_default();
                                     let to_name =
                                         compiler.find_clock_name(to_var).unwrap_or_default();
-
-                                    constraint_operations.push((
-                                        to_var,
-                                        from_var,
-                                        time_in_minutes,
-                                        format!(
-                                            "{} (category {}) must be ≥{}h{}m after {} (category {})",
-                                            from_name,
-                                            from_category,
-                                            time_in_minutes / 60,
-                                            time_in_minutes % 60,
-                                            to_name,
-                                            to_category
-                                        ),
-                                    ));
-                                }
-                            }
-                        }
-                        ConstraintType::ApartFrom => {
-                            // Apply apart from constraints: minimum separation between entities
-                            // Note: This is more complex as we need to ensure separation in either direction
-                            if compiler.debug {
-                                debug_print(
-                                    compiler,
-                                    "ℹ️",
-                                    &format!(
-                                        "Category constraint: {} must be ≥{}h{}m apart from {}",
+                                    let description = format!(
+                                        "{} (category {}) must be {} apart from {} (category {})",
+                                        from_name,
                                         from_category,
-                                        time_in_minutes / 60,
-                                        time_in_minutes % 60,
+                                        describe_gap(min_gap, max_gap),
+                                        to_name,
                                         to_category
-                                    ),
-                                );
+                                    );
+
+                                    match max_gap {
+                                        None => disjunctions.push((
+                                            origin,
+                                            Disjunction::apart(from_var, to_var, min_gap, description),
+                                        )),
+                                        Some(max_gap) if min_gap == 0 => {
+                                            for constraint in [
+                                                DiffConstraint::diff_le(from_var, to_var, max_gap),
+                                                DiffConstraint::diff_ge(from_var, to_var, -max_gap),
+                                            ] {
+                                                constraint_operations.push((origin, constraint, description.clone()));
+                                            }
+                                        }
+                                        Some(max_gap) => {
+                                            let side = |later, earlier| {
+                                                vec![
+                                                    DiffConstraint::diff_ge(later, earlier, min_gap),
+                                                    DiffConstraint::diff_le(later, earlier, max_gap),
+                                                ]
+                                            };
+                                            disjunctions.push((
+                                                origin,
+                                                Disjunction::new(
+                                                    vec![side(from_var, to_var), side(to_var, from_var)],
+                                                    description,
+                                                ),
+                                            ));
+                                        }
+                                    }
+                                }
                             }
-
-                            // We don't directly add ApartFrom as a constraint here
-                            // because it's a disjunctive constraint that needs special handling
-                            // This would need additional logic in the DBM system
                         }
-                        ConstraintType::Apart => {
-                            // This type doesn't make sense for category constraints
+                        ConstraintType::Apart | ConstraintType::With => {
+                            // These types don't make sense for category constraints
                             debug_error(
                                 compiler,
                                 "⚠️",
                                 &format!(
-                                    "Apart constraint type not applicable for category constraints: {} and {}",
-                                    from_category, to_category
+                                    "{} constraint type not applicable for category constraints: {} and {}",
+                                    constraint.constraint_type, from_category, to_category
                                 ),
                             );
                         }
                     }
                 }
-                _ => {
-                    debug_error(
-                        compiler,
-                        "⚠️",
-                        &format!(
-                            "Could not find clocks for categories: {} and/or {}",
-                            from_category, to_category
-                        ),
-                    );
+                // A category no entity has (one whose entities have no instances in the horizon
+                // is in the map, with nothing to relate)
+                (None, _) => {
+                    return Err(ScheduleError::UnresolvedReference {
+                        reference: from_category.clone(),
+                    })
+                }
+                (_, None) => {
+                    return Err(ScheduleError::UnresolvedReference {
+                        reference: to_category.clone(),
+                    })
                 }
             }
         }
     }
 
     // Apply all the constraints we've collected
-    for (from_var, to_var, time_minutes, description) in constraint_operations {
-        compiler.add_constraint_safely(
-            || Constraint::new_diff_ge(to_var, from_var, time_minutes),
-            &description,
-        );
+    let groups: Vec<usize> = origins
+        .into_iter()
+        .map(|origin| compiler.new_group(origin))
+        .collect();
+
+    for (origin, constraint, description) in constraint_operations {
+        compiler.add_constraint(groups[origin], constraint, &description);
+    }
+
+    for (origin, disjunction) in disjunctions {
+        compiler.add_disjunction(groups[origin], disjunction);
     }
 
     Ok(())
@@ -1,27 +1,126 @@
-use crate::compiler::debugging::debug_print;
+use crate::compiler::config::format_window;
+use crate::compiler::federation::{DiffConstraint, Disjunction};
+use crate::compiler::infeasibility::ConstraintOrigin;
 use crate::compiler::time_constraint_compiler::TimeConstraintCompiler;
-use clock_zones::{Constraint, Zone};
-
-pub fn apply_daily_bounds(compiler: &mut TimeConstraintCompiler) -> Result<(), String> {
-    // Convert time to minutes (0-1440 for a 24-hour day)
-    for (clock_id, clock_info) in &compiler.clocks {
-        // Not before 0:00
-        compiler
-            .zone
-            .add_constraint(Constraint::new_ge(clock_info.variable, 0));
-        // Not after 23:59
-        compiler
-            .zone
-            .add_constraint(Constraint::new_le(clock_info.variable, 1440));
-
-        if compiler.debug {
-            debug_print(
-                compiler,
-                "⏱️",
-                &format!("Set bounds for {}: [0:00, 23:59]", clock_id),
+use crate::error::ScheduleError;
+use clock_zones::Clock;
+use std::collections::HashMap;
+
+pub const MINUTES_PER_DAY: i64 = 1440;
+
+pub fn apply_daily_bounds(compiler: &mut TimeConstraintCompiler) -> Result<(), ScheduleError> {
+    let days = compiler.config.horizon_days as i64;
+
+    // Each clock uses its entity's window, else its category's, else the day window
+    // (in minutes, 0-1440 for the whole 24-hour day)
+    let mut clocks: Vec<_> = compiler
+        .clocks
+        .iter()
+        .map(|(clock_id, clock_info)| {
+            let category = compiler
+                .entities
+                .get(&clock_info.entity_name)
+                .map(|entity| entity.category.as_str())
+                .unwrap_or_default();
+            let target = if compiler.config.entity_windows.contains_key(&clock_info.entity_name) {
+                Some(clock_info.entity_name.clone())
+            } else if compiler.config.category_windows.contains_key(category) {
+                Some(category.to_string())
+            } else {
+                None
+            };
+            let window = compiler.config.window_for(&clock_info.entity_name, category);
+            (clock_id.clone(), clock_info.variable, clock_info.day, target, window)
+        })
+        .collect();
+    // In clock order, so the groups are created in the same order on every run
+    clocks.sort_by_key(|&(_, variable, ..)| variable.number());
+
+    // One group for the day window and one per entity or category window, so a conflict
+    // names the window involved
+    let mut groups: HashMap<Option<String>, usize> = HashMap::new();
+
+    for (clock_id, variable, day, target, (start, end)) in clocks {
+        let group = *groups.entry(target.clone()).or_insert_with(|| {
+            compiler.new_group(match target {
+                Some(target) => ConstraintOrigin::Window { target, start, end },
+                None => ConstraintOrigin::DayWindow { start, end },
+            })
+        });
+
+        // A clock allocated to a day stays within that day's window; any other clock stays
+        // within the horizon and, below, within the window of one of its days
+        let (lower, upper) = match day {
+            Some(day) => {
+                let offset = (day as i64 - 1) * MINUTES_PER_DAY;
+                (offset + start, offset + end)
+            }
+            None => (start, (days - 1) * MINUTES_PER_DAY + end),
+        };
+
+        let description = format!(
+            "Set bounds for {}: [{}, {}]",
+            clock_id,
+            format_minutes(lower, days),
+            format_minutes(upper, days)
+        );
+
+        // Not before the start of the day
+        compiler.add_constraint(
+            group,
+            DiffConstraint::diff_ge(variable, Clock::ZERO, lower),
+            &description,
+        );
+        // Not after the end of the day
+        compiler.add_constraint(
+            group,
+            DiffConstraint::diff_le(variable, Clock::ZERO, upper),
+            &description,
+        );
+
+        // Outside the window on every day is not allowed either: one alternative per day
+        if day.is_none() && days > 1 && end - start < MINUTES_PER_DAY {
+            let alternatives = (0..days)
+                .map(|d| {
+                    let offset = d * MINUTES_PER_DAY;
+                    vec![
+                        DiffConstraint::diff_ge(variable, Clock::ZERO, offset + start),
+                        DiffConstraint::diff_le(variable, Clock::ZERO, offset + end),
+                    ]
+                })
+                .collect();
+            compiler.add_disjunction(
+                group,
+                Disjunction::new(
+                    alternatives,
+                    format!(
+                        "{} must fall within its window {}",
+                        clock_id,
+                        format_window(start, end)
+                    ),
+                ),
             );
         }
     }
 
     Ok(())
 }
+
+// Minutes from the start of the horizon as "HH:MM", prefixed with the day when there are several
+pub fn format_minutes(minutes: i64, days: i64) -> String {
+    let (day, time) = if minutes == days * MINUTES_PER_DAY {
+        // The very end of the horizon reads as 24:00 on the last day
+        (days, MINUTES_PER_DAY)
+    } else {
+        (
+            minutes.div_euclid(MINUTES_PER_DAY) + 1,
+            minutes.rem_euclid(MINUTES_PER_DAY),
+        )
+    };
+
+    if days > 1 {
+        format!("day {} {:02}:{:02}", day, time / 60, time % 60)
+    } else {
+        format!("{:02}:{:02}", time / 60, time % 60)
+    }
+}
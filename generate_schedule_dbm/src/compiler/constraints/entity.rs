@@ -1,31 +1,57 @@
 use crate::compiler::clock_info::ClockInfo;
+use crate::compiler::constraints::daily_bounds::MINUTES_PER_DAY;
 use crate::compiler::debugging::{debug_error, debug_print};
-use crate::compiler::reference_resolution::resolve_reference;
+use crate::compiler::federation::{DiffConstraint, Disjunction};
+use crate::compiler::infeasibility::ConstraintOrigin;
+use crate::compiler::reference_resolution::{paired_reference_clocks, resolve_reference};
 use crate::compiler::time_constraint_compiler::TimeConstraintCompiler;
-use crate::types::constraints::{ConstraintExpression, ConstraintReference, ConstraintType};
-use crate::types::time_unit::TimeUnit::Hour;
-use clock_zones::{Constraint, Variable, Zone};
-
-pub fn apply_entity_constraints(compiler: &mut TimeConstraintCompiler) -> Result<(), String> {
+use crate::error::ScheduleError;
+use crate::types::constraints::{
+    ConstraintExpression, ConstraintReference, ConstraintType, GapBound, Pairing,
+};
+use crate::types::entity::Entity;
+use clock_zones::Variable;
+use std::collections::{HashMap, HashSet};
+
+pub fn apply_entity_constraints(compiler: &mut TimeConstraintCompiler) -> Result<(), ScheduleError> {
     // First, collect all constraint operations we need to perform
     let mut constraint_operations = Vec::new();
+    let mut disjunctions = Vec::new();
+    // The source of each constraint, indexed by the first element of the operations above
+    let mut origins = Vec::new();
 
-    // Build our entity clock map up front
-    let mut entity_clocks_map = std::collections::HashMap::new();
+    // Build our entity clock map up front, each entity's clocks in instance order
+    let mut entity_clocks_map = HashMap::new();
     for (entity_name, _) in &compiler.entities {
-        let entity_clocks: Vec<&ClockInfo> = compiler
+        let mut entity_clocks: Vec<&ClockInfo> = compiler
             .clocks
             .values()
             .filter(|c| c.entity_name == *entity_name)
             .collect();
+        entity_clocks.sort_by_key(|c| c.instance);
         entity_clocks_map.insert(entity_name.clone(), entity_clocks);
     }
+    let positions = clock_positions(compiler);
+
+    // By name, so the constraint groups and disjunctions come in the same order on every run
+    // (the conflict explanation and the alternatives follow it)
+    let mut entities: Vec<(&String, &Entity)> = compiler.entities.iter().collect();
+    entities.sort_by_key(|&(entity_name, _)| entity_name);
 
-    for (entity_name, entity) in &compiler.entities {
+    for (entity_name, entity) in entities {
         let entity_clocks = entity_clocks_map.get(entity_name).unwrap();
+        let paired = paired_gaps(&entity.constraints);
+        let mut paired_done = HashSet::new();
 
         // Process all constraint types for this entity
         for constraint in &entity.constraints {
+            let origin = origins.len();
+            origins.push(ConstraintOrigin::Entity {
+                entity: entity_name.clone(),
+                row: entity.source_row,
+                expression: paired_expression(&entity.constraints, constraint, &paired),
+            });
+
             match &constraint.constraint_type {
                 ConstraintType::Apart => {
                     // Existing Apart handling...
@@ -37,29 +63,52 @@ pub fn apply_entity_constraints(compiler: &mut TimeConstraintCompiler) -> Result
                     let mut ordered_clocks = entity_clocks.clone();
                     ordered_clocks.sort_by_key(|c| c.instance);
 
-                    let time_in_minutes =
-                        constraint.time_unit.to_minutes(constraint.time_value) as i64;
+                    let (min_gap, max_gap) = constraint.gap_minutes();
 
                     // Create sequential constraints
                     for i in 0..ordered_clocks.len() - 1 {
                         let current = ordered_clocks[i];
                         let next = ordered_clocks[i + 1];
 
-                        // Store the constraint operation for later execution
-                        constraint_operations.push((
-                            current.variable,
-                            next.variable,
-                            time_in_minutes,
-                            format!(
-                                "{} must be ≥{}h{}m after {}",
+                        // Store the constraint operations for later execution
+                        push_gap(
+                            &mut constraint_operations,
+                            origin,
+                            (next.variable, current.variable),
+                            (min_gap, max_gap),
+                            0,
+                            &format!(
+                                "{} must be {} after {}",
                                 compiler.find_clock_name(next.variable).unwrap_or_default(),
-                                time_in_minutes / 60,
-                                time_in_minutes % 60,
+                                describe_gap(min_gap, max_gap),
                                 compiler
                                     .find_clock_name(current.variable)
                                     .unwrap_or_default()
                             ),
-                        ));
+                        );
+                    }
+
+                    // When the schedule repeats, the first instance of the next cycle must also
+                    // be far enough after the last (and, with an upper bound, close enough):
+                    // first + period - last ≥ spacing
+                    if compiler.config.cyclic {
+                        let period = compiler.config.horizon_days as i64 * MINUTES_PER_DAY;
+                        let first = ordered_clocks[0];
+                        let last = ordered_clocks[ordered_clocks.len() - 1];
+
+                        push_gap(
+                            &mut constraint_operations,
+                            origin,
+                            (first.variable, last.variable),
+                            (min_gap, max_gap),
+                            period,
+                            &format!(
+                                "{} (next cycle) must be {} after {}",
+                                compiler.find_clock_name(first.variable).unwrap_or_default(),
+                                describe_gap(min_gap, max_gap),
+                                compiler.find_clock_name(last.variable).unwrap_or_default()
+                            ),
+                        );
                     }
                 }
                 ConstraintType::Before | ConstraintType::After => {
@@ -70,113 +119,120 @@ pub fn apply_entity_constraints(compiler: &mut TimeConstraintCompiler) -> Result
                     };
 
                     // Get reference clocks based on the constraint reference
-                    let reference_clocks = match &constraint.reference {
-                        ConstraintReference::Unresolved(ref_str) => {
-                            match resolve_reference(compiler, ref_str) {
-                                Ok(clocks) => clocks,
-                                Err(e) => {
-                                    debug_error(
-                                        compiler,
-                                        "⚠️",
-                                        &format!(
-                                            "Could not resolve reference '{}': {}",
-                                            ref_str, e
+                    let reference_groups = reference_groups(compiler, constraint)?;
+                    let reference_clocks = all_clocks(&reference_groups);
+
+                    let (min_gap, max_gap) = constraint.gap_minutes();
+
+                    // "≥X before R" together with "≥Y after R" means each instance falls
+                    // outside the band around every R instance: between consecutive R, or before
+                    // the first / after the last. That's a disjunction per clock pair, added once
+                    // (from the Before side) with the strongest gap of each kind.
+                    let paired_gaps = paired
+                        .get(&reference_str)
+                        .filter(|_| either_side_part(constraint));
+                    if let Some(&(before_gap, after_gap)) = paired_gaps {
+                        if constraint.constraint_type == ConstraintType::Before
+                            && paired_done.insert(reference_str.clone())
+                        {
+                            for entity_var in entity_clocks.iter().map(|c| c.variable) {
+                                for &reference_var in &reference_clocks {
+                                    if entity_var == reference_var || !compiler.clocks_related(entity_var, reference_var, 1) {
+                                        continue;
+                                    }
+
+                                    let entity_clock_name =
+                                        compiler.find_clock_name(entity_var).unwrap_or_default();
+                                    let reference_clock_name =
+                                        compiler.find_clock_name(reference_var).unwrap_or_default();
+
+                                    let disjunction = Disjunction::either_side(
+                                        entity_var,
+                                        reference_var,
+                                        before_gap,
+                                        after_gap,
+                                        format!(
+                                            "{} must be ≥{}h{}m before or ≥{}h{}m after {}",
+                                            entity_clock_name,
+                                            before_gap / 60,
+                                            before_gap % 60,
+                                            after_gap / 60,
+                                            after_gap % 60,
+                                            reference_clock_name
                                         ),
                                     );
-                                    continue;
+                                    disjunctions.push((origin, disjunction));
                                 }
                             }
                         }
-                        ConstraintReference::WithinGroup => {
-                            debug_error(
-                                compiler,
-                                "⚠️",
-                                "WithinGroup reference should not be used here",
-                            );
-                            continue;
-                        }
-                    };
-
-                    let time_in_minutes =
-                        constraint.time_unit.to_minutes(constraint.time_value) as i64;
-
-                    // Check for potential conflicts that would create cycles
-                    if constraint.constraint_type == ConstraintType::Before &&
-                       reference_str == "food" && entity_name == "Antepsin" {
-                        // Special case for Antepsin before food constraint
-                        if compiler.debug {
-                            debug_print(
-                                compiler,
-                                "ℹ️",
-                                &format!("Special handling for {} before {} constraint", entity_name, reference_str)
-                            );
-                        }
-
-                        // We'll handle this differently to avoid cycles
-                        // Just log it and move on - we'll handle it in a second pass
-                        constraint_operations.push((
-                            clock_zones::Clock::variable(0),  // placeholder, special value
-                            clock_zones::Clock::variable(0),
-                            0,
-                            format!("Special constraint: {} before {}", entity_name, reference_str),
-                        ));
-                    } else if constraint.constraint_type == ConstraintType::After &&
-                              reference_str == "food" && entity_name == "Antepsin" {
-                        // Special case for Antepsin after food constraint - skip for now
-                        debug_print(
-                            compiler,
-                            "⚠️",
-                            &format!(
-                                "Skipping potentially conflicting constraint: {} after {}",
-                                entity_name, reference_str
-                            ),
-                        );
                     } else {
-                        // Normal case - collect constraints
-                        // Apply Before/After constraints by iterating through all entity clocks and reference clocks
+                        // Relate each entity instance to the reference instances it is paired with
                         let entity_vars: Vec<Variable> = entity_clocks.iter().map(|c| c.variable).collect();
                         for entity_var in entity_vars {
-                            for &reference_var in &reference_clocks {
-                                // Skip if same variable
-                                if entity_var == reference_var {
+                            let entity_clock_name = compiler.find_clock_name(entity_var).unwrap_or_default();
+                            // Against each "and" operand separately
+                            for reference_clocks in &reference_groups {
+                                let paired_clocks = paired_reference_clocks(
+                                    compiler,
+                                    entity_var,
+                                    reference_clocks,
+                                    constraint.pairing,
+                                );
+
+                                // The gap runs from the earlier clock to the later one
+                                let ordered = |reference_var| match constraint.constraint_type {
+                                    ConstraintType::Before => (reference_var, entity_var),
+                                    ConstraintType::After => (entity_var, reference_var),
+                                    _ => unreachable!(),
+                                };
+
+                                if constraint.pairing == Pairing::Nearest {
+                                    push_nearest_gap(
+                                        compiler,
+                                        &mut constraint_operations,
+                                        &mut disjunctions,
+                                        origin,
+                                        entity_var,
+                                        &paired_clocks,
+                                        ordered,
+                                        (min_gap, max_gap),
+                                        &constraint.constraint_type,
+                                    );
                                     continue;
                                 }
 
-                                let entity_clock_name = compiler.find_clock_name(entity_var).unwrap_or_default();
-                                let reference_clock_name = compiler.find_clock_name(reference_var).unwrap_or_default();
-
-                                match constraint.constraint_type {
-                                    ConstraintType::Before => {
-                                        // Entity must be before reference
-                                        constraint_operations.push((
-                                            entity_var,
-                                            reference_var,
-                                            time_in_minutes,
+                                // An instance without a matching reference instance can't be paired,
+                                // which is a conflict rather than nothing to enforce
+                                if constraint.pairing == Pairing::Matching && paired_clocks.is_empty() {
+                                    disjunctions.push((
+                                        origin,
+                                        Disjunction::new(
+                                            Vec::new(),
                                             format!(
-                                                "{} must be ≥{}h{}m before {}",
-                                                entity_clock_name,
-                                                time_in_minutes / 60,
-                                                time_in_minutes % 60,
-                                                reference_clock_name
+                                                "{} has no matching instance of {} that day",
+                                                entity_clock_name, reference_str
                                             ),
-                                        ));
-                                    }
-                                    ConstraintType::After => {
-                                        // Entity must be after reference
-                                        constraint_operations.push((
-                                            reference_var,
-                                            entity_var,
-                                            time_in_minutes,
-                                            format!(
-                                                "{} must be ≥{}h{}m after {}",
-                                                entity_clock_name,
-                                                time_in_minutes / 60,
-                                                time_in_minutes % 60,
-                                                reference_clock_name
-                                            ),
-                                        ));
-                                    }
-                                    _ => unreachable!(),
+                                        ),
+                                    ));
+                                    continue;
+                                }
+
+                                for reference_var in paired_clocks {
+                                    let reference_clock_name = compiler.find_clock_name(reference_var).unwrap_or_default();
+                                    push_gap(
+                                        &mut constraint_operations,
+                                        origin,
+                                        ordered(reference_var),
+                                        (min_gap, max_gap),
+                                        0,
+                                        &format!(
+                                            "{} must be {} {} {}",
+                                            entity_clock_name,
+                                            describe_gap(min_gap, max_gap),
+                                            constraint.constraint_type,
+                                            reference_clock_name
+                                        ),
+                                    );
                                 }
                             }
                         }
@@ -188,19 +244,14 @@ pub fn apply_entity_constraints(compiler: &mut TimeConstraintCompiler) -> Result
                             compiler,
                             "ℹ️",
                             &format!(
-                                "Applied {} constraint: {} must be ≥{}{}m {} {}",
+                                "Applied {} constraint: {} must be {} {} {}",
                                 match constraint.constraint_type {
                                     ConstraintType::Before => "before",
                                     ConstraintType::After => "after",
                                     _ => "related to",
                                 },
                                 entity_name,
-                                constraint.time_value,
-                                if constraint.time_unit == Hour {
-                                    "h"
-                                } else {
-                                    "m"
-                                },
+                                describe_gap(min_gap, max_gap),
                                 match constraint.constraint_type {
                                     ConstraintType::Before => "before",
                                     ConstraintType::After => "after",
@@ -211,47 +262,79 @@ pub fn apply_entity_constraints(compiler: &mut TimeConstraintCompiler) -> Result
                         );
                     }
                 }
-                ConstraintType::ApartFrom => {
-                    // Handle ApartFrom constraints - these are simpler than Before/After
-                    // as they enforce minimum separation regardless of order
-                    let reference_clocks = match &constraint.reference {
-                        ConstraintReference::Unresolved(reference_str) => {
-                            match resolve_reference(compiler, reference_str) {
-                                Ok(clocks) => clocks,
-                                Err(e) => {
-                                    debug_error(
-                                        compiler,
-                                        "⚠️",
-                                        &format!(
-                                            "Could not resolve reference '{}': {}",
-                                            reference_str, e
-                                        ),
+                ConstraintType::With => {
+                    let reference_groups = reference_groups(compiler, constraint)?;
+
+                    let (_, tolerance) = constraint.gap_minutes();
+                    let tolerance = tolerance.unwrap_or(0);
+
+                    // Each instance goes with one reference instance on its day, whichever is
+                    // nearest among its neighbours by order: a disjunction over those candidates,
+                    // each alternative holding the pair within the tolerance. No candidate leaves
+                    // no alternative, which is reported as a conflict rather than dropped. With
+                    // "and", one such instance of each operand.
+                    for entity_var in entity_clocks.iter().map(|c| c.variable) {
+                        let entity_name = compiler.find_clock_name(entity_var).unwrap_or_default();
+                        for reference_clocks in &reference_groups {
+                            let mut candidates = Vec::new();
+                            let alternatives: Vec<Vec<DiffConstraint>> = reference_clocks
+                                .iter()
+                                .filter(|&&reference_var| {
+                                    entity_var != reference_var
+                                        && compiler.clocks_related(entity_var, reference_var, 0)
+                                        && neighbours_by_order(&positions, entity_var, reference_var)
+                                })
+                                .map(|&reference_var| {
+                                    candidates.push(
+                                        compiler.find_clock_name(reference_var).unwrap_or_default(),
                                     );
-                                    continue;
+                                    vec![
+                                        DiffConstraint::diff_le(entity_var, reference_var, tolerance),
+                                        DiffConstraint::diff_ge(entity_var, reference_var, -tolerance),
+                                    ]
+                                })
+                                .collect();
+
+                            let description = format!(
+                                "{} must be within {}h{}m of one of {}",
+                                entity_name,
+                                tolerance / 60,
+                                tolerance % 60,
+                                if candidates.is_empty() {
+                                    "(no instances that day)".to_string()
+                                } else {
+                                    candidates.join(", ")
                                 }
-                            }
-                        }
-                        ConstraintReference::WithinGroup => {
-                            debug_error(
-                                compiler,
-                                "⚠️",
-                                "WithinGroup reference should not be used here",
                             );
-                            continue;
-                        }
-                    };
-
-                    let time_in_minutes =
-                        constraint.time_unit.to_minutes(constraint.time_value) as i64;
-
-                    // For "apart from", we note these constraints but don't directly add them
-                    // as they require disjunctive constraints (either A-B>=time OR B-A>=time)
-                    // This is difficult to express directly in a DBM
 
+                            if alternatives.len() == 1 {
+                                for constraint in alternatives.into_iter().flatten() {
+                                    constraint_operations.push((origin, constraint, description.clone()));
+                                }
+                            } else {
+                                disjunctions.push((origin, Disjunction::new(alternatives, description)));
+                            }
+                        }
+                    }
+                }
+                ConstraintType::ApartFrom => {
+                    // Handle ApartFrom constraints - these are simpler than Before/After
+                    // as they enforce minimum separation regardless of order, from every
+                    // instance of every operand alike
+                    let reference_clocks = all_clocks(&reference_groups(compiler, constraint)?);
+
+                    let (min_gap, max_gap) = constraint.gap_minutes();
+                    // A lower bound keeps clear of neighbouring days too; an upper bound only
+                    // relates clocks on the same day
+                    let span = if max_gap.is_some() { 0 } else { 1 };
+
+                    // "Apart from" is disjunctive (either A - B ≥ t or B - A ≥ t), which a
+                    // single DBM can't hold, so it becomes a disjunction for the federation search.
+                    // "Within t of B" alone (-t ≤ A - B ≤ t) is convex and goes in the zone.
                     for entity_var in entity_clocks.iter().map(|c| c.variable) {
                         for &reference_var in &reference_clocks {
-                            // Skip if same variable
-                            if entity_var == reference_var {
+                            // Skip if same variable, or on unrelated days
+                            if entity_var == reference_var || !compiler.clocks_related(entity_var, reference_var, span) {
                                 continue;
                             }
 
@@ -260,19 +343,41 @@ pub fn apply_entity_constraints(compiler: &mut TimeConstraintCompiler) -> Result
                                 compiler.find_clock_name(entity_var).unwrap_or_default();
                             let ref_name =
                                 compiler.find_clock_name(reference_var).unwrap_or_default();
+                            let description = format!(
+                                "{} must be {} apart from {}",
+                                entity_name,
+                                describe_gap(min_gap, max_gap),
+                                ref_name
+                            );
 
-                            if compiler.debug {
-                                debug_print(
-                                    compiler,
-                                    "ℹ️",
-                                    &format!(
-                                        "Adding apartFrom constraint: {} must be ≥{}h{}m apart from {}",
-                                        entity_name,
-                                        time_in_minutes / 60,
-                                        time_in_minutes % 60,
-                                        ref_name
-                                    ),
-                                );
+                            match max_gap {
+                                None => disjunctions.push((
+                                    origin,
+                                    Disjunction::apart(entity_var, reference_var, min_gap, description),
+                                )),
+                                Some(max_gap) if min_gap == 0 => {
+                                    for constraint in [
+                                        DiffConstraint::diff_le(entity_var, reference_var, max_gap),
+                                        DiffConstraint::diff_ge(entity_var, reference_var, -max_gap),
+                                    ] {
+                                        constraint_operations.push((origin, constraint, description.clone()));
+                                    }
+                                }
+                                Some(max_gap) => {
+                                    let side = |later, earlier| {
+                                        vec![
+                                            DiffConstraint::diff_ge(later, earlier, min_gap),
+                                            DiffConstraint::diff_le(later, earlier, max_gap),
+                                        ]
+                                    };
+                                    disjunctions.push((
+                                        origin,
+                                        Disjunction::new(
+                                            vec![side(entity_var, reference_var), side(reference_var, entity_var)],
+                                            description,
+                                        ),
+                                    ));
+                                }
                             }
                         }
                     }
@@ -281,143 +386,254 @@ pub fn apply_entity_constraints(compiler: &mut TimeConstraintCompiler) -> Result
         }
     }
 
-    // Apply the constraints we collected
-    for (from_var, to_var, time_minutes, description) in constraint_operations {
-        if description.starts_with("Special constraint:") {
-            // Special case for Antepsin before food
-            if description.contains("Special constraint: Antepsin before food") {
-                // Apply directly to all Antepsin and food entities
-                let antepsin_clocks: Vec<Variable> = compiler
-                    .clocks
-                    .values()
-                    .filter(|c| c.entity_name == "Antepsin")
-                    .map(|c| c.variable)
-                    .collect();
-
-                let food_clocks: Vec<Variable> = compiler
-                    .clocks
-                    .values()
-                    .filter(|c| {
-                        if let Some(entity) = compiler.entities.get(&c.entity_name) {
-                            entity.category == "food"
-                        } else {
-                            false
-                        }
-                    })
-                    .map(|c| c.variable)
-                    .collect();
-
-                for &antepsin_var in &antepsin_clocks {
-                    for &food_var in &food_clocks {
-                        let antepsin_name = compiler.find_clock_name(antepsin_var).unwrap_or_default();
-                        let food_name = compiler.find_clock_name(food_var).unwrap_or_default();
-
-                        compiler.add_constraint_safely(
-                            || Constraint::new_diff_ge(food_var, antepsin_var, 60),  // 1 hour = 60 minutes
-                            &format!("{} must be ≥1h0m before {}", antepsin_name, food_name),
-                        );
-                    }
-                }
-            }
-            continue;
-        }
+    // Apply the constraints we collected, grouped by the expression they came from
+    let groups: Vec<usize> = origins
+        .into_iter()
+        .map(|origin| compiler.new_group(origin))
+        .collect();
+
+    for (origin, constraint, description) in constraint_operations {
+        compiler.add_constraint(groups[origin], constraint, &description);
+    }
 
-        compiler.add_constraint_safely(
-            || Constraint::new_diff_ge(to_var, from_var, time_minutes),
-            &description,
-        );
+    for (origin, disjunction) in disjunctions {
+        compiler.add_disjunction(groups[origin], disjunction);
     }
 
     Ok(())
 }
 
-pub fn apply_test_constraint(
+// The reference clocks of a constraint, one set per "and" operand; an unknown name is an error
+fn reference_groups(
     compiler: &TimeConstraintCompiler,
-    test_zone: &mut clock_zones::Dbm<i64>,
-    entity_name: &str,
     constraint: &ConstraintExpression,
-) -> Result<(), String> {
-    // Convert time value to minutes
-    let time_in_minutes = constraint.time_unit.to_minutes(constraint.time_value) as i64;
+) -> Result<Vec<Vec<Variable>>, ScheduleError> {
+    match &constraint.reference {
+        ConstraintReference::Unresolved(reference_str) => resolve_reference(compiler, reference_str),
+        ConstraintReference::WithinGroup => {
+            debug_error(compiler, "⚠️", "WithinGroup reference should not be used here");
+            Ok(Vec::new())
+        }
+    }
+}
 
-    // Get all clocks for this entity
-    let entity_clocks: Vec<Variable> = compiler
+// The clocks of all the sets, once each
+fn all_clocks(groups: &[Vec<Variable>]) -> Vec<Variable> {
+    let mut clocks = Vec::new();
+    for &clock in groups.iter().flatten() {
+        if !clocks.contains(&clock) {
+            clocks.push(clock);
+        }
+    }
+    clocks
+}
+
+// Each clock's entity and its (index, count) among that entity's instances on its day; clocks
+// without a day have no position
+fn clock_positions(compiler: &TimeConstraintCompiler) -> HashMap<Variable, (&str, usize, usize)> {
+    let day_positions = compiler.day_positions();
+    compiler
         .clocks
-        .values()
-        .filter(|c| c.entity_name == entity_name)
-        .map(|c| c.variable)
+        .iter()
+        .filter_map(|(clock_id, info)| {
+            let &(index, count) = day_positions.get(clock_id)?;
+            Some((info.variable, (info.entity_name.as_str(), index, count)))
+        })
+        .collect()
+}
+
+// Whether a reference instance neighbours an entity instance by order, so that pairing each
+// instance with its nearest keeps both in order: the kth of the entity's n instances that day
+// goes with the kth of the reference entity's m, give or take the difference of the counts on
+// the side that has more. With as many of each, that's a single reference instance and no
+// search. An entity isn't paired with its own instances; a clock without a day neighbours any.
+fn neighbours_by_order(
+    positions: &HashMap<Variable, (&str, usize, usize)>,
+    entity_var: Variable,
+    reference_var: Variable,
+) -> bool {
+    match (positions.get(&entity_var), positions.get(&reference_var)) {
+        (Some(&(entity, k, n)), Some(&(reference, j, m))) => {
+            entity != reference
+                && k.saturating_sub(n.saturating_sub(m)) <= j
+                && j <= k + m.saturating_sub(n)
+        }
+        _ => true,
+    }
+}
+
+// Constrain `later - earlier` to the gap range (min, max), less `period` when the later clock
+// is in the next cycle
+pub fn push_gap(
+    operations: &mut Vec<(usize, DiffConstraint, String)>,
+    origin: usize,
+    (later, earlier): (Variable, Variable),
+    (min_gap, max_gap): (i64, Option<i64>),
+    period: i64,
+    description: &str,
+) {
+    operations.push((
+        origin,
+        DiffConstraint::diff_ge(later, earlier, min_gap - period),
+        description.to_string(),
+    ));
+    if let Some(max_gap) = max_gap {
+        operations.push((
+            origin,
+            DiffConstraint::diff_le(later, earlier, max_gap - period),
+            description.to_string(),
+        ));
+    }
+}
+
+// Constrain the gap between an entity instance and the nearest of its paired reference
+// instances on the constrained side ("≥2h after nearest food": the last food before it). One of
+// them must be within the gap, and, so that it is the nearest, none may fall between the
+// instance and the minimum gap: later - earlier ≥ min or later - earlier ≤ -1 for each.
+#[allow(clippy::too_many_arguments)]
+fn push_nearest_gap(
+    compiler: &TimeConstraintCompiler,
+    operations: &mut Vec<(usize, DiffConstraint, String)>,
+    disjunctions: &mut Vec<(usize, Disjunction)>,
+    origin: usize,
+    entity_var: Variable,
+    reference_clocks: &[Variable],
+    ordered: impl Fn(Variable) -> (Variable, Variable),
+    (min_gap, max_gap): (i64, Option<i64>),
+    constraint_type: &ConstraintType,
+) {
+    let entity_clock_name = compiler.find_clock_name(entity_var).unwrap_or_default();
+    let reference_names: Vec<String> = reference_clocks
+        .iter()
+        .map(|&var| compiler.find_clock_name(var).unwrap_or_default())
         .collect();
 
-    match &constraint.constraint_type {
-        ConstraintType::Apart => {
-            // Apply spacing constraint between instances of the same entity
-            if entity_clocks.len() <= 1 {
-                // No constraints needed for single instance
-                return Ok(());
+    let description = format!(
+        "{} must be {} {} the nearest of {}",
+        entity_clock_name,
+        describe_gap(min_gap, max_gap),
+        constraint_type,
+        if reference_names.is_empty() {
+            "(no instances that day)".to_string()
+        } else {
+            reference_names.join(", ")
+        }
+    );
+    let alternatives: Vec<Vec<DiffConstraint>> = reference_clocks
+        .iter()
+        .map(|&reference_var| {
+            let (later, earlier) = ordered(reference_var);
+            let mut alternative = vec![DiffConstraint::diff_ge(later, earlier, min_gap)];
+            if let Some(max_gap) = max_gap {
+                alternative.push(DiffConstraint::diff_le(later, earlier, max_gap));
             }
+            alternative
+        })
+        .collect();
 
-            for i in 0..entity_clocks.len() {
-                for j in i + 1..entity_clocks.len() {
-                    // Ensure minimum spacing in either direction
-                    test_zone.add_constraints([Constraint::new_diff_ge(
-                        entity_clocks[i],
-                        entity_clocks[j],
-                        time_in_minutes,
-                    )]);
-                    test_zone.add_constraints([Constraint::new_diff_ge(
-                        entity_clocks[j],
-                        entity_clocks[i],
-                        time_in_minutes,
-                    )]);
-                }
-            }
+    if alternatives.len() == 1 {
+        for constraint in alternatives.into_iter().flatten() {
+            operations.push((origin, constraint, description.clone()));
         }
+        return;
+    }
+    disjunctions.push((origin, Disjunction::new(alternatives, description)));
 
-        ConstraintType::Before | ConstraintType::After | ConstraintType::ApartFrom => {
-            // Get reference clocks based on the constraint reference
-            let reference_clocks = match &constraint.reference {
-                ConstraintReference::Unresolved(reference_str) => {
-                    resolve_reference(compiler, reference_str)?
-                }
-                ConstraintReference::WithinGroup => {
-                    return Err("WithinGroup reference should not be used here".to_string())
-                }
-            };
+    if min_gap == 0 {
+        return;
+    }
+    for (&reference_var, reference_name) in reference_clocks.iter().zip(&reference_names) {
+        let (later, earlier) = ordered(reference_var);
+        disjunctions.push((
+            origin,
+            Disjunction::new(
+                vec![
+                    vec![DiffConstraint::diff_ge(later, earlier, min_gap)],
+                    vec![DiffConstraint::diff_le(later, earlier, -1)],
+                ],
+                format!(
+                    "{} must not be within {} {} {}",
+                    reference_name,
+                    describe_gap(0, Some(min_gap - 1)),
+                    match constraint_type {
+                        ConstraintType::Before => "after",
+                        _ => "before",
+                    },
+                    entity_clock_name
+                ),
+            ),
+        ));
+    }
+}
 
-            for &entity_clock in &entity_clocks {
-                for &reference_clock in &reference_clocks {
-                    match constraint.constraint_type {
-                        ConstraintType::Before => {
-                            // Entity must be scheduled at least X minutes before reference
-                            test_zone.add_constraints([Constraint::new_diff_ge(
-                                reference_clock,
-                                entity_clock,
-                                time_in_minutes,
-                            )]);
-                        }
-                        ConstraintType::After => {
-                            // Entity must be scheduled at least X minutes after reference
-                            test_zone.add_constraints([Constraint::new_diff_ge(
-                                entity_clock,
-                                reference_clock,
-                                time_in_minutes,
-                            )]);
-                        }
-                        ConstraintType::ApartFrom => {
-                            // For testing, we can at least check one direction
-                            // In a real solution, we would need to handle disjunctive constraints
-                            test_zone.add_constraints([Constraint::new_diff_ge(
-                                entity_clock,
-                                reference_clock,
-                                time_in_minutes,
-                            )]);
-                        }
-                        _ => unreachable!(),
-                    }
-                }
-            }
+// "≥1h0m", "≤0h30m" or "1h0m-2h0m", for constraint descriptions
+pub fn describe_gap(min_gap: i64, max_gap: Option<i64>) -> String {
+    let hm = |minutes: i64| format!("{}h{}m", minutes / 60, minutes % 60);
+    match max_gap {
+        None => format!("≥{}", hm(min_gap)),
+        Some(max_gap) if min_gap == 0 => format!("≤{}", hm(max_gap)),
+        Some(max_gap) => format!("{}-{}", hm(min_gap), hm(max_gap)),
+    }
+}
+
+// References that an entity must be both before and after, mapped to the strongest
+// (before, after) gaps in minutes. These are encoded as "either side" disjunctions.
+pub fn paired_gaps(constraints: &[ConstraintExpression]) -> HashMap<String, (i64, i64)> {
+    let mut before: HashMap<String, i64> = HashMap::new();
+    let mut after: HashMap<String, i64> = HashMap::new();
+
+    for constraint in constraints.iter().filter(|c| either_side_part(c)) {
+        if let ConstraintReference::Unresolved(reference) = &constraint.reference {
+            let gaps = match constraint.constraint_type {
+                ConstraintType::Before => &mut before,
+                ConstraintType::After => &mut after,
+                _ => continue,
+            };
+            let minutes = constraint.time_unit.to_minutes(constraint.time_value) as i64;
+            let gap = gaps.entry(reference.clone()).or_insert(minutes);
+            *gap = (*gap).max(minutes);
         }
     }
 
-    Ok(())
+    before
+        .into_iter()
+        .filter_map(|(reference, before_gap)| {
+            after
+                .get(&reference)
+                .map(|&after_gap| (reference, (before_gap, after_gap)))
+        })
+        .collect()
+}
+
+// Only lower bounds against every reference instance pair up: "≤30m after food" places the
+// entity right after food, not clear of it, and "after nearest food" already picks a side
+fn either_side_part(constraint: &ConstraintExpression) -> bool {
+    constraint.bound == GapBound::AtLeast && constraint.pairing == Pairing::All
+}
+
+// A before/after pair is enforced as one disjunction, so its parts are reported together
+fn paired_expression(
+    constraints: &[ConstraintExpression],
+    constraint: &ConstraintExpression,
+    paired: &HashMap<String, (i64, i64)>,
+) -> String {
+    match &constraint.reference {
+        ConstraintReference::Unresolved(reference)
+            if paired.contains_key(reference) && either_side_part(constraint) =>
+        {
+            constraints
+                .iter()
+                .filter(|other| {
+                    matches!(
+                        other.constraint_type,
+                        ConstraintType::Before | ConstraintType::After
+                    ) && either_side_part(other)
+                        && matches!(&other.reference, ConstraintReference::Unresolved(r) if r == reference)
+                })
+                .map(|other| other.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        }
+        _ => constraint.to_string(),
+    }
 }
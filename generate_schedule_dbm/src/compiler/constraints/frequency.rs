@@ -1,9 +1,13 @@
-use crate::compiler::debugging::debug_print;
+use crate::compiler::constraints::daily_bounds::{format_minutes, MINUTES_PER_DAY};
+use crate::compiler::federation::DiffConstraint;
+use crate::compiler::infeasibility::ConstraintOrigin;
 use crate::compiler::time_constraint_compiler::TimeConstraintCompiler;
-use clock_zones::{Constraint, Variable, Zone};
+use crate::error::ScheduleError;
+use crate::types::frequency::Frequency;
+use clock_zones::{Clock, Variable};
 use std::collections::HashMap;
 
-pub fn apply_frequency_constraints(compiler: &mut TimeConstraintCompiler) -> Result<(), String> {
+pub fn apply_frequency_constraints(compiler: &mut TimeConstraintCompiler) -> Result<(), ScheduleError> {
     // Group clocks by entity
     let mut entity_clocks: HashMap<String, Vec<Variable>> = HashMap::new();
 
@@ -14,13 +18,23 @@ pub fn apply_frequency_constraints(compiler: &mut TimeConstraintCompiler) -> Res
             .push(clock_info.variable);
     }
 
-    // For each entity, ensure instance ordering and apply default spacing
+    // For each entity by name, ensure instance ordering and apply default spacing
+    let mut entity_clocks: Vec<(String, Vec<Variable>)> = entity_clocks.into_iter().collect();
+    entity_clocks.sort_by(|(a, _), (b, _)| a.cmp(b));
     for (entity_name, clocks) in entity_clocks {
         if clocks.len() <= 1 {
             continue; // No constraints needed for single instances
         }
 
         let entity = compiler.entities.get(&entity_name).unwrap();
+        let min_spacing_setting = entity.min_spacing;
+        let implied_spacing = entity.frequency.implied_spacing();
+        let origin = ConstraintOrigin::Frequency {
+            entity: entity_name.clone(),
+            row: entity.source_row,
+            frequency: entity.frequency.to_string(),
+        };
+        let group = compiler.new_group(origin);
 
         // Sort clocks by instance number
         let mut ordered_clocks: Vec<(usize, Variable)> = compiler
@@ -36,45 +50,103 @@ pub fn apply_frequency_constraints(compiler: &mut TimeConstraintCompiler) -> Res
             let (instance_i, current) = ordered_clocks[i];
             let (instance_j, next) = ordered_clocks[i + 1];
 
-            // Next instance must come after current instance
-            compiler
-                .zone
-                .add_constraint(Constraint::new_diff_gt(next, current, 0));
-
-            if compiler.debug {
-                debug_print(
-                    compiler,
-                    "🔢",
-                    &format!(
-                        "{}_{}  must be after  {}_{}",
-                        entity_name, instance_j, entity_name, instance_i
-                    ),
-                );
-            }
+            // Next instance must come after current instance (a whole minute later, as
+            // times are integral)
+            compiler.add_constraint(
+                group,
+                DiffConstraint::diff_ge(next, current, 1),
+                &format!(
+                    "{}_{}  must be after  {}_{}",
+                    entity_name, instance_j, entity_name, instance_i
+                ),
+            );
 
             // Apply minimum spacing only if specified
-            let min_spacing = if let Some(spacing) = entity.min_spacing {
+            let min_spacing = if let Some(spacing) = min_spacing_setting {
                 spacing as i64
             } else {
                 0 // no default enforced spacing
             };
+            // Interval frequencies ("every 90 minutes", "every 5 hours", "weekly") keep their
+            // interval
+            let min_spacing = min_spacing.max(implied_spacing.unwrap_or(0));
+
+            compiler.add_constraint(
+                group,
+                DiffConstraint::diff_ge(next, current, min_spacing),
+                &format!(
+                    "{}_{}  must be ≥{}h{}m after  {}_{}",
+                    entity_name,
+                    instance_j,
+                    min_spacing / 60,
+                    min_spacing % 60,
+                    entity_name,
+                    instance_i
+                ),
+            );
+        }
+    }
+
+    Ok(())
+}
 
-            compiler
-                .zone
-                .add_constraint(Constraint::new_diff_ge(next, current, min_spacing));
-
-            if compiler.debug {
-                let hours = min_spacing / 60;
-                let mins = min_spacing % 60;
-                debug_print(
-                    compiler,
-                    "↔️",
-                    &format!(
-                        "{}_{}  must be ≥{}h{}m after  {}_{}",
-                        entity_name, instance_j, hours, mins, entity_name, instance_i
-                    ),
-                );
+// Pin each instance of a fixed-time frequency ("at 08:00, 20:00 ±30m") to its time of day,
+// within the tolerance: instance k of a day falls within the kth time
+pub fn apply_fixed_times(compiler: &mut TimeConstraintCompiler) -> Result<(), ScheduleError> {
+    let mut entity_names: Vec<String> = compiler
+        .entities
+        .iter()
+        .filter(|(_, entity)| matches!(entity.frequency, Frequency::Custom(_)))
+        .map(|(name, _)| name.clone())
+        .collect();
+    entity_names.sort();
+    let days = compiler.config.horizon_days as i64;
+
+    for entity_name in entity_names {
+        let entity = &compiler.entities[&entity_name];
+        let times = match entity.frequency.fixed_times() {
+            Some(times) => {
+                times.map_err(|e| e.in_field(format!("Entity '{}' frequency", entity_name)))?
             }
+            None => continue,
+        };
+        let origin = ConstraintOrigin::Frequency {
+            entity: entity_name.clone(),
+            row: entity.source_row,
+            frequency: entity.frequency.to_string(),
+        };
+        let group = compiler.new_group(origin);
+
+        let mut clocks: Vec<(String, usize, Option<usize>, Variable)> = compiler
+            .clocks
+            .iter()
+            .filter(|(_, info)| info.entity_name == entity_name)
+            .map(|(id, info)| (id.clone(), info.instance, info.day, info.variable))
+            .collect();
+        clocks.sort_by_key(|&(_, instance, _, _)| instance);
+
+        for (clock_id, instance, day, variable) in clocks {
+            let (time, tolerance) = times[(instance - 1) % times.len()];
+            let offset = (day.unwrap_or(1) as i64 - 1) * MINUTES_PER_DAY;
+            let earliest = offset + (time - tolerance).max(0);
+            let latest = offset + (time + tolerance).min(MINUTES_PER_DAY);
+
+            let description = format!(
+                "{} must be at {}-{}",
+                clock_id,
+                format_minutes(earliest, days),
+                format_minutes(latest, days)
+            );
+            compiler.add_constraint(
+                group,
+                DiffConstraint::diff_ge(variable, Clock::ZERO, earliest),
+                &description,
+            );
+            compiler.add_constraint(
+                group,
+                DiffConstraint::diff_le(variable, Clock::ZERO, latest),
+                &description,
+            );
         }
     }
 
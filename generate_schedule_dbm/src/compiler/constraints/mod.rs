@@ -2,3 +2,4 @@ pub mod daily_bounds;
 pub mod entity;
 pub mod frequency;
 pub mod category;
+pub mod windows;
@@ -0,0 +1,131 @@
+use crate::compiler::constraints::daily_bounds::MINUTES_PER_DAY;
+use crate::compiler::federation::{DiffConstraint, Disjunction};
+use crate::compiler::infeasibility::ConstraintOrigin;
+use crate::compiler::time_constraint_compiler::TimeConstraintCompiler;
+use crate::error::ScheduleError;
+use crate::types::window::{check_windows, WindowSpec};
+use clock_zones::{Clock, Variable};
+
+pub fn apply_entity_windows(compiler: &mut TimeConstraintCompiler) -> Result<(), ScheduleError> {
+    let days = compiler.config.horizon_days as i64;
+
+    let mut entity_names: Vec<String> = compiler
+        .entities
+        .iter()
+        .filter(|(_, entity)| !entity.windows.is_empty())
+        .map(|(name, _)| name.clone())
+        .collect();
+    entity_names.sort();
+
+    let positions = compiler.day_positions();
+    for entity_name in entity_names {
+        let entity = &compiler.entities[&entity_name];
+        let row = entity.source_row;
+
+        // Windows in the order of the day, matching the order of the instances
+        let mut windows = entity.windows.clone();
+        windows.sort_by_key(|window| window.start());
+
+        let ranges: Vec<(i64, i64)> = windows
+            .iter()
+            .filter_map(|window| match *window {
+                WindowSpec::Range(start, end) => Some((start, end)),
+                WindowSpec::Anchor(_) => None,
+            })
+            .collect();
+        let anchors: Vec<i64> = windows
+            .iter()
+            .filter_map(|window| match *window {
+                WindowSpec::Anchor(anchor) => Some(anchor),
+                WindowSpec::Range(..) => None,
+            })
+            .collect();
+
+        check_windows(&windows, &entity.frequency)
+            .map_err(|e| e.in_field(format!("Entity '{}' windows", entity_name)))?;
+
+        let mut clocks: Vec<(String, usize, Option<usize>, Variable)> = compiler
+            .clocks
+            .iter()
+            .filter(|(_, info)| info.entity_name == entity_name)
+            .map(|(id, info)| (id.clone(), info.instance, info.day, info.variable))
+            .collect();
+        clocks.sort_by_key(|&(_, instance, _, _)| instance);
+
+        let group = compiler.new_group(ConstraintOrigin::EntityWindows {
+            entity: entity_name.clone(),
+            row,
+            windows: describe(&windows),
+        });
+
+        for (clock_id, _, day, variable) in clocks {
+            // Instance k of a day uses window k when there is one window per instance;
+            // otherwise the instance may use any range, and anchors are taken in order
+            let position = positions.get(&clock_id).copied();
+            let index_in_day = position.map(|(k, _)| k);
+            let one_per_instance = position.is_some_and(|(_, count)| count == windows.len());
+
+            let offsets: Vec<i64> = match day {
+                Some(day) => vec![(day as i64 - 1) * MINUTES_PER_DAY],
+                None => (0..days).map(|d| d * MINUTES_PER_DAY).collect(),
+            };
+
+            let (clock_ranges, anchor) = match (one_per_instance, index_in_day) {
+                (true, Some(k)) => match windows[k] {
+                    WindowSpec::Range(start, end) => (vec![(start, end)], None),
+                    WindowSpec::Anchor(anchor) => (Vec::new(), Some(anchor)),
+                },
+                (_, k) => (
+                    ranges.clone(),
+                    k.and_then(|k| anchors.get(k.min(anchors.len().saturating_sub(1))).copied()),
+                ),
+            };
+
+            // Anchors are targets for extraction, on the clock's own day
+            if let (Some(anchor), Some(&offset), Some(_)) = (anchor, offsets.first(), day) {
+                if let Some(info) = compiler.clocks.get_mut(&clock_id) {
+                    info.target = Some(offset + anchor);
+                }
+            }
+
+            // Ranges are hard: the clock falls within one of them, on one of its days
+            let alternatives: Vec<Vec<DiffConstraint>> = offsets
+                .iter()
+                .flat_map(|&offset| {
+                    clock_ranges.iter().map(move |&(start, end)| {
+                        vec![
+                            DiffConstraint::diff_ge(variable, Clock::ZERO, offset + start),
+                            DiffConstraint::diff_le(variable, Clock::ZERO, offset + end),
+                        ]
+                    })
+                })
+                .collect();
+
+            let used: Vec<WindowSpec> = clock_ranges
+                .iter()
+                .map(|&(start, end)| WindowSpec::Range(start, end))
+                .collect();
+            let description = format!("{} must fall within {}", clock_id, describe(&used));
+            match alternatives.len() {
+                0 => {}
+                1 => {
+                    for constraint in alternatives.into_iter().flatten() {
+                        compiler.add_constraint(group, constraint, &description);
+                    }
+                }
+                _ => compiler.add_disjunction(group, Disjunction::new(alternatives, description)),
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// "08:00, 18:00-20:00"
+fn describe(windows: &[WindowSpec]) -> String {
+    windows
+        .iter()
+        .map(|window| window.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
@@ -0,0 +1,290 @@
+use clock_zones::{AnyClock, Bound, Clock, Constraint, Dbm, Variable, Zone};
+
+// A difference constraint `left - right ≤ bound`.
+// clock_zones::Constraint is not Clone, so disjunctions keep the parts and rebuild the
+// constraint every time an alternative is tried in a different zone.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DiffConstraint {
+    pub left: Clock,
+    pub right: Clock,
+    pub bound: i64,
+}
+
+impl DiffConstraint {
+    // left - right ≥ value
+    pub fn diff_ge(left: impl AnyClock, right: impl AnyClock, value: i64) -> Self {
+        DiffConstraint {
+            left: right.as_clock(),
+            right: left.as_clock(),
+            bound: -value,
+        }
+    }
+
+    // left - right ≤ value
+    pub fn diff_le(left: impl AnyClock, right: impl AnyClock, value: i64) -> Self {
+        DiffConstraint {
+            left: left.as_clock(),
+            right: right.as_clock(),
+            bound: value,
+        }
+    }
+
+    pub fn to_constraint(self) -> Constraint<i64> {
+        Constraint::new_diff_le(self.left, self.right, self.bound)
+    }
+
+    // Add the constraint to a zone. clock_zones only re-canonicalizes through the two clocks
+    // involved and reports emptiness from the zero clock's diagonal, so a negative cycle that
+    // doesn't pass through the zero clock goes unnoticed; close the cycle check here and mark
+    // the zone empty ourselves.
+    pub fn apply_to(self, zone: &mut Dbm<i64>) {
+        let reverse = zone.get_bound(self.right, self.left).constant();
+        if reverse.is_some_and(|reverse| reverse + self.bound < 0) {
+            zone.add_constraint(Constraint::new_diff_le(Clock::ZERO, Clock::ZERO, -1));
+            return;
+        }
+        zone.add_constraint(self.to_constraint());
+    }
+}
+
+// At least one of the alternatives (each a conjunction of difference constraints) must hold.
+// A DBM is convex, so it can't hold "A - B ≥ t or B - A ≥ t" itself: instead the compiler
+// branches over the alternatives and keeps the resulting set of zones (a federation).
+#[derive(Debug, Clone)]
+pub struct Disjunction {
+    pub alternatives: Vec<Vec<DiffConstraint>>,
+    pub description: String,
+}
+
+impl Disjunction {
+    pub fn new(alternatives: Vec<Vec<DiffConstraint>>, description: String) -> Self {
+        Disjunction {
+            alternatives,
+            description,
+        }
+    }
+
+    // The two orderings of "a and b are at least `gap` apart"
+    pub fn apart(a: impl AnyClock, b: impl AnyClock, gap: i64, description: String) -> Self {
+        Disjunction::new(
+            vec![
+                vec![DiffConstraint::diff_ge(b, a, gap)],
+                vec![DiffConstraint::diff_ge(a, b, gap)],
+            ],
+            description,
+        )
+    }
+
+    // `entity` is at least `before` ahead of `reference`, or at least `after` behind it
+    pub fn either_side(
+        entity: impl AnyClock,
+        reference: impl AnyClock,
+        before: i64,
+        after: i64,
+        description: String,
+    ) -> Self {
+        Disjunction::new(
+            vec![
+                vec![DiffConstraint::diff_ge(reference, entity, before)],
+                vec![DiffConstraint::diff_ge(entity, reference, after)],
+            ],
+            description,
+        )
+    }
+}
+
+pub fn apply_alternative(zone: &Dbm<i64>, alternative: &[DiffConstraint]) -> Dbm<i64> {
+    let mut branch = zone.clone();
+    for constraint in alternative {
+        constraint.apply_to(&mut branch);
+    }
+    branch
+}
+
+fn is_entailed(zone: &Dbm<i64>, alternative: &[DiffConstraint]) -> bool {
+    alternative
+        .iter()
+        .all(|constraint| zone.is_satisfied(constraint.to_constraint()))
+}
+
+// How much work the disjunction search may do, counted in zone operations (an alternative
+// applied to a zone or checked against it). Without a limit, orderings that fail deep in the
+// search can take exponential time. Searches sharing a budget draw it down together.
+#[derive(Debug, Clone, Copy)]
+pub struct SearchBudget {
+    remaining: usize,
+    spent: usize,
+}
+
+impl SearchBudget {
+    pub fn new(steps: usize) -> Self {
+        SearchBudget {
+            remaining: steps,
+            spent: 0,
+        }
+    }
+
+    pub fn spent(&self) -> usize {
+        self.spent
+    }
+
+    pub fn is_exhausted(&self) -> bool {
+        self.remaining == 0
+    }
+
+    fn spend(&mut self, steps: usize) {
+        let steps = steps.min(self.remaining);
+        self.remaining -= steps;
+        self.spent += steps;
+    }
+}
+
+// The budget ran out before the search found any zone, so whether the disjunctions can hold
+// is unknown
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SearchExhausted {
+    pub steps: usize,
+}
+
+// Search the orderings allowed by the disjunctions, returning up to `limit` non-empty zones.
+// Every point of every returned zone satisfies the base zone and all of the disjunctions.
+//
+// The search is deterministic: disjunctions are taken in the order of the clocks they relate
+// (not the order they were compiled in), and the one with the fewest feasible alternatives is
+// branched on first. If the budget runs out, the zones found so far are returned, or an error
+// if there are none.
+pub fn search(
+    zone: &Dbm<i64>,
+    disjunctions: &[Disjunction],
+    limit: usize,
+    budget: &mut SearchBudget,
+) -> Result<Vec<Dbm<i64>>, SearchExhausted> {
+    let mut federation = Vec::new();
+    if zone.is_empty() || limit == 0 {
+        return Ok(federation);
+    }
+
+    let mut pending: Vec<&Disjunction> = disjunctions.iter().collect();
+    pending.sort_by_cached_key(|disjunction| {
+        disjunction
+            .alternatives
+            .iter()
+            .map(|alternative| {
+                alternative
+                    .iter()
+                    .map(|c| (clock_index(c.left), clock_index(c.right), c.bound))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>()
+    });
+    explore(zone.clone(), pending, limit, budget, &mut federation);
+
+    if federation.is_empty() && budget.is_exhausted() {
+        return Err(SearchExhausted {
+            steps: budget.spent(),
+        });
+    }
+    Ok(federation)
+}
+
+pub fn first_zone(
+    zone: &Dbm<i64>,
+    disjunctions: &[Disjunction],
+    budget: &mut SearchBudget,
+) -> Result<Option<Dbm<i64>>, SearchExhausted> {
+    Ok(search(zone, disjunctions, 1, budget)?.into_iter().next())
+}
+
+// The position of a clock in a zone, the zero clock being 0
+fn clock_index(clock: Clock) -> usize {
+    Variable::try_from(clock).map_or(0, |variable| variable.number() + 1)
+}
+
+fn explore(
+    mut zone: Dbm<i64>,
+    mut pending: Vec<&Disjunction>,
+    limit: usize,
+    budget: &mut SearchBudget,
+    federation: &mut Vec<Dbm<i64>>,
+) {
+    // Propagate: drop disjunctions the zone already entails, commit to the only feasible
+    // alternative where there is one, and give up on this branch if none is feasible
+    let branch_on = loop {
+        let mut changed = false;
+        let mut candidate: Option<(usize, Vec<Dbm<i64>>)> = None;
+        let mut index = 0;
+
+        while index < pending.len() {
+            if budget.is_exhausted() {
+                return;
+            }
+            let disjunction = pending[index];
+            budget.spend(disjunction.alternatives.len());
+
+            if disjunction
+                .alternatives
+                .iter()
+                .any(|alternative| is_entailed(&zone, alternative))
+            {
+                pending.remove(index);
+                continue;
+            }
+
+            let branches: Vec<(&Vec<DiffConstraint>, Dbm<i64>)> = disjunction
+                .alternatives
+                .iter()
+                .map(|alternative| (alternative, apply_alternative(&zone, alternative)))
+                .filter(|(_, branch)| !branch.is_empty())
+                .collect();
+
+            // An alternative whose branch implies another's is covered by that branch: any
+            // ordering extending it extends the other too. Keep the first of equal branches.
+            let feasible: Vec<Dbm<i64>> = branches
+                .iter()
+                .enumerate()
+                .filter(|&(i, (_, branch))| {
+                    !branches.iter().enumerate().any(|(j, (other, other_branch))| {
+                        j != i
+                            && is_entailed(branch, other)
+                            && (j < i || !is_entailed(other_branch, branches[i].0))
+                    })
+                })
+                .map(|(_, (_, branch))| branch.clone())
+                .collect();
+
+            match feasible.len() {
+                0 => return,
+                1 => {
+                    zone = feasible.into_iter().next().unwrap();
+                    pending.remove(index);
+                    changed = true;
+                }
+                n => {
+                    // Remember the most constrained disjunction to branch on, the first one
+                    // in order among equals
+                    if candidate.as_ref().is_none_or(|(_, b)| n < b.len()) {
+                        candidate = Some((index, feasible));
+                    }
+                    index += 1;
+                }
+            }
+        }
+
+        if !changed {
+            break candidate;
+        }
+    };
+
+    match branch_on {
+        None => federation.push(zone),
+        Some((index, branches)) => {
+            pending.remove(index);
+            for branch in branches {
+                if federation.len() >= limit || budget.is_exhausted() {
+                    return;
+                }
+                explore(branch, pending.clone(), limit, budget, federation);
+            }
+        }
+    }
+}
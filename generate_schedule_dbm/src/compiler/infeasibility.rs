@@ -0,0 +1,194 @@
+use crate::compiler::config::format_window;
+use crate::compiler::federation::{self, DiffConstraint, Disjunction, SearchBudget};
+use clock_zones::{Dbm, Zone};
+use std::fmt;
+
+// Where a group of compiled constraints came from, so a conflict can be reported in the
+// user's terms rather than as clock differences
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConstraintOrigin {
+    // The schedulable part of the day, in minutes from midnight
+    DayWindow { start: i64, end: i64 },
+    // A category's or an entity's own window, replacing the day window for it
+    Window {
+        target: String,
+        start: i64,
+        end: i64,
+    },
+    // Instance ordering and minimum spacing implied by an entity's frequency
+    Frequency {
+        entity: String,
+        row: Option<usize>,
+        frequency: String,
+    },
+    // An entity's own windows (anchors and ranges)
+    EntityWindows {
+        entity: String,
+        row: Option<usize>,
+        windows: String,
+    },
+    // One constraint expression from an entity's Constraints column
+    Entity {
+        entity: String,
+        row: Option<usize>,
+        expression: String,
+    },
+    // One category-level constraint
+    Category { expression: String },
+}
+
+impl fmt::Display for ConstraintOrigin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConstraintOrigin::DayWindow { start, end } => {
+                write!(f, "day window {}", format_window(*start, *end))
+            }
+            ConstraintOrigin::Window { target, start, end } => {
+                write!(f, "{} window {}", target, format_window(*start, *end))
+            }
+            ConstraintOrigin::Frequency {
+                entity,
+                row,
+                frequency,
+            } => {
+                write_entity(f, entity, *row)?;
+                write!(f, ": {}", frequency)
+            }
+            ConstraintOrigin::EntityWindows {
+                entity,
+                row,
+                windows,
+            } => {
+                write_entity(f, entity, *row)?;
+                write!(f, ": windows {}", windows)
+            }
+            ConstraintOrigin::Entity {
+                entity,
+                row,
+                expression,
+            } => {
+                write_entity(f, entity, *row)?;
+                write!(f, ": {}", expression)
+            }
+            ConstraintOrigin::Category { expression } => {
+                write!(f, "category constraint: {}", expression)
+            }
+        }
+    }
+}
+
+fn write_entity(f: &mut fmt::Formatter<'_>, entity: &str, row: Option<usize>) -> fmt::Result {
+    match row {
+        Some(row) => write!(f, "{} (row {})", entity, row),
+        None => write!(f, "{}", entity),
+    }
+}
+
+// The clock constraints compiled from one source constraint
+#[derive(Debug, Clone)]
+pub struct ConstraintGroup {
+    pub origin: ConstraintOrigin,
+    pub constraints: Vec<DiffConstraint>,
+    pub disjunctions: Vec<Disjunction>,
+}
+
+impl ConstraintGroup {
+    pub fn new(origin: ConstraintOrigin) -> Self {
+        ConstraintGroup {
+            origin,
+            constraints: Vec::new(),
+            disjunctions: Vec::new(),
+        }
+    }
+}
+
+// A minimal set of source constraints that can't all hold: dropping any one of them makes
+// the rest satisfiable
+#[derive(Debug, Clone)]
+pub struct Infeasibility {
+    pub conflict: Vec<ConstraintOrigin>,
+    // False if the search budget ran out while shrinking the set: the constraints still
+    // conflict, but some of them may not be needed for it
+    pub minimal: bool,
+}
+
+impl fmt::Display for Infeasibility {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.conflict.is_empty() {
+            return write!(f, "Schedule is not feasible with the given constraints");
+        }
+
+        write!(f, "Schedule is not feasible; these constraints conflict:")?;
+        for origin in &self.conflict {
+            write!(f, "\n  - {}", origin)?;
+        }
+        if !self.minimal {
+            write!(f, "\n(the search gave up before narrowing this down further)")?;
+        }
+        Ok(())
+    }
+}
+
+// Whether the given groups are satisfiable together, disjunctions included, or None if the
+// search budget ran out before it could tell
+pub fn is_feasible(
+    clock_count: usize,
+    groups: &[&ConstraintGroup],
+    budget: &mut SearchBudget,
+) -> Option<bool> {
+    let mut zone: Dbm<i64> = Dbm::new_unconstrained(clock_count);
+    for group in groups {
+        for constraint in &group.constraints {
+            constraint.apply_to(&mut zone);
+        }
+    }
+    if zone.is_empty() {
+        return Some(false);
+    }
+
+    let disjunctions: Vec<Disjunction> = groups
+        .iter()
+        .flat_map(|group| group.disjunctions.iter().cloned())
+        .collect();
+    match federation::first_zone(&zone, &disjunctions, budget) {
+        Ok(zone) => Some(zone.is_some()),
+        Err(_) => None,
+    }
+}
+
+// Shrink an infeasible set of groups to a minimal conflicting subset (deletion filter): drop
+// each group in turn and keep it out if the rest is still infeasible. Every check draws on the
+// one budget; once it runs out, the groups not yet dropped are kept, so the result is still
+// a conflict, just not necessarily a minimal one.
+pub fn minimal_conflict(
+    clock_count: usize,
+    groups: &[ConstraintGroup],
+    budget: &mut SearchBudget,
+) -> Infeasibility {
+    let mut kept: Vec<&ConstraintGroup> = groups.iter().collect();
+
+    if is_feasible(clock_count, &kept, budget) != Some(false) {
+        return Infeasibility {
+            conflict: Vec::new(),
+            minimal: !budget.is_exhausted(),
+        };
+    }
+
+    let mut index = 0;
+    while index < kept.len() {
+        let mut candidate = kept.clone();
+        candidate.remove(index);
+
+        if is_feasible(clock_count, &candidate, budget) == Some(false) {
+            kept = candidate;
+        } else {
+            // Needed for the conflict, or the budget ran out before it was known not to be
+            index += 1;
+        }
+    }
+
+    Infeasibility {
+        conflict: kept.into_iter().map(|group| group.origin.clone()).collect(),
+        minimal: !budget.is_exhausted(),
+    }
+}
@@ -1,10 +1,16 @@
 // Compiler module exports
+pub mod alternatives;
 pub mod clock_info;
+pub mod config;
 pub mod constraints;
 pub mod debugging;
+pub mod federation;
+pub mod infeasibility;
 pub mod reference_resolution;
 pub mod schedule_extraction;
+pub mod slack;
 pub mod time_constraint_compiler;
+pub mod verification;
 
 // Re-export the primary struct
 pub use time_constraint_compiler::TimeConstraintCompiler;
@@ -1,79 +1,117 @@
 use crate::compiler::time_constraint_compiler::TimeConstraintCompiler;
+use crate::error::ScheduleError;
+use crate::types::constraints::Pairing;
 use clock_zones::Variable;
+use schedule_grammar::Reference;
 
-// Enhanced resolve_reference method to handle "or" expressions without sorting
+// Resolve a reference expression to the clocks it names, as the sets of clocks a constraint
+// must hold against: one set per "and" operand, so "with food and med" goes with a food and
+// with a med, while "with food or snack" goes with either. Unknown names are errors.
 pub fn resolve_reference(
     compiler: &TimeConstraintCompiler,
     reference_str: &str,
-) -> Result<Vec<Variable>, String> {
-    // Check if the reference contains " or "
-    if reference_str.contains(" or ") {
-        let parts: Vec<&str> = reference_str.split(" or ").collect();
-        let mut all_clocks = Vec::new();
-
-        // Resolve each part separately and combine the results
-        for part in parts {
-            match resolve_single_reference(compiler, part.trim()) {
-                Ok(clocks) => {
-                    // Add only unique clocks
-                    for clock in clocks {
-                        if !all_clocks.contains(&clock) {
-                            all_clocks.push(clock);
-                        }
-                    }
-                }
-                Err(_) => (), // Ignore errors for individual parts in an OR expression
-            }
-        }
-
-        if all_clocks.is_empty() {
-            return Err(format!(
-                "Could not resolve any part of reference '{}'",
-                reference_str
-            ));
-        }
-
-        return Ok(all_clocks);
-    }
+) -> Result<Vec<Vec<Variable>>, ScheduleError> {
+    Reference::parse(reference_str)?.resolve(&mut |name| resolve_single_reference(compiler, name))
+}
 
-    // If no "or", just resolve as a single reference
-    resolve_single_reference(compiler, reference_str)
+// Entity and category names are matched regardless of case, through this key ("Food" is the
+// category food)
+pub fn name_key(name: &str) -> String {
+    name.to_lowercase()
 }
 
-// Helper method to resolve a single reference (no OR)
+// Resolve one entity or category name
 pub fn resolve_single_reference(
     compiler: &TimeConstraintCompiler,
     reference_str: &str,
-) -> Result<Vec<Variable>, String> {
-    // First try to find it as an entity (exact match)
-    let entity_clocks: Vec<Variable> = compiler
+) -> Result<Vec<Variable>, ScheduleError> {
+    let key = name_key(reference_str);
+
+    // First try to find it as an entity. Clocks come in clock order (by entity name, then
+    // instance), so the constraints on them do too
+    let mut entity_clocks: Vec<Variable> = compiler
         .clocks
         .values()
-        .filter(|c| c.entity_name.to_lowercase() == reference_str.to_lowercase())
+        .filter(|c| name_key(&c.entity_name) == key)
         .map(|c| c.variable)
         .collect();
+    entity_clocks.sort_by_key(|var| var.number());
 
     if !entity_clocks.is_empty() {
         return Ok(entity_clocks);
     }
 
-    // If not found as entity, try as a category
-    if let Some(entities) = compiler.categories.get(reference_str) {
-        let category_clocks: Vec<Variable> = compiler
-            .clocks
-            .values()
-            .filter(|c| entities.contains(&c.entity_name))
-            .map(|c| c.variable)
-            .collect();
+    // If not found as entity, try as a category (every category spelled that way: "Med" and
+    // "med" in different rows are the same category)
+    let category_entities: Vec<&String> = compiler
+        .categories
+        .iter()
+        .filter(|(category, _)| name_key(category) == key)
+        .flat_map(|(_, entities)| entities)
+        .collect();
+    let mut category_clocks: Vec<Variable> = compiler
+        .clocks
+        .values()
+        .filter(|c| category_entities.contains(&&c.entity_name))
+        .map(|c| c.variable)
+        .collect();
+    category_clocks.sort_by_key(|var| var.number());
+
+    if !category_clocks.is_empty() {
+        return Ok(category_clocks);
+    }
+
+    // A known entity or category may have no instances in the horizon ("Sat/Sun" on a single
+    // Monday), which leaves nothing to relate to rather than an unknown name
+    let known_entity = compiler.entities.keys().any(|name| name_key(name) == key);
+    if known_entity || !category_entities.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    // If still not found, return an error
+    Err(ScheduleError::UnresolvedReference {
+        reference: reference_str.to_string(),
+    })
+}
 
-        if !category_clocks.is_empty() {
-            return Ok(category_clocks);
+// The reference clocks an instance is held against under the pairing: those on the same day,
+// or for a matching pairing only those with the same index in the day (for each entity of a
+// category, its instance with that index)
+pub fn paired_reference_clocks(
+    compiler: &TimeConstraintCompiler,
+    entity_var: Variable,
+    reference_clocks: &[Variable],
+    pairing: Pairing,
+) -> Vec<Variable> {
+    let same_day = reference_clocks
+        .iter()
+        .copied()
+        .filter(|&reference_var| {
+            reference_var != entity_var && compiler.clocks_related(entity_var, reference_var, 0)
+        });
+
+    match pairing {
+        Pairing::All | Pairing::Nearest => same_day.collect(),
+        Pairing::Matching => {
+            let position = instance_in_day(compiler, entity_var);
+            same_day
+                .filter(|&reference_var| instance_in_day(compiler, reference_var) == position)
+                .collect()
         }
     }
+}
 
-    // If still not found, return an error
-    Err(format!(
-        "Could not resolve reference '{}' - not found as entity or category",
-        reference_str
-    ))
+// The day of a clock and its index (from 1) among the instances of its entity on that day
+fn instance_in_day(compiler: &TimeConstraintCompiler, var: Variable) -> Option<(Option<usize>, usize)> {
+    let info = compiler.clocks.values().find(|info| info.variable == var)?;
+    let index = compiler
+        .clocks
+        .values()
+        .filter(|other| {
+            other.entity_name == info.entity_name
+                && other.day == info.day
+                && other.instance <= info.instance
+        })
+        .count();
+    Some((info.day, index))
 }
@@ -1,60 +1,55 @@
+use crate::compiler::constraints::daily_bounds::format_minutes;
 use crate::compiler::time_constraint_compiler::TimeConstraintCompiler;
-use clock_zones::Zone;
+use crate::types::frequency::Frequency;
 use std::collections::HashMap;
 
-// Extract a concrete schedule from the zone
-pub fn extract_schedule(compiler: &TimeConstraintCompiler) -> Result<HashMap<String, i32>, String> {
-    if compiler.zone.is_empty() {
-        return Err("Cannot extract schedule from empty zone".to_string());
-    }
-
-    let mut schedule = HashMap::new();
-
-    // For each clock, get a feasible time
-    for (clock_id, clock_info) in &compiler.clocks {
-        // Get the lower and upper bounds for this clock (in minutes)
-        let lower = compiler
-            .zone
-            .get_lower_bound(clock_info.variable)
-            .unwrap_or(0);
-        let upper = compiler
-            .zone
-            .get_upper_bound(clock_info.variable)
-            .unwrap_or(1440);
-
-        // Choose a time in the middle of the feasible range
-        let time_in_minutes = ((lower + upper) / 2) as i32;
-        schedule.insert(clock_id.clone(), time_in_minutes);
-    }
-
-    Ok(schedule)
-}
-
 // Format the schedule into a human-readable format
 pub fn format_schedule(
     compiler: &TimeConstraintCompiler,
     schedule: &HashMap<String, i32>,
 ) -> String {
     let mut result = String::new();
-    result.push_str("Daily Schedule:\n");
+    let days = compiler.config.horizon_days as i64;
 
-    // Convert minutes to HH:MM format and sort by time
-    let mut time_entries: Vec<(String, String)> = schedule
+    // Sort by time
+    let mut time_entries: Vec<(i32, &String)> = schedule
         .iter()
-        .map(|(clock_id, &minutes)| {
-            let hours = minutes / 60;
-            let mins = minutes % 60;
-            let time_str = format!("{:02}:{:02}", hours, mins);
-            (time_str.clone(), format!("{}: {}", clock_id, time_str))
-        })
+        .map(|(clock_id, &minutes)| (minutes, clock_id))
         .collect();
+    time_entries.sort();
 
-    // Sort by time
-    time_entries.sort_by(|a, b| a.0.cmp(&b.0));
-
-    // Add to result
-    for (_, entry) in time_entries {
-        result.push_str(&format!("  {}\n", entry));
+    if days == 1 {
+        result.push_str("Daily Schedule:\n");
+        for (minutes, clock_id) in time_entries {
+            result.push_str(&format!("  {}: {}\n", clock_id, format_minutes(minutes as i64, 1)));
+        }
+    } else {
+        // One section per day, with times of day. A clock allocated to a day stays under it
+        // even at the day's 24:00 boundary.
+        result.push_str(&format!("Schedule ({} days):\n", days));
+        let mut day_entries: Vec<(i64, i32, &String)> = time_entries
+            .into_iter()
+            .map(|(minutes, clock_id)| {
+                let day = compiler
+                    .clocks
+                    .get(clock_id)
+                    .and_then(|info| info.day)
+                    .map(|day| day as i64)
+                    .unwrap_or_else(|| (minutes as i64).min(days * 1440 - 1).div_euclid(1440) + 1);
+                (day, minutes, clock_id)
+            })
+            .collect();
+        day_entries.sort();
+
+        let mut current_day = 0;
+        for (day, minutes, clock_id) in day_entries {
+            if day != current_day {
+                result.push_str(&format!("  Day {}:\n", day));
+                current_day = day;
+            }
+            let time_of_day = minutes as i64 - (day - 1) * 1440;
+            result.push_str(&format!("    {}: {}\n", clock_id, format_minutes(time_of_day, 1)));
+        }
     }
 
     // Group by entity
@@ -84,9 +79,11 @@ pub fn format_schedule(
         sorted_times.sort_by_key(|&(_, minutes)| minutes);
 
         for (clock_id, minutes) in sorted_times {
-            let hours = minutes / 60;
-            let mins = minutes % 60;
-            result.push_str(&format!("    {}: {:02}:{:02}", clock_id, hours, mins));
+            result.push_str(&format!(
+                "    {}: {}",
+                clock_id,
+                format_minutes(minutes as i64, days)
+            ));
 
             // Add amount information if available
             if let Some(entity) = compiler.entities.get(&entity_name) {
@@ -109,5 +106,31 @@ pub fn format_schedule(
         }
     }
 
+    // As-needed entities have no clocks: list their maximum and constraints, the rules for
+    // when a dose may be taken
+    let mut as_needed: Vec<(&String, u32)> = compiler
+        .entities
+        .iter()
+        .filter_map(|(entity_name, entity)| match entity.frequency {
+            Frequency::AsNeeded { max_per_day } => Some((entity_name, max_per_day)),
+            _ => None,
+        })
+        .collect();
+    as_needed.sort();
+    if !as_needed.is_empty() {
+        result.push_str("\nAs Needed:\n");
+        for (entity_name, max_per_day) in as_needed {
+            let entity = &compiler.entities[entity_name];
+            let mut rules = vec![format!("up to {} a day", max_per_day)];
+            rules.extend(entity.constraints.iter().map(|constraint| constraint.to_string()));
+            result.push_str(&format!(
+                "  {} ({}): {}\n",
+                entity_name,
+                entity.category,
+                rules.join(", ")
+            ));
+        }
+    }
+
     result
 }
@@ -0,0 +1,224 @@
+use crate::compiler::constraints::daily_bounds::format_minutes;
+use crate::compiler::federation::DiffConstraint;
+use crate::compiler::time_constraint_compiler::TimeConstraintCompiler;
+use crate::compiler::verification::{self, hm};
+use crate::error::ScheduleError;
+use crate::extractor::schedule_extractor::ScheduleExtractor;
+use clock_zones::{AnyClock, Bound, Clock, Dbm, Zone};
+use serde::Serialize;
+use std::collections::HashMap;
+
+// How far one instance of a schedule can move, in minutes from the start of the horizon
+#[derive(Debug, Clone, PartialEq)]
+pub struct Slack {
+    pub clock: String,
+    pub entity: String,
+    pub time: i64,
+    // The range it can move in while every other instance keeps its time
+    pub earliest: i64,
+    pub latest: i64,
+    // The range the zone allows it when the other instances may move as well
+    pub free_earliest: i64,
+    pub free_latest: i64,
+}
+
+impl Slack {
+    // Minutes it can move earlier, the others fixed
+    pub fn earlier(&self) -> i64 {
+        self.time - self.earliest
+    }
+
+    // Minutes it can move later, the others fixed
+    pub fn later(&self) -> i64 {
+        self.latest - self.time
+    }
+
+    // Whether it can't move at all without moving something else
+    pub fn is_rigid(&self) -> bool {
+        self.earliest == self.latest
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlackFormat {
+    Table,
+    Json,
+}
+
+// The slack of every instance of a schedule (clock id -> minutes), in time order.
+//
+// The ranges come from the zone of the schedule's ordering: the compiled constraints plus, for
+// each disjunction, the alternative the schedule satisfies. With the other clocks fixed, a
+// clock's range is where every difference bound of that zone with them still holds; with the
+// others free it is the clock's bounds in the zone. Every time within a range keeps the schedule
+// feasible. Moving an instance past another (a different ordering) may also be allowed, but
+// isn't counted: the other times would have to be checked again.
+pub fn slack(
+    compiler: &TimeConstraintCompiler,
+    schedule: &HashMap<String, i32>,
+) -> Result<Vec<Slack>, ScheduleError> {
+    // Also reports unknown or missing clocks
+    let violations = verification::verify(compiler, schedule)?;
+    if let Some(violation) = violations.first() {
+        return Err(ScheduleError::Input(format!(
+            "The schedule has no slack, it already violates {}",
+            violation
+        )));
+    }
+
+    let times: HashMap<Clock, i64> = compiler
+        .clocks
+        .iter()
+        .map(|(clock_id, info)| (info.variable.as_clock(), schedule[clock_id] as i64))
+        .chain(std::iter::once((Clock::ZERO, 0)))
+        .collect();
+    let zone = ordering_zone(compiler, &times);
+    let extractor = ScheduleExtractor::new(&zone, &compiler.clocks);
+
+    let mut report: Vec<Slack> = compiler
+        .clocks
+        .iter()
+        .map(|(clock_id, info)| {
+            let clock = info.variable.as_clock();
+            let free = extractor.get_bounds(clock);
+            let time = times[&clock];
+
+            // clock - other ≤ bound and other - clock ≤ bound, with the other's time fixed
+            let (mut earliest, mut latest) = (free.lb, free.ub);
+            for (&other, &other_time) in &times {
+                if other == clock {
+                    continue;
+                }
+                if let Some(bound) = zone.get_bound(clock, other).constant() {
+                    latest = latest.min(other_time + bound);
+                }
+                if let Some(bound) = zone.get_bound(other, clock).constant() {
+                    earliest = earliest.max(other_time - bound);
+                }
+            }
+
+            Slack {
+                clock: clock_id.clone(),
+                entity: info.entity_name.clone(),
+                time,
+                earliest,
+                latest,
+                free_earliest: free.lb,
+                free_latest: free.ub,
+            }
+        })
+        .collect();
+
+    report.sort_by(|a, b| (a.time, &a.clock).cmp(&(b.time, &b.clock)));
+    Ok(report)
+}
+
+// The compiled constraints with each disjunction narrowed to the first alternative the
+// schedule satisfies (one always is: the schedule was verified)
+fn ordering_zone(compiler: &TimeConstraintCompiler, times: &HashMap<Clock, i64>) -> Dbm<i64> {
+    let holds = |constraint: &DiffConstraint| {
+        times[&constraint.left] - times[&constraint.right] <= constraint.bound
+    };
+
+    let mut zone = compiler.base_zone();
+    for disjunction in compiler.groups.iter().flat_map(|group| &group.disjunctions) {
+        if let Some(alternative) = disjunction
+            .alternatives
+            .iter()
+            .find(|alternative| alternative.iter().all(holds))
+        {
+            for constraint in alternative {
+                constraint.apply_to(&mut zone);
+            }
+        }
+    }
+    zone
+}
+
+// The report as a table, one instance per line, "rigid" marking those that can't move
+pub fn format_slack(compiler: &TimeConstraintCompiler, report: &[Slack]) -> String {
+    let days = compiler.config.horizon_days as i64;
+    let range = |start: i64, end: i64| {
+        format!("{} - {}", format_minutes(start, days), format_minutes(end, days))
+    };
+
+    let header = ["Instance", "Time", "Earlier", "Later", "Others fixed", "Others free", ""];
+    let mut rows: Vec<[String; 7]> = vec![header.map(String::from)];
+    for slack in report {
+        rows.push([
+            slack.clock.clone(),
+            format_minutes(slack.time, days),
+            hm(slack.earlier()),
+            hm(slack.later()),
+            range(slack.earliest, slack.latest),
+            range(slack.free_earliest, slack.free_latest),
+            if slack.is_rigid() { "rigid" } else { "" }.to_string(),
+        ]);
+    }
+
+    let mut widths = [0; 7];
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let mut result = String::from("Slack:\n");
+    for row in &rows {
+        let mut line = String::from(" ");
+        for (cell, &width) in row.iter().zip(&widths) {
+            line.push_str(&format!(" {:<width$} ", cell, width = width));
+        }
+        result.push_str(line.trim_end());
+        result.push('\n');
+    }
+    result
+}
+
+// One instance in the JSON form of the report
+#[derive(Serialize)]
+struct SlackEntry<'a> {
+    clock: &'a str,
+    entity: &'a str,
+    time: String,
+    // The time in minutes from the start of the horizon
+    minutes: i64,
+    // Minutes it can move earlier and later with the others fixed
+    earlier: i64,
+    later: i64,
+    rigid: bool,
+    fixed: Range,
+    free: Range,
+}
+
+#[derive(Serialize)]
+struct Range {
+    earliest: String,
+    latest: String,
+}
+
+// The report as a JSON array, in the same order as the table
+pub fn slack_json(compiler: &TimeConstraintCompiler, report: &[Slack]) -> String {
+    let days = compiler.config.horizon_days as i64;
+    let range = |earliest: i64, latest: i64| Range {
+        earliest: format_minutes(earliest, days),
+        latest: format_minutes(latest, days),
+    };
+
+    let entries: Vec<SlackEntry> = report
+        .iter()
+        .map(|slack| SlackEntry {
+            clock: &slack.clock,
+            entity: &slack.entity,
+            time: format_minutes(slack.time, days),
+            minutes: slack.time,
+            earlier: slack.earlier(),
+            later: slack.later(),
+            rigid: slack.is_rigid(),
+            fixed: range(slack.earliest, slack.latest),
+            free: range(slack.free_earliest, slack.free_latest),
+        })
+        .collect();
+
+    serde_json::to_string_pretty(&entries).unwrap_or_default()
+}
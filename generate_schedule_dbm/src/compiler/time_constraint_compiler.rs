@@ -3,13 +3,21 @@ use colored::*;
 use std::collections::{HashMap, HashSet};
 use std::env;
 
+use crate::compiler::alternatives::{self, Alternative};
 use crate::compiler::clock_info::ClockInfo;
-use crate::compiler::constraints::{daily_bounds, entity, frequency, category};
+use crate::compiler::config::{CompilerConfig, Preference, ANCHOR_WEIGHT};
+use crate::compiler::constraints::{category, daily_bounds, entity, frequency, windows};
 use crate::compiler::debugging;
+use crate::compiler::federation::{self, DiffConstraint, Disjunction, SearchBudget};
+use crate::compiler::infeasibility::{self, ConstraintGroup, ConstraintOrigin};
 use crate::compiler::schedule_extraction;
+use crate::compiler::slack::{self, Slack};
+use crate::compiler::verification::{self, Violation};
+use crate::error::ScheduleError;
 use crate::extractor::schedule_extractor::ScheduleStrategy;
 use crate::types::entity::Entity;
 use crate::types::constraints::CategoryConstraint;
+use crate::types::frequency::Weekday;
 
 pub struct TimeConstraintCompiler {
     // Maps entity names to their data
@@ -26,10 +34,20 @@ pub struct TimeConstraintCompiler {
     pub debug: bool,
     // Optional category-level constraints
     pub category_constraints: Option<Vec<CategoryConstraint>>,
+    // Day window, per-category/entity windows, horizon and search settings
+    pub config: CompilerConfig,
+    // Compiled constraints grouped by the source constraint they came from
+    pub groups: Vec<ConstraintGroup>,
+    // The zones satisfying every disjunction, found by branching over their alternatives
+    pub federation: Vec<Dbm<i64>>,
 }
 
 impl TimeConstraintCompiler {
     pub fn new(entities: Vec<Entity>) -> Self {
+        Self::with_config(entities, CompilerConfig::default())
+    }
+
+    pub fn with_config(entities: Vec<Entity>, config: CompilerConfig) -> Self {
         // Check if debug flag is set
         let debug = env::var("RUST_DEBUG").is_ok() || env::args().any(|arg| arg == "--debug");
 
@@ -64,6 +82,9 @@ impl TimeConstraintCompiler {
             next_clock_index: 0,
             debug,
             category_constraints: None,
+            config,
+            groups: Vec::new(),
+            federation: Vec::new(),
         }
     }
 
@@ -72,23 +93,109 @@ impl TimeConstraintCompiler {
         self.category_constraints = Some(constraints);
     }
 
-    fn allocate_clocks(&mut self) -> Result<(), String> {
+    // Restrict every clock to the given part of the day (minutes from midnight)
+    pub fn set_day_window(&mut self, start: i64, end: i64) {
+        self.config.day_window = (start, end);
+    }
+
+    // Schedule every entity of a category within its own window instead of the day window
+    pub fn set_category_window(&mut self, category: &str, start: i64, end: i64) {
+        self.config
+            .category_windows
+            .insert(category.to_string(), (start, end));
+    }
+
+    // Schedule one entity within its own window (overrides its category's window)
+    pub fn set_entity_window(&mut self, entity: &str, start: i64, end: i64) {
+        self.config
+            .entity_windows
+            .insert(entity.to_string(), (start, end));
+    }
+
+    // Schedule over several days instead of one
+    pub fn set_horizon_days(&mut self, days: usize) {
+        self.config.horizon_days = days.max(1);
+    }
+
+    // The weekday of day 1, for weekday frequencies such as "Mon/Wed/Fri"
+    pub fn set_first_weekday(&mut self, weekday: Weekday) {
+        self.config.first_weekday = weekday;
+    }
+
+    // Treat the schedule as repeating every horizon (every day by default), so spacing holds
+    // across the wrap from the last instance to the first
+    pub fn set_cyclic(&mut self, cyclic: bool) {
+        self.config.cyclic = cyclic;
+    }
+
+    // Prefer the entity's instances at these times of day (in order through each day), at a
+    // cost of `weight` per minute away, when extracting with the preferred strategy
+    pub fn set_preference(&mut self, entity: &str, mut times: Vec<i64>, weight: u32) {
+        times.sort();
+        self.config
+            .preferences
+            .insert(entity.to_string(), Preference { times, weight });
+    }
+
+    // Limit how many zones the disjunction search keeps
+    pub fn set_max_zones(&mut self, max_zones: usize) {
+        self.config.max_zones = max_zones.max(1);
+    }
+
+    // Limit how many zone operations the disjunction search (and the search for a conflict
+    // when there is no schedule) may take
+    pub fn set_search_budget(&mut self, steps: usize) {
+        self.config.search_budget = steps.max(1);
+    }
+
+    fn allocate_clocks(&mut self) -> Result<(), ScheduleError> {
         use clock_zones::Clock;
 
-        for (entity_name, entity) in &self.entities {
-            let instances = entity.frequency.get_instances_per_day();
+        let days = self.config.horizon_days;
+        let first_weekday = self.config.first_weekday;
+        let mut entity_names: Vec<&String> = self.entities.keys().collect();
+        entity_names.sort();
+        for entity_name in entity_names {
+            self.entities[entity_name]
+                .frequency
+                .check_horizon(days)
+                .map_err(|e| e.in_field(format!("Entity '{}' frequency", entity_name)))?;
+        }
+
+        let instance_days: HashMap<String, Vec<Option<usize>>> = self
+            .entities
+            .values()
+            .map(|e| {
+                let (start, end) = self.config.window_for(&e.name, &e.category);
+                let days = e.frequency.instance_days(days, first_weekday, end - start);
+                (e.name.clone(), days)
+            })
+            .collect();
+        let total_clocks = instance_days.values().map(Vec::len).sum();
+        self.zone = Dbm::new_unconstrained(total_clocks);
+
+        // By name, so clocks are numbered the same way on every run (the disjunction search
+        // takes its order from them)
+        let mut entities: Vec<(&String, &Entity)> = self.entities.iter().collect();
+        entities.sort_by_key(|&(entity_name, _)| entity_name);
+
+        for (entity_name, entity) in entities {
+            let instance_days = &instance_days[entity_name];
+            let instances = instance_days.len();
             if self.debug {
                 debugging::debug_print(
                     self,
                     "📝",
                     &format!(
-                        "Entity: {} - Frequency: {:?} - Instances: {}",
+                        "Entity: {} - Frequency: {} - Instances: {}",
                         entity_name, entity.frequency, instances
                     ),
                 );
             }
 
-            for i in 0..instances {
+            // Instances are numbered across the horizon, so day 2 of a 2x daily entity
+            // holds instances 3 and 4, and an every 2 days entity has instances on days 1, 3, ...
+            for (i, &day) in instance_days.iter().enumerate() {
                 let clock_id = format!("{}_{}", entity_name, i + 1);
                 let variable = Clock::variable(self.next_clock_index);
                 self.next_clock_index += 1;
@@ -98,7 +205,9 @@ impl TimeConstraintCompiler {
                     ClockInfo {
                         entity_name: entity_name.clone(),
                         instance: i + 1,
+                        day,
                         variable,
+                        target: None,
                     },
                 );
 
@@ -119,21 +228,24 @@ impl TimeConstraintCompiler {
         Ok(())
     }
 
-    pub fn compile(&mut self) -> Result<&Dbm<i64>, String> {
+    pub fn compile(&mut self) -> Result<&Dbm<i64>, ScheduleError> {
         debugging::debug_print(self, "🚀", "Starting compilation process");
+        self.config.validate()?;
 
         // 1. Create clock variables for all entity instances
         debugging::debug_print(self, "⏰", "Step 1: Allocating clock variables");
         self.allocate_clocks()?;
         debugging::debug_zone_state(self);
 
-        // 2. Set daily bounds (0-24 hours in minutes)
-        debugging::debug_print(self, "📅", "Step 2: Setting daily bounds (0-24 hours)");
+        // 2. Set daily bounds (the day window in minutes)
+        debugging::debug_print(self, "📅", "Step 2: Setting daily bounds (day window)");
         daily_bounds::apply_daily_bounds(self)?;
+        windows::apply_entity_windows(self)?;
         debugging::debug_zone_state(self);
 
-        // 3. Apply frequency-based constraints (spacing between occurrences)
+        // 3. Apply frequency-based constraints (fixed times, spacing between occurrences)
         debugging::debug_print(self, "🔄", "Step 3: Applying frequency-based constraints");
+        frequency::apply_fixed_times(self)?;
         frequency::apply_frequency_constraints(self)?;
         debugging::debug_zone_state(self);
 
@@ -147,23 +259,62 @@ impl TimeConstraintCompiler {
         category::apply_category_constraints(self)?;
         debugging::debug_zone_state(self);
 
-        // 6. Check feasibility
-        if self.zone.is_empty() {
-            debugging::debug_error(
+        // 6. Branch over the disjunctive constraints, keeping the zones that satisfy all of them
+        let disjunctions: Vec<Disjunction> = self
+            .groups
+            .iter()
+            .flat_map(|group| group.disjunctions.iter().cloned())
+            .collect();
+
+        if !disjunctions.is_empty() && !self.zone.is_empty() {
+            debugging::debug_print(
                 self,
-                "❌",
-                "Schedule is not feasible with the given constraints",
+                "🌿",
+                &format!(
+                    "Step 6: Searching {} disjunctive constraints",
+                    disjunctions.len()
+                ),
             );
+            let mut budget = SearchBudget::new(self.config.search_budget);
+            self.federation =
+                federation::search(&self.zone, &disjunctions, self.config.max_zones, &mut budget)
+                    .map_err(|exhausted| ScheduleError::SearchLimit {
+                        steps: exhausted.steps,
+                        disjunctions: disjunctions.len(),
+                    })?;
+
+            if let Some(first) = self.federation.first() {
+                self.zone = first.clone();
+                debugging::debug_print(
+                    self,
+                    "🌿",
+                    &format!(
+                        "Federation has {} feasible zone(s), found in {} search steps",
+                        self.federation.len(),
+                        budget.spent()
+                    ),
+                );
+            }
+        } else if !self.zone.is_empty() {
+            self.federation = vec![self.zone.clone()];
+        }
 
-            // Try to identify which constraint caused infeasibility
+        // 7. Check feasibility, explaining a failure by a minimal set of conflicting constraints
+        if self.zone.is_empty() || self.federation.is_empty() {
             debugging::debug_error(
                 self,
-                "🔍",
-                "Attempting to identify problematic constraints...",
+                "❌",
+                "Schedule is not feasible with the given constraints",
             );
-            debugging::diagnose_infeasibility::<i32>(self);
 
-            return Err("Schedule is not feasible with the given constraints".to_string());
+            let mut budget = SearchBudget::new(self.config.search_budget);
+            let infeasibility =
+                infeasibility::minimal_conflict(self.next_clock_index, &self.groups, &mut budget);
+            for origin in &infeasibility.conflict {
+                debugging::debug_error(self, "  👉", &origin.to_string());
+            }
+
+            return Err(ScheduleError::Infeasible(infeasibility));
         }
 
         debugging::debug_print(
@@ -174,6 +325,26 @@ impl TimeConstraintCompiler {
         Ok(&self.zone)
     }
 
+    // Whether a constraint between two clocks applies: clocks on days more than `day_span`
+    // apart don't constrain each other (e.g. "before food" relates doses to that day's meals)
+    pub fn clocks_related(
+        &self,
+        a: clock_zones::Variable,
+        b: clock_zones::Variable,
+        day_span: usize,
+    ) -> bool {
+        let day_of = |var| {
+            self.clocks
+                .values()
+                .find(|info| info.variable == var)
+                .and_then(|info| info.day)
+        };
+        match (day_of(a), day_of(b)) {
+            (Some(day_a), Some(day_b)) => day_a.abs_diff(day_b) <= day_span,
+            _ => true,
+        }
+    }
+
     pub fn find_clock_name(&self, var: clock_zones::Variable) -> Option<String> {
         for (name, info) in &self.clocks {
             if info.variable == var {
@@ -183,50 +354,168 @@ impl TimeConstraintCompiler {
         None
     }
 
-    pub fn add_constraint_safely<F>(&mut self, constraint_builder: F, description: &str) -> bool
-    where
-        F: Fn() -> clock_zones::Constraint<i64>,
-    {
-        // Create a test zone to see if adding this constraint would make it infeasible
-        let mut test_zone = self.zone.clone();
-        test_zone.add_constraint(constraint_builder());
+    // Start a group for the constraints compiled from one source constraint
+    pub fn new_group(&mut self, origin: ConstraintOrigin) -> usize {
+        self.groups.push(ConstraintGroup::new(origin));
+        self.groups.len() - 1
+    }
 
-        if test_zone.is_empty() {
-            debugging::debug_error(
+    pub fn add_constraint(&mut self, group: usize, constraint: DiffConstraint, description: &str) {
+        debugging::debug_print(self, "✅", &format!("Adding constraint: {}", description));
+        constraint.apply_to(&mut self.zone);
+        self.groups[group].constraints.push(constraint);
+    }
+
+    pub fn add_disjunction(&mut self, group: usize, disjunction: Disjunction) {
+        debugging::debug_print(
+            self,
+            "🔀",
+            &format!("Adding disjunction: {}", disjunction.description),
+        );
+        self.groups[group].disjunctions.push(disjunction);
+    }
+
+    // A zone (the compiled one, or another of the federation) with every clock that has a
+    // target (an anchor window) fixed as close to it as the zone allows. The zone is canonical,
+    // so any value within a clock's bounds extends to a full schedule; fixing one clock
+    // re-tightens the bounds of the rest.
+    pub fn zone_with_targets(&self, zone: &Dbm<i64>) -> Dbm<i64> {
+        let mut zone = zone.clone();
+
+        let mut targets: Vec<(&String, &ClockInfo, i64)> = self
+            .clocks
+            .iter()
+            .filter_map(|(id, info)| info.target.map(|target| (id, info, target)))
+            .collect();
+        targets.sort_by_key(|&(id, _, _)| id);
+
+        for (clock_id, info, target) in targets {
+            let lower = zone.get_lower_bound(info.variable).unwrap_or(target);
+            let upper = zone.get_upper_bound(info.variable).unwrap_or(target);
+            let time = target.clamp(lower, upper);
+
+            debugging::debug_print(
                 self,
-                "⚠️",
-                &format!(
-                    "Cannot add constraint - would make schedule infeasible: {}",
-                    description
-                ),
+                "📍",
+                &format!("Anchoring {} at {} (target {})", clock_id, time, target),
             );
-            false
-        } else {
-            debugging::debug_print(self, "✅", &format!("Adding constraint: {}", description));
-            self.zone.add_constraint(constraint_builder());
-            true
+            DiffConstraint::diff_ge(info.variable, clock_zones::Clock::ZERO, time).apply_to(&mut zone);
+            DiffConstraint::diff_le(info.variable, clock_zones::Clock::ZERO, time).apply_to(&mut zone);
         }
+
+        zone
+    }
+
+    // The zone of every compiled constraint but the disjunctions, rebuilt from the groups (the
+    // compiled zone has the first ordering of the federation in it)
+    pub fn base_zone(&self) -> Dbm<i64> {
+        let mut zone: Dbm<i64> = Dbm::new_unconstrained(self.next_clock_index);
+        for constraint in self.groups.iter().flat_map(|group| &group.constraints) {
+            constraint.apply_to(&mut zone);
+        }
+        zone
+    }
+
+    // For each clock allocated to a day, its place among its entity's clocks that day (from 0)
+    // and how many there are. Counted from the clocks allocated, not the frequency: a minute
+    // interval fits as many instances as the entity's window holds, fewer than a whole day would.
+    pub fn day_positions(&self) -> HashMap<String, (usize, usize)> {
+        let mut same_day: HashMap<(&str, usize), Vec<(usize, &String)>> = HashMap::new();
+        for (clock_id, info) in &self.clocks {
+            if let Some(day) = info.day {
+                same_day
+                    .entry((&info.entity_name, day))
+                    .or_default()
+                    .push((info.instance, clock_id));
+            }
+        }
+
+        let mut positions = HashMap::new();
+        for mut clocks in same_day.into_values() {
+            clocks.sort_unstable();
+            let count = clocks.len();
+            for (k, (_, clock_id)) in clocks.into_iter().enumerate() {
+                positions.insert(clock_id.clone(), (k, count));
+            }
+        }
+        positions
+    }
+
+    // The preferred time and weight of each clock for the preferred strategy: its entity's
+    // preferred times, taken in order through each day's instances, or else its anchor
+    pub fn clock_preferences(&self) -> HashMap<String, (i64, u32)> {
+        let positions = self.day_positions();
+        self.clocks
+            .iter()
+            .filter_map(|(clock_id, info)| {
+                match self.config.preferences.get(&info.entity_name) {
+                    Some(preference) if !preference.times.is_empty() => {
+                        let day = info.day?;
+                        let index_in_day = positions.get(clock_id).map_or(0, |&(k, _)| k);
+                        let time = preference.times[index_in_day.min(preference.times.len() - 1)];
+                        let offset = (day as i64 - 1) * daily_bounds::MINUTES_PER_DAY;
+                        Some((clock_id.clone(), (offset + time, preference.weight)))
+                    }
+                    _ => info
+                        .target
+                        .map(|target| (clock_id.clone(), (target, ANCHOR_WEIGHT))),
+                }
+            })
+            .collect()
+    }
+
+    // The preferred strategy over the whole of the constraints: the disjunctive ones are
+    // searched again, for the ordering that lets the clocks come nearest their preferred times,
+    // rather than taking the orderings the federation happened to keep
+    fn extract_preferred(&self) -> Result<HashMap<String, i32>, ScheduleError> {
+        use crate::extractor::min_cost::preferred_zone;
+        use crate::extractor::schedule_extractor::ScheduleExtractor;
+
+        let zone = self.base_zone();
+        let disjunctions: Vec<Disjunction> = self
+            .groups
+            .iter()
+            .flat_map(|group| group.disjunctions.iter().cloned())
+            .collect();
+
+        let preferences = self.clock_preferences();
+        let clock_preferences: Vec<(clock_zones::Clock, i64, u32)> = preferences
+            .iter()
+            .map(|(clock_id, &(time, weight))| (self.clocks[clock_id].variable.into(), time, weight))
+            .collect();
+
+        let zone = preferred_zone(&zone, &disjunctions, &clock_preferences).ok_or_else(|| {
+            ScheduleError::Extraction("No schedule satisfies the constraints".to_string())
+        })?;
+        ScheduleExtractor::new(&zone, &self.clocks)
+            .with_preferences(preferences)
+            .extract_schedule(ScheduleStrategy::Preferred)
     }
 
     pub fn finalize_schedule(
         &self,
         strategy: ScheduleStrategy,
-    ) -> Result<HashMap<String, i32>, String> {
+    ) -> Result<HashMap<String, i32>, ScheduleError> {
         use crate::extractor::schedule_extractor::ScheduleExtractor;
 
         // Make sure zone is properly compiled and feasible
         if self.zone.is_empty() {
-            return Err(
+            return Err(ScheduleError::Extraction(
                 "Cannot extract schedule from empty zone. Did you call compile() first?"
                     .to_string(),
-            );
+            ));
         }
 
-        // Create the extractor and pass references to zone and clocks
-        let extractor = ScheduleExtractor::new(&self.zone, &self.clocks);
-
-        // Extract schedule using the selected strategy
-        let schedule = extractor.extract_schedule(strategy)?;
+        // Extract schedule using the selected strategy. Other than the preferred strategy
+        // (which weighs anchors against the other preferences itself), create the extractor
+        // and pass references to zone and clocks, with anchored clocks already placed.
+        let schedule = match strategy {
+            ScheduleStrategy::Preferred => self.extract_preferred()?,
+            _ => {
+                let zone = self.zone_with_targets(&self.zone);
+                ScheduleExtractor::new(&zone, &self.clocks).extract_schedule(strategy)?
+            }
+        };
 
         // Debug output for schedule extraction
         if self.debug {
@@ -240,6 +529,7 @@ impl TimeConstraintCompiler {
                 ScheduleStrategy::Centered => println!("  Strategy: Centered"),
                 ScheduleStrategy::Justified => println!("  Strategy: Justified"),
                 ScheduleStrategy::MaximumSpread => println!("  Strategy: MaximumSpread"),
+                ScheduleStrategy::Preferred => println!("  Strategy: Preferred"),
             }
 
             // Convert to a sorted list like in format_schedule
@@ -263,13 +553,43 @@ impl TimeConstraintCompiler {
         Ok(schedule)
     }
 
-    // Delegate to schedule_extraction module
-    pub fn extract_schedule(&self) -> Result<HashMap<String, i32>, String> {
-        schedule_extraction::extract_schedule(self)
+    // Extract a schedule with the default (centered) strategy
+    pub fn extract_schedule(&self) -> Result<HashMap<String, i32>, ScheduleError> {
+        self.finalize_schedule(ScheduleStrategy::Centered)
+    }
+
+    // Up to `count` feasible schedules as different from one another as possible, each labelled
+    // by the order of its instances; the first is the one `strategy` extracts
+    pub fn alternatives(
+        &self,
+        count: usize,
+        strategy: ScheduleStrategy,
+    ) -> Result<Vec<Alternative>, ScheduleError> {
+        alternatives::alternatives(self, count, strategy)
+    }
+
+    // Check a concrete schedule against the source constraints, returning the ones it
+    // violates. Works after compile(), even when it reported the constraints infeasible.
+    pub fn verify(&self, schedule: &HashMap<String, i32>) -> Result<Vec<Violation>, ScheduleError> {
+        verification::verify(self, schedule)
+    }
+
+    // How far each instance of a schedule can move, with the others fixed and with them free
+    pub fn slack(&self, schedule: &HashMap<String, i32>) -> Result<Vec<Slack>, ScheduleError> {
+        slack::slack(self, schedule)
     }
 
     // Delegate to schedule_extraction module
     pub fn format_schedule(&self, schedule: &HashMap<String, i32>) -> String {
         schedule_extraction::format_schedule(self, schedule)
     }
+
+    // Delegate to slack module
+    pub fn format_slack(&self, report: &[Slack]) -> String {
+        slack::format_slack(self, report)
+    }
+
+    pub fn slack_json(&self, report: &[Slack]) -> String {
+        slack::slack_json(self, report)
+    }
 }
@@ -0,0 +1,187 @@
+use crate::compiler::constraints::daily_bounds::format_minutes;
+use crate::compiler::federation::DiffConstraint;
+use crate::compiler::infeasibility::ConstraintOrigin;
+use crate::compiler::time_constraint_compiler::TimeConstraintCompiler;
+use crate::error::ScheduleError;
+use clock_zones::{AnyClock, Clock};
+use std::collections::HashMap;
+use std::fmt;
+
+// A source constraint a concrete schedule doesn't satisfy, and by how much
+#[derive(Debug, Clone, PartialEq)]
+pub struct Violation {
+    pub origin: ConstraintOrigin,
+    // What the constraint asks for and what the schedule does instead
+    pub detail: String,
+    // How many minutes the schedule would have to move by to satisfy it
+    pub minutes: i64,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} (off by {})", self.origin, self.detail, hm(self.minutes))
+    }
+}
+
+// Check a schedule (clock id -> minutes from the start of the horizon) against every source
+// constraint the compiler collected, disjunctions included. The zone alone can't do this: it
+// holds only the ordering the disjunction search picked first, so a schedule using another
+// ordering would look wrong.
+pub fn verify(
+    compiler: &TimeConstraintCompiler,
+    schedule: &HashMap<String, i32>,
+) -> Result<Vec<Violation>, ScheduleError> {
+    if compiler.groups.is_empty() && !compiler.entities.is_empty() {
+        return Err(ScheduleError::Input(
+            "Cannot verify a schedule before the constraints are compiled. Did you call compile() first?"
+                .to_string(),
+        ));
+    }
+
+    let mut unknown: Vec<&String> = schedule
+        .keys()
+        .filter(|clock_id| !compiler.clocks.contains_key(*clock_id))
+        .collect();
+    unknown.sort();
+    if let Some(clock_id) = unknown.first() {
+        return Err(ScheduleError::Input(format!(
+            "The schedule has a time for {}, which is not a clock of these entities",
+            clock_id
+        )));
+    }
+
+    let mut missing: Vec<&String> = compiler
+        .clocks
+        .keys()
+        .filter(|clock_id| !schedule.contains_key(*clock_id))
+        .collect();
+    missing.sort();
+    if let Some(clock_id) = missing.first() {
+        return Err(ScheduleError::Input(format!(
+            "The schedule has no time for {}",
+            clock_id
+        )));
+    }
+
+    let mut clocks: HashMap<Clock, (&str, i64)> = compiler
+        .clocks
+        .iter()
+        .map(|(clock_id, info)| {
+            (info.variable.as_clock(), (clock_id.as_str(), schedule[clock_id] as i64))
+        })
+        .collect();
+    clocks.insert(Clock::ZERO, ("start", 0));
+
+    let days = compiler.config.horizon_days as i64;
+    let mut violations = Vec::new();
+
+    for group in &compiler.groups {
+        for constraint in &group.constraints {
+            let excess = excess(&clocks, constraint);
+            if excess > 0 {
+                violations.push(Violation {
+                    origin: group.origin.clone(),
+                    detail: describe(&clocks, constraint, days),
+                    minutes: excess,
+                });
+            }
+        }
+
+        // A disjunction holds if any alternative does; otherwise report how close the
+        // nearest alternative comes. A pair one constraint relates twice (as in "apart from
+        // Kibble and food", Kibble being a food) has the same alternatives twice, and is
+        // reported once.
+        let mut reported: Vec<&Vec<Vec<DiffConstraint>>> = Vec::new();
+        for disjunction in &group.disjunctions {
+            let alternatives = &disjunction.alternatives;
+            if reported.iter().any(|seen| {
+                seen.len() == alternatives.len()
+                    && alternatives.iter().all(|alternative| seen.contains(alternative))
+            }) {
+                continue;
+            }
+
+            let nearest = disjunction
+                .alternatives
+                .iter()
+                .map(|alternative| {
+                    alternative
+                        .iter()
+                        .map(|constraint| excess(&clocks, constraint))
+                        .max()
+                        .unwrap_or(0)
+                })
+                .min()
+                .unwrap_or(0);
+            if nearest > 0 {
+                reported.push(alternatives);
+                violations.push(Violation {
+                    origin: group.origin.clone(),
+                    detail: disjunction.description.clone(),
+                    minutes: nearest,
+                });
+            }
+        }
+    }
+
+    Ok(violations)
+}
+
+// How far `left - right` is above the constraint's bound (0 or less when it holds)
+fn excess(clocks: &HashMap<Clock, (&str, i64)>, constraint: &DiffConstraint) -> i64 {
+    let (_, left) = clocks[&constraint.left];
+    let (_, right) = clocks[&constraint.right];
+    left - right - constraint.bound
+}
+
+fn describe(clocks: &HashMap<Clock, (&str, i64)>, constraint: &DiffConstraint, days: i64) -> String {
+    let (left_id, left) = clocks[&constraint.left];
+    let (right_id, right) = clocks[&constraint.right];
+
+    if constraint.right == Clock::ZERO {
+        format!(
+            "{} at {} must be no later than {}",
+            left_id,
+            format_minutes(left, days),
+            format_minutes(constraint.bound, days)
+        )
+    } else if constraint.left == Clock::ZERO {
+        format!(
+            "{} at {} must be no earlier than {}",
+            right_id,
+            format_minutes(right, days),
+            format_minutes(-constraint.bound, days)
+        )
+    } else if constraint.bound < 0 {
+        // right - left ≥ -bound
+        format!(
+            "{} must be ≥{} after {}, but is {}",
+            right_id,
+            hm(-constraint.bound),
+            left_id,
+            offset(right - left, left_id)
+        )
+    } else {
+        format!(
+            "{} must be ≤{} after {}, but is {}",
+            left_id,
+            hm(constraint.bound),
+            right_id,
+            offset(left - right, right_id)
+        )
+    }
+}
+
+// Where a clock is relative to another, `minutes` after it
+fn offset(minutes: i64, other: &str) -> String {
+    match minutes {
+        0 => format!("at the same time as {}", other),
+        m if m > 0 => format!("{} after it", hm(m)),
+        m => format!("{} before it", hm(-m)),
+    }
+}
+
+// A duration as "XhYm"
+pub fn hm(minutes: i64) -> String {
+    format!("{}h{}m", minutes / 60, minutes % 60)
+}
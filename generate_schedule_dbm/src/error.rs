@@ -0,0 +1,145 @@
+use crate::compiler::infeasibility::Infeasibility;
+use schedule_grammar::{underline, GrammarError, Span};
+use std::fmt;
+
+// Errors returned by the scheduler
+#[derive(Debug, Clone)]
+pub enum ScheduleError {
+    // A DSL expression or table cell that couldn't be parsed
+    Parse(ParseError),
+    // An input file that couldn't be read, or isn't in a format we understand
+    Input(String),
+    // A constraint refers to a name that is neither an entity nor a category
+    UnresolvedReference { reference: String },
+    // The constraints can't all hold; carries a minimal conflicting subset
+    Infeasible(Infeasibility),
+    // No concrete schedule could be extracted from the compiled zone
+    Extraction(String),
+    // The search over the disjunctive constraints used up its budget without finding an
+    // ordering that satisfies them, so it's unknown whether one exists
+    SearchLimit { steps: usize, disjunctions: usize },
+}
+
+// Where and why parsing failed. Rows and columns are 1-based, as shown in a spreadsheet.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub row: Option<usize>,
+    pub column: Option<usize>,
+    // The column name, or other context such as the entity a spec value belongs to
+    pub field: Option<String>,
+    // The text that failed to parse
+    pub expression: String,
+    pub message: String,
+    // The byte range of the expression the error is about, when the DSL grammar knows it
+    pub span: Option<(u32, u32)>,
+}
+
+impl ScheduleError {
+    pub fn parse(expression: impl Into<String>, message: impl Into<String>) -> Self {
+        ScheduleError::Parse(ParseError {
+            row: None,
+            column: None,
+            field: None,
+            expression: expression.into(),
+            message: message.into(),
+            span: None,
+        })
+    }
+
+    // Attach a table position to a parse error (other errors are returned unchanged)
+    pub fn at_cell(self, row: usize, column: usize, field: &str) -> Self {
+        match self {
+            ScheduleError::Parse(error) => ScheduleError::Parse(ParseError {
+                row: Some(row),
+                column: Some(column),
+                field: Some(field.to_string()),
+                ..error
+            }),
+            other => other,
+        }
+    }
+
+    // Attach context, such as the entity being read, to a parse error
+    pub fn in_field(self, field: impl Into<String>) -> Self {
+        match self {
+            ScheduleError::Parse(error) => ScheduleError::Parse(ParseError {
+                field: Some(field.into()),
+                ..error
+            }),
+            other => other,
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.row, self.column, &self.field) {
+            (Some(row), Some(column), Some(field)) => {
+                write!(f, "Row {}, column {} ({}): ", row, column, field)?
+            }
+            (Some(row), Some(column), None) => write!(f, "Row {}, column {}: ", row, column)?,
+            (Some(row), None, _) => write!(f, "Row {}: ", row)?,
+            (None, _, Some(field)) => write!(f, "{}: ", field)?,
+            (None, _, None) => {}
+        }
+        write!(f, "{}", self.message)?;
+        // Point at the offending part: "≥1h befor food" with "befor" underlined
+        if let Some((start, end)) = self.span {
+            let span = Span::new(start as usize, end as usize);
+            write!(
+                f,
+                "\n    {}\n    {}",
+                self.expression,
+                underline(&self.expression, span)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl From<GrammarError> for ScheduleError {
+    fn from(error: GrammarError) -> Self {
+        ScheduleError::Parse(ParseError {
+            row: None,
+            column: None,
+            field: None,
+            expression: error.source,
+            message: error.message,
+            span: Some((error.span.start as u32, error.span.end as u32)),
+        })
+    }
+}
+
+impl fmt::Display for ScheduleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScheduleError::Parse(error) => write!(f, "{}", error),
+            ScheduleError::Input(message) => write!(f, "{}", message),
+            ScheduleError::UnresolvedReference { reference } => write!(
+                f,
+                "Could not resolve reference '{}' - not found as entity or category",
+                reference
+            ),
+            ScheduleError::Infeasible(infeasibility) => write!(f, "{}", infeasibility),
+            ScheduleError::Extraction(message) => write!(f, "{}", message),
+            ScheduleError::SearchLimit {
+                steps,
+                disjunctions,
+            } => write!(
+                f,
+                "Gave up searching the orderings of {} disjunctive constraints after {} steps \
+                 without finding one that satisfies them all. The constraints may still be \
+                 satisfiable: raise the search budget (set_search_budget), or schedule fewer days",
+                disjunctions, steps
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ScheduleError {}
+
+impl From<ScheduleError> for String {
+    fn from(error: ScheduleError) -> Self {
+        error.to_string()
+    }
+}
@@ -0,0 +1,275 @@
+use crate::compiler::federation::{apply_alternative, DiffConstraint, Disjunction};
+use clock_zones::{Bound, Clock, Dbm, Zone};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+// How many zones the branch and bound may solve before settling for the best schedule so far
+const MAX_BRANCHES: usize = 2000;
+
+// The part of the zone around the schedule that minimises the weighted distance from preferred
+// times while also satisfying the disjunctions.
+//
+// Branch and bound: the best point of a zone ignoring the disjunctions costs no more than any
+// point that satisfies them, so a zone whose best point costs at least as much as the best
+// schedule found so far is dropped. Otherwise, if the point breaks a disjunction, branch on
+// its alternatives; if it satisfies them all it is the best schedule in the zone, and the
+// zone is narrowed to the alternatives it satisfies, so every point of the returned zone
+// satisfies the disjunctions.
+pub fn preferred_zone(
+    zone: &Dbm<i64>,
+    disjunctions: &[Disjunction],
+    preferences: &[(Clock, i64, u32)],
+) -> Option<Dbm<i64>> {
+    let clocks: Vec<Clock> = clock_zones::clocks(zone).collect();
+    let index_of = |clock: Clock| clocks.iter().position(|&c| c == clock).unwrap_or(0);
+    let holds = |constraint: &DiffConstraint, point: &[i64]| {
+        point[index_of(constraint.left)] - point[index_of(constraint.right)] <= constraint.bound
+    };
+    let cost = |point: &[i64]| -> i64 {
+        preferences
+            .iter()
+            .map(|&(clock, time, weight)| weight as i64 * (point[index_of(clock)] - time).abs())
+            .sum()
+    };
+
+    let mut best: Option<(i64, Dbm<i64>)> = None;
+    let mut pending = vec![zone.clone()];
+    let mut explored = 0;
+
+    while let Some(zone) = pending.pop() {
+        explored += 1;
+        if explored > MAX_BRANCHES && best.is_some() {
+            break;
+        }
+
+        let Some(point) = preferred_point(&zone, preferences) else {
+            continue;
+        };
+        let point_cost = cost(&point);
+        if best.as_ref().is_some_and(|(best_cost, _)| point_cost >= *best_cost) {
+            continue;
+        }
+
+        let broken = disjunctions.iter().find(|disjunction| {
+            !disjunction
+                .alternatives
+                .iter()
+                .any(|alternative| alternative.iter().all(|c| holds(c, &point)))
+        });
+        match broken {
+            Some(disjunction) => {
+                for alternative in &disjunction.alternatives {
+                    let branch = apply_alternative(&zone, alternative);
+                    if !branch.is_empty() {
+                        pending.push(branch);
+                    }
+                }
+            }
+            None => {
+                let mut narrowed = zone;
+                for disjunction in disjunctions {
+                    if let Some(alternative) = disjunction
+                        .alternatives
+                        .iter()
+                        .find(|alternative| alternative.iter().all(|c| holds(c, &point)))
+                    {
+                        narrowed = apply_alternative(&narrowed, alternative);
+                    }
+                }
+                best = Some((point_cost, narrowed));
+            }
+        }
+    }
+
+    best.map(|(_, zone)| zone)
+}
+
+// The point of the zone that minimises the weighted distance from preferred times,
+//
+//   minimise  Σ weight · |t(clock) - preferred|   subject to   t(left) - t(right) ≤ bound
+//
+// over every bound of the zone. This is the dual of a min-cost flow problem: each bound is an
+// arc right → left of cost `bound` and unlimited capacity, and each preference a pair of arcs
+// between the zero clock and its clock, zero → clock of cost `preferred` and clock → zero of
+// cost `-preferred`, each of capacity `weight`. The node potentials of an optimal flow are an
+// optimal schedule, and since every cost is a whole number of minutes, so are they.
+//
+// The flow is found by successive shortest paths: start from potentials that satisfy every
+// bound, saturate the preference arcs they make negative, then route the resulting surplus
+// back along shortest paths (Dijkstra on reduced costs), updating the potentials as we go.
+//
+// `preferences` holds (clock, preferred time, weight); the result is indexed by clock, with
+// the zero clock at 0, or None if the zone has no point.
+pub fn preferred_point(zone: &Dbm<i64>, preferences: &[(Clock, i64, u32)]) -> Option<Vec<i64>> {
+    if zone.is_empty() {
+        return None;
+    }
+
+    let clocks: Vec<Clock> = clock_zones::clocks(zone).collect();
+    let index_of = |clock: Clock| clocks.iter().position(|&c| c == clock);
+    let mut network = Network::new(clocks.len());
+
+    for (right, &right_clock) in clocks.iter().enumerate() {
+        for (left, &left_clock) in clocks.iter().enumerate() {
+            if left == right {
+                continue;
+            }
+            if let Some(bound) = zone.get_bound(left_clock, right_clock).constant() {
+                network.add_arc(right, left, UNLIMITED, bound);
+            }
+        }
+    }
+
+    // Potentials satisfying every bound: shortest distances from a virtual source joined to
+    // every clock at cost 0. Still improving after a pass per clock means a negative cycle,
+    // bounds no schedule satisfies.
+    let mut potential = vec![0; clocks.len()];
+    for pass in 0..=clocks.len() {
+        let mut changed = false;
+        for arc in &network.arcs {
+            if arc.capacity > 0 && potential[arc.from] + arc.cost < potential[arc.to] {
+                potential[arc.to] = potential[arc.from] + arc.cost;
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+        if pass == clocks.len() {
+            return None;
+        }
+    }
+
+    // Surplus of flow arriving at each node over flow leaving it
+    let mut surplus = vec![0; clocks.len()];
+    for &(clock, preferred, weight) in preferences {
+        let clock = index_of(clock)?;
+        let weight = weight as i64;
+        let later = network.add_arc(0, clock, weight, preferred);
+        let earlier = network.add_arc(clock, 0, weight, -preferred);
+
+        // Saturate whichever arc has a negative reduced cost
+        for arc in [later, earlier] {
+            if network.reduced_cost(arc, &potential) < 0 {
+                let (from, to) = (network.arcs[arc].from, network.arcs[arc].to);
+                network.push(arc, weight);
+                surplus[from] -= weight;
+                surplus[to] += weight;
+            }
+        }
+    }
+
+    while let Some(source) = surplus.iter().position(|&s| s > 0) {
+        let (distance, via) = network.shortest_paths(source, &potential);
+
+        // The nearest node short of flow
+        let sink = (0..clocks.len())
+            .filter(|&node| surplus[node] < 0 && distance[node] < UNLIMITED)
+            .min_by_key(|&node| distance[node])?;
+
+        for node in 0..clocks.len() {
+            potential[node] += distance[node].min(distance[sink]);
+        }
+
+        // Push as much as the path, the source and the sink allow
+        let mut path = Vec::new();
+        let mut node = sink;
+        while node != source {
+            let arc = via[node]?;
+            path.push(arc);
+            node = network.arcs[arc].from;
+        }
+        let amount = path
+            .iter()
+            .map(|&arc| network.arcs[arc].capacity)
+            .min()
+            .unwrap_or(UNLIMITED)
+            .min(surplus[source])
+            .min(-surplus[sink]);
+        for arc in path {
+            network.push(arc, amount);
+        }
+        surplus[source] -= amount;
+        surplus[sink] += amount;
+    }
+
+    Some(potential.iter().map(|&p| p - potential[0]).collect())
+}
+
+const UNLIMITED: i64 = i64::MAX / 4;
+
+// A residual arc. Arcs are added in pairs, so `arc ^ 1` is the reverse of `arc`.
+struct Arc {
+    from: usize,
+    to: usize,
+    capacity: i64,
+    cost: i64,
+}
+
+struct Network {
+    arcs: Vec<Arc>,
+    outgoing: Vec<Vec<usize>>,
+}
+
+impl Network {
+    fn new(nodes: usize) -> Self {
+        Network {
+            arcs: Vec::new(),
+            outgoing: vec![Vec::new(); nodes],
+        }
+    }
+
+    fn add_arc(&mut self, from: usize, to: usize, capacity: i64, cost: i64) -> usize {
+        let arc = self.arcs.len();
+        self.arcs.push(Arc { from, to, capacity, cost });
+        self.arcs.push(Arc {
+            from: to,
+            to: from,
+            capacity: 0,
+            cost: -cost,
+        });
+        self.outgoing[from].push(arc);
+        self.outgoing[to].push(arc + 1);
+        arc
+    }
+
+    fn reduced_cost(&self, arc: usize, potential: &[i64]) -> i64 {
+        let arc = &self.arcs[arc];
+        arc.cost + potential[arc.from] - potential[arc.to]
+    }
+
+    fn push(&mut self, arc: usize, amount: i64) {
+        self.arcs[arc].capacity -= amount;
+        self.arcs[arc ^ 1].capacity += amount;
+    }
+
+    // Dijkstra over the arcs with capacity left, by reduced cost (never negative): the
+    // distance to each node and the arc it is reached by
+    fn shortest_paths(&self, source: usize, potential: &[i64]) -> (Vec<i64>, Vec<Option<usize>>) {
+        let mut distance = vec![UNLIMITED; self.outgoing.len()];
+        let mut via = vec![None; self.outgoing.len()];
+        let mut queue = BinaryHeap::new();
+
+        distance[source] = 0;
+        queue.push(Reverse((0, source)));
+        while let Some(Reverse((d, node))) = queue.pop() {
+            if d > distance[node] {
+                continue;
+            }
+            for &arc in &self.outgoing[node] {
+                if self.arcs[arc].capacity <= 0 {
+                    continue;
+                }
+                let to = self.arcs[arc].to;
+                let next = d + self.reduced_cost(arc, potential);
+                if next < distance[to] {
+                    distance[to] = next;
+                    via[to] = Some(arc);
+                    queue.push(Reverse((next, to)));
+                }
+            }
+        }
+
+        (distance, via)
+    }
+}
@@ -1,6 +1,6 @@
 pub mod schedule_extractor;
 pub mod debug;
 pub mod topological_sort;
-pub mod passes;
+pub mod placement;
 pub mod strategies;
-pub mod fix_violations;
+pub mod min_cost;
@@ -0,0 +1,51 @@
+use std::collections::HashMap;
+use clock_zones::{Constraint, Zone};
+use crate::compiler::clock_info::ClockInfo;
+use crate::error::ScheduleError;
+use crate::extractor::schedule_extractor::{bounds_in, Bounds, ScheduleExtractor};
+
+impl<'a> ScheduleExtractor<'a> {
+    // Fix the clocks one at a time, in the given order: each at the time its target asks for,
+    // moved into the range the zone still allows, and the zone restricted to that time before
+    // the next clock is chosen. `target` gets the clock's position in the order, its id and
+    // that range.
+    //
+    // Restricting a clock re-canonicalises the zone, so each clock's range accounts for every
+    // clock fixed before it. A canonical, non-empty zone has a solution for any time in a
+    // clock's range, so the zone never becomes empty and the schedule satisfies all of its
+    // constraints, whichever targets the strategy picks.
+    pub fn place_clocks(
+        &self,
+        sorted_clocks: &[(String, &ClockInfo)],
+        target: impl Fn(usize, &str, &Bounds) -> i64,
+    ) -> Result<HashMap<String, i32>, ScheduleError> {
+        let mut zone = self.zone.clone();
+        let mut schedule = HashMap::new();
+
+        for (i, (clock_id, info)) in sorted_clocks.iter().enumerate() {
+            let bounds = bounds_in(&zone, info.variable);
+            let wanted = target(i, clock_id, &bounds);
+            let time = wanted.clamp(bounds.lb, bounds.ub);
+            if time != wanted {
+                self.debug_print("📍", &format!(
+                    "{} target {} moved to {} (still feasible: [{}, {}])",
+                    clock_id, wanted, time, bounds.lb, bounds.ub
+                ));
+            }
+
+            zone.add_constraint(Constraint::new_ge(info.variable, time));
+            zone.add_constraint(Constraint::new_le(info.variable, time));
+            if zone.is_empty() {
+                return Err(ScheduleError::Extraction(format!(
+                    "Placing {} at {} left no schedule in the zone",
+                    clock_id, time
+                )));
+            }
+
+            schedule.insert(clock_id.clone(), time as i32);
+            self.debug_set_time(clock_id, time as i32);
+        }
+
+        Ok(schedule)
+    }
+}
@@ -1,15 +1,20 @@
 use std::collections::HashMap;
 use std::env;
-use clock_zones::{AnyClock, Bound, Dbm, Zone};
+use clock_zones::{AnyClock, Bound, Clock, Dbm, Zone};
+use serde::{Deserialize, Serialize};
 use crate::compiler::clock_info::ClockInfo;
+use crate::error::ScheduleError;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum ScheduleStrategy {
     Earliest,
     Latest,
     Centered,
     Justified,
+    #[serde(alias = "spread")]
     MaximumSpread,
+    Preferred,
 }
 
 /// A small struct to hold lower/upper bounds for a clock.
@@ -21,6 +26,8 @@ pub struct Bounds {
 pub struct ScheduleExtractor<'a> {
     pub zone: &'a Dbm<i64>,
     pub clocks: &'a HashMap<String, ClockInfo>,
+    // Preferred time and weight per clock id, for the preferred strategy
+    pub preferences: HashMap<String, (i64, u32)>,
     pub debug: bool,
 }
 
@@ -29,53 +36,39 @@ impl<'a> ScheduleExtractor<'a> {
         // Check if debug flag is set - same approach as compiler
         let debug = env::var("RUST_DEBUG").is_ok() || env::args().any(|arg| arg == "--debug");
 
-        Self { zone, clocks, debug }
-    }
-
-    pub fn get_bounds(&self, variable: impl AnyClock) -> Bounds {
-        let lb = self.zone.get_lower_bound(variable).unwrap_or(0);
-        let ub = self.zone.get_upper_bound(variable).unwrap_or(1440);
-        Bounds { lb, ub }
+        Self {
+            zone,
+            clocks,
+            preferences: HashMap::new(),
+            debug,
+        }
     }
 
-    fn is_within_bounds(&self, variable: impl AnyClock, time: i32) -> bool {
-        let bounds = self.get_bounds(variable);
-        let result = time >= bounds.lb as i32 && time <= bounds.ub as i32;
-        if !result && self.debug {
-            self.debug_error("⚠️", &format!(
-                "Time {} is outside bounds [{}, {}]",
-                time, bounds.lb, bounds.ub
-            ));
-        }
-        result
+    pub fn with_preferences(mut self, preferences: HashMap<String, (i64, u32)>) -> Self {
+        self.preferences = preferences;
+        self
     }
 
-    fn clamp_to_bounds(&self, variable: impl AnyClock, time: i32) -> i32 {
-        let bounds = self.get_bounds(variable);
-        let clamped = time.clamp(bounds.lb as i32, bounds.ub as i32);
-        if clamped != time && self.debug {
-            self.debug_print("🔄", &format!(
-                "Clamped time {} to {} (bounds: [{}, {}])",
-                time, clamped, bounds.lb, bounds.ub
-            ));
-        }
-        clamped
+    pub fn get_bounds(&self, variable: impl AnyClock) -> Bounds {
+        bounds_in(self.zone, variable)
     }
 
     pub fn extract_schedule(
         &self,
         strategy: ScheduleStrategy,
-    ) -> Result<HashMap<String, i32>, String> {
+    ) -> Result<HashMap<String, i32>, ScheduleError> {
         self.debug_print("🧩", &format!("Extracting schedule with {:?} strategy", strategy));
 
         // Feasibility check
         if self.zone.is_empty() {
             self.debug_error("❌", "Zone is empty; no schedule is possible.");
-            return Err("Zone is empty; no schedule is possible.".to_string());
+            return Err(ScheduleError::Extraction(
+                "Zone is empty; no schedule is possible.".to_string(),
+            ));
         }
 
         // Dispatch to appropriate strategy
-        let mut schedule = match strategy {
+        let schedule = match strategy {
             ScheduleStrategy::Earliest => {
                 self.debug_print("⏱️", "Using Earliest strategy - placing all events at their earliest possible times");
                 crate::extractor::strategies::extract_earliest(self)
@@ -96,53 +89,52 @@ impl<'a> ScheduleExtractor<'a> {
                 self.debug_print("↔️", "Using MaximumSpread strategy - maximizing distance between consecutive events");
                 crate::extractor::strategies::extract_max_spread_with_constraints(self)
             },
+            ScheduleStrategy::Preferred => {
+                self.debug_print("🎯", "Using Preferred strategy - minimising the weighted distance from preferred times");
+                crate::extractor::strategies::extract_preferred(self)
+            },
         }?;
 
-        // Final validation to ensure all times are within bounds
+        // Final validation: the schedule must be a point of the zone
         self.debug_print("✅", "Validating final schedule");
-        self.validate_schedule(&mut schedule)?;
+        self.validate_schedule(&schedule)?;
 
         self.debug_print("🏁", "Schedule extraction complete");
         Ok(schedule)
     }
 
-    // Ensure all clock assignments are within their bounds
-    fn validate_schedule(&self, schedule: &mut HashMap<String, i32>) -> Result<(), String> {
-        self.debug_print("🔎", "Validating schedule - checking all times are within bounds");
+    // Check every clock is within its bounds and every pair of clocks within their difference
+    // bound. Placement guarantees this, so a violation is reported rather than adjusted.
+    fn validate_schedule(&self, schedule: &HashMap<String, i32>) -> Result<(), ScheduleError> {
+        self.debug_print("🔎", "Validating schedule - checking it satisfies every zone constraint");
 
+        let mut times = vec![(Clock::ZERO, "zero", 0)];
         for (clock_id, info) in self.clocks.iter() {
-            if let Some(time) = schedule.get_mut(clock_id) {
-                if !self.is_within_bounds(info.variable, *time) {
-                    let old_time = *time;
-                    // Clamp to valid range
-                    *time = self.clamp_to_bounds(info.variable, *time);
-                    self.debug_print("📌", &format!(
-                        "Adjusted {} from {} to {} to fit within bounds",
-                        clock_id, old_time, *time
-                    ));
+            let time = schedule.get(clock_id).ok_or_else(|| {
+                ScheduleError::Extraction(format!("No time was chosen for {}", clock_id))
+            })?;
+            times.push((info.variable.into(), clock_id.as_str(), *time as i64));
+        }
+
+        for &(left, left_id, left_time) in &times {
+            for &(right, right_id, right_time) in &times {
+                if let Some(bound) = self.zone.get_bound(left, right).constant() {
+                    if left_time - right_time > bound {
+                        return Err(ScheduleError::Extraction(format!(
+                            "{} - {} is {} minutes, but the zone allows at most {}",
+                            left_id, right_id, left_time - right_time, bound
+                        )));
+                    }
                 }
             }
         }
         Ok(())
     }
+}
 
-    // Calculate the difference constraint between two clocks
-    pub fn get_difference_constraints(&self, from_var: impl AnyClock + Copy, to_var: impl AnyClock + Copy) -> i64 {
-        // If there's a constraint to_var - from_var <= c, then from_var must be at least (-c) after to_var
-        // That means: from_var >= to_var + (-c)
-        if let Some(bound) = self.zone.get_bound(to_var, from_var).constant() {
-            if self.debug {
-                self.debug_print("🔗", &format!(
-                    "Found constraint: difference must be at least {} minutes", -bound
-                ));
-            }
-            return -bound;
-        }
-
-        // If no constraint, return a conservative default
-        if self.debug {
-            self.debug_print("🔗", "No explicit constraint found, using default (0 minutes)");
-        }
-        0 // Default: no minimum separation required
-    }
+// A clock's bounds in the zone (0 and the end of the day if it has none)
+pub fn bounds_in(zone: &Dbm<i64>, variable: impl AnyClock) -> Bounds {
+    let lb = zone.get_lower_bound(variable).unwrap_or(0);
+    let ub = zone.get_upper_bound(variable).unwrap_or(lb.max(1440));
+    Bounds { lb, ub }
 }
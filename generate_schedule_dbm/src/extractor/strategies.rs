@@ -1,204 +1,103 @@
 use std::collections::HashMap;
+use clock_zones::Clock;
+use crate::extractor::min_cost::preferred_point;
 use crate::extractor::schedule_extractor::ScheduleExtractor;
+use crate::error::ScheduleError;
 
-// Extract earliest schedule using forward pass
-pub fn extract_earliest(extractor: &ScheduleExtractor) -> Result<HashMap<String, i32>, String> {
+// Extract earliest schedule: each clock at the earliest time left for it
+pub fn extract_earliest(extractor: &ScheduleExtractor) -> Result<HashMap<String, i32>, ScheduleError> {
     extractor.debug_print("⏱️", "Extracting earliest feasible schedule");
 
-    // Sort clocks topologically
     let sorted_clocks = extractor.sort_clocks_topologically();
-
-    // Use the forward pass to get the earliest feasible schedule
-    let mut schedule = HashMap::new();
-    extractor.forward_pass(&sorted_clocks, &mut schedule);
-
-    Ok(schedule)
+    extractor.place_clocks(&sorted_clocks, |_, _, bounds| bounds.lb)
 }
 
-// Extract latest schedule using backward pass
-pub fn extract_latest(extractor: &ScheduleExtractor) -> Result<HashMap<String, i32>, String> {
+// Extract latest schedule: each clock at the latest time left for it
+pub fn extract_latest(extractor: &ScheduleExtractor) -> Result<HashMap<String, i32>, ScheduleError> {
     extractor.debug_print("⏰", "Extracting latest feasible schedule");
 
-    // Sort clocks topologically
     let sorted_clocks = extractor.sort_clocks_topologically();
-
-    // Use the backward pass to get the latest feasible schedule
-    let mut schedule = HashMap::new();
-    extractor.backward_pass(&sorted_clocks, &mut schedule);
-
-    Ok(schedule)
+    extractor.place_clocks(&sorted_clocks, |_, _, bounds| bounds.ub)
 }
 
-// Extract centered schedule
-pub fn extract_centered(extractor: &ScheduleExtractor) -> Result<HashMap<String, i32>, String> {
+// Extract centered schedule: each clock in the middle of the range left for it
+pub fn extract_centered(extractor: &ScheduleExtractor) -> Result<HashMap<String, i32>, ScheduleError> {
     extractor.debug_print("⚖️", "Extracting centered schedule");
 
-    // Get both earliest and latest schedules
     let sorted_clocks = extractor.sort_clocks_topologically();
-
-    let mut earliest_schedule = HashMap::new();
-    extractor.forward_pass(&sorted_clocks, &mut earliest_schedule);
-
-    let mut latest_schedule = HashMap::new();
-    extractor.backward_pass(&sorted_clocks, &mut latest_schedule);
-
-    // Create a new schedule with times at the midpoint
-    let mut centered_schedule = HashMap::new();
-
-    for (clock_id, _) in &sorted_clocks {
-        let earliest = *earliest_schedule.get(clock_id).unwrap_or(&0) as f64;
-        let latest = *latest_schedule.get(clock_id).unwrap_or(&1440) as f64;
-
-        // Use the midpoint
-        let centered_time = ((earliest + latest) / 2.0) as i32;
-
+    extractor.place_clocks(&sorted_clocks, |_, clock_id, bounds| {
+        let centered_time = (bounds.lb + bounds.ub) / 2;
         extractor.debug_print("↔️", &format!(
             "Centering {} between {} and {} at {}",
-            clock_id, earliest as i32, latest as i32, centered_time
+            clock_id, bounds.lb, bounds.ub, centered_time
         ));
-
-        centered_schedule.insert(clock_id.clone(), centered_time);
-        extractor.debug_set_time(clock_id, centered_time);
-    }
-
-    extractor.debug_print("🔄", "Verifying and fixing any constraint violations");
-    extractor.fix_constraint_violations(&sorted_clocks, &mut centered_schedule);
-
-    Ok(centered_schedule)
+        centered_time
+    })
 }
 
-// Justified schedule that respects constraints
-pub fn extract_justified_with_constraints(extractor: &ScheduleExtractor) -> Result<HashMap<String, i32>, String> {
+// Justified schedule: the clocks spread evenly, in order, over the span from the earliest
+// time any clock can take to the latest, the first and last at the ends
+pub fn extract_justified_with_constraints(extractor: &ScheduleExtractor) -> Result<HashMap<String, i32>, ScheduleError> {
     extractor.debug_print("📏", "Extracting justified schedule that respects constraints");
 
-    // Sort clocks topologically
     let sorted_clocks = extractor.sort_clocks_topologically();
     if sorted_clocks.is_empty() {
-        return Err("No clocks found to schedule".to_string());
+        return Err(ScheduleError::Extraction(
+            "No clocks found to schedule".to_string(),
+        ));
     }
 
-    // Get earliest feasible times with a forward pass
-    let mut earliest_schedule = HashMap::new();
-    extractor.forward_pass(&sorted_clocks, &mut earliest_schedule);
-
-    // Get latest feasible times with a backward pass
-    let mut latest_schedule = HashMap::new();
-    extractor.backward_pass(&sorted_clocks, &mut latest_schedule);
-
     // Find the global earliest and latest times
-    let mut global_earliest = i32::MAX;
-    let mut global_latest = i32::MIN;
-
-    for (clock_id, _) in &sorted_clocks {
-        let earliest = *earliest_schedule.get(clock_id).unwrap_or(&0);
-        let latest = *latest_schedule.get(clock_id).unwrap_or(&1440);
-
-        if earliest < global_earliest {
-            global_earliest = earliest;
-        }
-        if latest > global_latest {
-            global_latest = latest;
-        }
-    }
+    let global_earliest = sorted_clocks
+        .iter()
+        .map(|(_, info)| extractor.get_bounds(info.variable).lb)
+        .min()
+        .unwrap_or(0);
+    let global_latest = sorted_clocks
+        .iter()
+        .map(|(_, info)| extractor.get_bounds(info.variable).ub)
+        .max()
+        .unwrap_or(1440);
 
     extractor.debug_print("🌐", &format!(
         "Global feasible range: {} - {} (span of {} minutes)",
         global_earliest, global_latest, global_latest - global_earliest
     ));
 
-    let mut justified_schedule = HashMap::new();
-
-    // Create a justified schedule within the valid range for each clock
-    let n_clocks = sorted_clocks.len() as f64;
-
-    for (i, (clock_id, _)) in sorted_clocks.iter().enumerate() {
-        let earliest = *earliest_schedule.get(clock_id).unwrap_or(&0) as f64;
-        let latest = *latest_schedule.get(clock_id).unwrap_or(&1440) as f64;
-
-        let justified_time: i32;
-
-        // If this is the first or last clock, use the earliest or latest time
+    let last = sorted_clocks.len() - 1;
+    extractor.place_clocks(&sorted_clocks, |i, _, bounds| {
         if i == 0 {
-            justified_time = earliest as i32;
-            extractor.debug_print("🏁", &format!(
-                "First clock {} at earliest possible time {}",
-                clock_id, justified_time
-            ));
-        } else if i == sorted_clocks.len() - 1 {
-            justified_time = latest as i32;
-            extractor.debug_print("🏁", &format!(
-                "Last clock {} at latest possible time {}",
-                clock_id, justified_time
-            ));
+            bounds.lb
+        } else if i == last {
+            bounds.ub
         } else {
-            // Otherwise, distribute proportionally within the feasible range
-            let global_span = global_latest as f64 - global_earliest as f64;
-            let fraction = i as f64 / (n_clocks - 1.0);
-            let target_time = global_earliest as f64 + fraction * global_span;
-
-            // Clamp to this clock's feasible range
-            justified_time = if target_time < earliest {
-                extractor.debug_print("📍", &format!(
-                    "Clock {} target {} pushed forward to {} (its earliest feasible time)",
-                    clock_id, target_time as i32, earliest as i32
-                ));
-                earliest as i32
-            } else if target_time > latest {
-                extractor.debug_print("📍", &format!(
-                    "Clock {} target {} pulled back to {} (its latest feasible time)",
-                    clock_id, target_time as i32, latest as i32
-                ));
-                latest as i32
-            } else {
-                extractor.debug_print("📍", &format!(
-                    "Clock {} at {}/{} of span: {}",
-                    clock_id, i, sorted_clocks.len()-1, target_time as i32
-                ));
-                target_time as i32
-            };
+            // Distribute proportionally within the global range
+            let fraction = i as f64 / last as f64;
+            global_earliest + (fraction * (global_latest - global_earliest) as f64) as i64
         }
-
-        justified_schedule.insert(clock_id.clone(), justified_time);
-        extractor.debug_set_time(clock_id, justified_time);
-    }
-
-    extractor.debug_print("🔄", "Verifying and fixing any constraint violations");
-    extractor.fix_constraint_violations(&sorted_clocks, &mut justified_schedule);
-
-    Ok(justified_schedule)
+    })
 }
 
-// Maximum Spread schedule that respects constraints
-pub fn extract_max_spread_with_constraints(extractor: &ScheduleExtractor) -> Result<HashMap<String, i32>, String> {
+// Maximum Spread schedule: the clocks spread evenly, in order, from the earliest time of any
+// clock to the latest time of the last one
+pub fn extract_max_spread_with_constraints(extractor: &ScheduleExtractor) -> Result<HashMap<String, i32>, ScheduleError> {
     extractor.debug_print("↔️", "Extracting maximum spread schedule that respects constraints");
 
-    // Sort clocks topologically
     let sorted_clocks = extractor.sort_clocks_topologically();
     if sorted_clocks.is_empty() {
-        return Err("No clocks found to schedule".to_string());
-    }
-
-    // Get earliest and latest feasible times
-    let mut earliest_schedule = HashMap::new();
-    extractor.forward_pass(&sorted_clocks, &mut earliest_schedule);
-
-    let mut latest_schedule = HashMap::new();
-    extractor.backward_pass(&sorted_clocks, &mut latest_schedule);
-
-    // Get earliest time for the first clock
-    let mut global_earliest = i32::MAX;
-
-    for (clock_id, _) in &sorted_clocks {
-        let earliest = *earliest_schedule.get(clock_id).unwrap_or(&0);
-
-        if earliest < global_earliest {
-            global_earliest = earliest;
-        }
+        return Err(ScheduleError::Extraction(
+            "No clocks found to schedule".to_string(),
+        ));
     }
 
-    // Find the latest time for the last clock
-    let (last_id, _) = &sorted_clocks[sorted_clocks.len() - 1];
-    let global_latest = *latest_schedule.get(last_id).unwrap_or(&1440);
+    // Get earliest time for the first clock, and the latest time for the last clock
+    let global_earliest = sorted_clocks
+        .iter()
+        .map(|(_, info)| extractor.get_bounds(info.variable).lb)
+        .min()
+        .unwrap_or(0);
+    let (_, last_info) = &sorted_clocks[sorted_clocks.len() - 1];
+    let global_latest = extractor.get_bounds(last_info.variable).ub;
 
     extractor.debug_print("🌐", &format!(
         "Global schedule range: {} to {} (span of {} minutes)",
@@ -209,44 +108,56 @@ pub fn extract_max_spread_with_constraints(extractor: &ScheduleExtractor) -> Res
     let span = (global_latest - global_earliest) as f64;
     let n_clocks = sorted_clocks.len() as f64;
 
-    // Create a new schedule with maximum spread
-    let mut spread_schedule = HashMap::new();
+    extractor.place_clocks(&sorted_clocks, |i, _, _| {
+        let fraction = if n_clocks > 1.0 { i as f64 / (n_clocks - 1.0) } else { 0.0 };
+        global_earliest + (fraction * span) as i64
+    })
+}
 
-    for (i, (clock_id, _)) in sorted_clocks.iter().enumerate() {
-        let earliest = *earliest_schedule.get(clock_id).unwrap_or(&0);
-        let latest = *latest_schedule.get(clock_id).unwrap_or(&1440);
+// Preferred schedule: the point of the zone closest to the preferred times, by weighted minutes
+// away (see `min_cost`). Clocks with a preference are placed there first; the rest are centered
+// in the range left for them, as they don't affect the cost.
+pub fn extract_preferred(extractor: &ScheduleExtractor) -> Result<HashMap<String, i32>, ScheduleError> {
+    extractor.debug_print("🎯", "Extracting schedule closest to the preferred times");
+
+    let preferences: Vec<(Clock, i64, u32)> = extractor
+        .preferences
+        .iter()
+        .filter_map(|(clock_id, &(time, weight))| {
+            let info = extractor.clocks.get(clock_id)?;
+            Some((info.variable.into(), time, weight))
+        })
+        .collect();
+    let optimum = preferred_point(extractor.zone, &preferences).ok_or_else(|| {
+        ScheduleError::Extraction("Zone is empty; no schedule is possible.".to_string())
+    })?;
+
+    let mut sorted_clocks = extractor.sort_clocks_topologically();
+    sorted_clocks.sort_by_key(|(clock_id, _)| !extractor.preferences.contains_key(clock_id));
+
+    let schedule = extractor.place_clocks(&sorted_clocks, |_, clock_id, bounds| {
+        match extractor.preferences.get(clock_id) {
+            Some(_) => optimum[extractor.clocks[clock_id].variable.number() + 1],
+            None => (bounds.lb + bounds.ub) / 2,
+        }
+    })?;
 
-        // Calculate ideal position
-        let fraction = if n_clocks > 1.0 { i as f64 / (n_clocks - 1.0) } else { 0.0 };
-        let ideal_time = (global_earliest as f64 + fraction * span) as i32;
-
-        // Clamp to this clock's feasible range
-        let spread_time = if ideal_time < earliest {
-            extractor.debug_print("📍", &format!(
-                "Clock {} ideal {} pushed forward to {} (its earliest feasible time)",
-                clock_id, ideal_time, earliest
-            ));
-            earliest
-        } else if ideal_time > latest {
-            extractor.debug_print("📍", &format!(
-                "Clock {} ideal {} pulled back to {} (its latest feasible time)",
-                clock_id, ideal_time, latest
-            ));
-            latest
-        } else {
-            extractor.debug_print("📍", &format!(
-                "Clock {} at {}/{} of span: {}",
-                clock_id, i, sorted_clocks.len()-1, ideal_time
-            ));
-            ideal_time
-        };
-
-        spread_schedule.insert(clock_id.clone(), spread_time);
-        extractor.debug_set_time(clock_id, spread_time);
-    }
+    extractor.debug_print("🎯", &format!(
+        "Weighted distance from preferred times: {}",
+        preference_cost(&extractor.preferences, &schedule)
+    ));
 
-    extractor.debug_print("🔄", "Verifying and fixing any constraint violations");
-    extractor.fix_constraint_violations(&sorted_clocks, &mut spread_schedule);
+    Ok(schedule)
+}
 
-    Ok(spread_schedule)
+// Minutes each clock is away from its preferred time, times its weight, summed
+pub fn preference_cost(preferences: &HashMap<String, (i64, u32)>, schedule: &HashMap<String, i32>) -> i64 {
+    preferences
+        .iter()
+        .filter_map(|(clock_id, &(time, weight))| {
+            schedule
+                .get(clock_id)
+                .map(|&placed| weight as i64 * (placed as i64 - time).abs())
+        })
+        .sum()
 }
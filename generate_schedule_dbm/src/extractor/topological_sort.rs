@@ -144,12 +144,20 @@ impl<'a> ScheduleExtractor<'a> {
             }
         }
 
-        // Verify we've processed all nodes (sanity check for cycles)
+        // Clocks on a cycle are never ready; place them after the rest so every clock is placed
         if sorted_clocks.len() != self.clocks.len() {
             self.debug_error("⚠️", &format!(
                 "Topological sort only found {} nodes out of {}. There may be cycles in constraints.",
                 sorted_clocks.len(), self.clocks.len()
             ));
+            let mut remaining: Vec<(String, &ClockInfo)> = self
+                .clocks
+                .iter()
+                .filter(|(clock_id, _)| !sorted_clocks.iter().any(|(id, _)| id == *clock_id))
+                .map(|(clock_id, info)| (clock_id.clone(), info))
+                .collect();
+            remaining.sort_by(|(a, _), (b, _)| a.cmp(b));
+            sorted_clocks.extend(remaining);
         }
 
         if self.debug {
@@ -1,25 +1,46 @@
 // Main library file with re-exports
-use clock_zones::Zone;
+use std::path::Path;
 
 mod compiler;
+mod error;
 mod extractor;
 mod parser;
 mod types;
 
+// The reference expressions of constraints are the grammar crate's
+pub use schedule_grammar::Reference;
+
 // Re-export the main types and functionality
+pub use compiler::alternatives::Alternative;
 pub use compiler::clock_info::ClockInfo;
+pub use compiler::config::{CompilerConfig, Preference, ScheduleOptions};
+pub use compiler::infeasibility::{ConstraintOrigin, Infeasibility};
+pub use compiler::slack::{Slack, SlackFormat};
+pub use compiler::verification::Violation;
 pub use compiler::TimeConstraintCompiler;
+pub use error::{ParseError, ScheduleError};
 pub use extractor::schedule_extractor::ScheduleStrategy;
-pub use parser::table_parser::parse_from_table;
-pub use types::constraints::{ConstraintExpression, ConstraintReference, ConstraintType};
+pub use parser::delimited::read_table;
+pub use parser::schedule_file::{parse_schedule, read_schedule};
+pub use parser::spec::{parse_time_of_day, ScheduleSpec, SpecFormat, SPEC_VERSION};
+pub use parser::table_parser::{parse_category_constraints, parse_from_table};
+pub use types::constraints::{
+    CategoryConstraint, ConstraintExpression, ConstraintReference, ConstraintType, GapBound,
+    Pairing,
+};
 pub use types::entity::Entity;
-pub use types::frequency::Frequency;
+pub use types::frequency::{Frequency, Weekday};
 pub use types::time_unit::TimeUnit;
+pub use types::window::WindowSpec;
 
 // Example of usage with the provided table data
-pub fn example(strategy: ScheduleStrategy) -> Result<(), String> {
-    // This would come from parsing the table
-    let table_data = vec![
+pub fn example(options: &ScheduleOptions) -> Result<(), ScheduleError> {
+    schedule_from_table(example_table(), options)
+}
+
+// The built-in example table. This would come from parsing the table
+fn example_table() -> Vec<Vec<&'static str>> {
+    vec![
         vec![
             "Entity",
             "Category",
@@ -65,13 +86,13 @@ pub fn example(strategy: ScheduleStrategy) -> Result<(), String> {
         ],
         vec![
             "Pro-Kolin",
-            "med",
+            "supplement",
             "ml",
             "3.0",
             "null",
             "2x daily",
-            "[]",
-            "with food",
+            "[\"with food\"]",
+            "null",
         ],
         vec![
             "Chicken and rice",
@@ -83,22 +104,174 @@ pub fn example(strategy: ScheduleStrategy) -> Result<(), String> {
             "[]",
             "null",
         ],
-    ];
+        // Rules for whole categories follow the entities
+        vec!["Category Constraints"],
+        vec!["med ≥30m apart from food"],
+    ]
+}
+
+// Load a schedule spec (.json/.yaml/.toml) or an entity table (CSV/TSV) and schedule it.
+// The options (strategy, day window, days, cyclic) override those given in a spec.
+pub fn schedule_from_file(path: &Path, options: &ScheduleOptions) -> Result<(), ScheduleError> {
+    let (compiler, strategy) = compiler_from_file(path, options)?;
+    run_schedule(compiler, strategy)
+}
+
+// Parse the table (with its category constraints block, if any), compile the constraints and
+// print the extracted schedule
+pub fn schedule_from_table(
+    table_data: Vec<Vec<&str>>,
+    options: &ScheduleOptions,
+) -> Result<(), ScheduleError> {
+    let (compiler, strategy) = compiler_from_table(table_data, options)?;
+    run_schedule(compiler, strategy)
+}
+
+// Build the compiler from a spec (entities, category constraints, windows, horizon) and
+// schedule it
+pub fn schedule_from_spec(spec: &ScheduleSpec, options: &ScheduleOptions) -> Result<(), ScheduleError> {
+    let (compiler, strategy) = compiler_from_spec(spec, options)?;
+    run_schedule(compiler, strategy)
+}
+
+// Check a hand-written schedule file (see `parse_schedule`) against the constraints of a spec
+// or table, or of the built-in example without one, returning the constraints it violates
+pub fn check_schedule_file(
+    input: Option<&Path>,
+    schedule_path: &Path,
+    options: &ScheduleOptions,
+) -> Result<Vec<Violation>, ScheduleError> {
+    let schedule = read_schedule(schedule_path)?;
+    let (mut compiler, _) = match input {
+        Some(path) => compiler_from_file(path, options)?,
+        None => compiler_from_table(example_table(), options)?,
+    };
+
+    // Infeasible constraints are still checked: the schedule shows which ones it breaks. So are
+    // ones the search gave up on, which don't need the search to be checked.
+    match compiler.compile() {
+        Ok(_) | Err(ScheduleError::Infeasible(_)) | Err(ScheduleError::SearchLimit { .. }) => {}
+        Err(e) => return Err(e),
+    }
+    compiler.verify(&schedule)
+}
+
+// Compile the constraints of a spec or table, or of the built-in example without one, and print
+// up to `count` schedules as different from one another as possible
+pub fn schedule_alternatives(
+    input: Option<&Path>,
+    count: usize,
+    options: &ScheduleOptions,
+) -> Result<(), ScheduleError> {
+    let (mut compiler, strategy) = match input {
+        Some(path) => compiler_from_file(path, options)?,
+        None => compiler_from_table(example_table(), options)?,
+    };
+    compiler.compile()?;
+
+    let alternatives = compiler.alternatives(count, strategy)?;
+    for (index, alternative) in alternatives.iter().enumerate() {
+        println!("Option {} of {}: {}\n", index + 1, alternatives.len(), alternative.ordering);
+        println!("{}", compiler.format_schedule(&alternative.schedule));
+    }
+
+    Ok(())
+}
+
+// Compile the constraints of a spec or table, or of the built-in example without one, extract a
+// schedule and print how far each instance of it can move: the schedule and a table, or only
+// the JSON form
+pub fn schedule_slack(
+    input: Option<&Path>,
+    format: SlackFormat,
+    options: &ScheduleOptions,
+) -> Result<(), ScheduleError> {
+    let (mut compiler, strategy) = match input {
+        Some(path) => compiler_from_file(path, options)?,
+        None => compiler_from_table(example_table(), options)?,
+    };
+    compiler.compile()?;
+
+    let schedule = compiler.finalize_schedule(strategy)?;
+    let report = compiler.slack(&schedule)?;
+    match format {
+        SlackFormat::Table => {
+            println!("{}", compiler.format_schedule(&schedule));
+            println!("{}", compiler.format_slack(&report));
+        }
+        SlackFormat::Json => println!("{}", compiler.slack_json(&report)),
+    }
+
+    Ok(())
+}
+
+fn compiler_from_file(
+    path: &Path,
+    options: &ScheduleOptions,
+) -> Result<(TimeConstraintCompiler, ScheduleStrategy), ScheduleError> {
+    if SpecFormat::from_path(path).is_some() {
+        let spec = ScheduleSpec::from_path(path)?;
+        return compiler_from_spec(&spec, options);
+    }
+
+    let rows = read_table(path)?;
+    let table_data: Vec<Vec<&str>> = rows
+        .iter()
+        .map(|row| row.iter().map(|cell| cell.as_str()).collect())
+        .collect();
 
+    compiler_from_table(table_data, options)
+}
+
+fn compiler_from_table(
+    table_data: Vec<Vec<&str>>,
+    options: &ScheduleOptions,
+) -> Result<(TimeConstraintCompiler, ScheduleStrategy), ScheduleError> {
+    let category_constraints = parse_category_constraints(&table_data)?;
     let entities = parse_from_table(table_data)?;
 
+    let mut config = CompilerConfig::default();
+    options.apply_to(&mut config);
+
     // Create compiler and generate schedule
-    let mut compiler = TimeConstraintCompiler::new(entities);
+    let mut compiler = TimeConstraintCompiler::with_config(entities, config);
+    if !category_constraints.is_empty() {
+        compiler.set_category_constraints(category_constraints);
+    }
+    let strategy = options.strategy.unwrap_or(ScheduleStrategy::Centered);
+
+    Ok((compiler, strategy))
+}
 
-    // IMPORTANT: Only call compile() once!
-    let zone = compiler.compile()?;
+fn compiler_from_spec(
+    spec: &ScheduleSpec,
+    options: &ScheduleOptions,
+) -> Result<(TimeConstraintCompiler, ScheduleStrategy), ScheduleError> {
+    let mut config = spec.compiler_config()?;
+    options.apply_to(&mut config);
 
-    // Check if feasible
-    if zone.is_empty() {
-        println!("Schedule is not feasible");
-        return Err("Schedule is not feasible".to_string());
+    let mut compiler = TimeConstraintCompiler::with_config(spec.entities()?, config);
+
+    let category_constraints = spec.category_constraints()?;
+    if !category_constraints.is_empty() {
+        compiler.set_category_constraints(category_constraints);
     }
 
+    let strategy = options
+        .strategy
+        .or(spec.strategy)
+        .unwrap_or(ScheduleStrategy::Centered);
+
+    Ok((compiler, strategy))
+}
+
+fn run_schedule(
+    mut compiler: TimeConstraintCompiler,
+    strategy: ScheduleStrategy,
+) -> Result<(), ScheduleError> {
+    // IMPORTANT: Only call compile() once! An infeasible schedule is reported as an error
+    compiler.compile()?;
+
     // Extract a concrete schedule using the specified strategy
     let schedule = compiler.finalize_schedule(strategy)?;
 
@@ -1,5 +1,9 @@
-use generate_schedule::{example, ScheduleStrategy};
+use generate_schedule::{
+    check_schedule_file, example, parse_time_of_day, schedule_alternatives, schedule_from_file,
+    schedule_slack, ScheduleOptions, ScheduleStrategy, SlackFormat,
+};
 use std::env;
+use std::path::PathBuf;
 use std::process;
 
 fn main() {
@@ -15,7 +19,85 @@ fn main() {
     // Parse strategy from args
     let strategy = parse_strategy_from_args();
 
-    match example(strategy) {
+    // Parse the number of days to schedule, if given
+    let days = match parse_days_from_args() {
+        Ok(days) => days,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            process::exit(1);
+        }
+    };
+
+    // Parse the number of alternative schedules to show, if given
+    let alternatives = match parse_alternatives_from_args() {
+        Ok(alternatives) => alternatives,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            process::exit(1);
+        }
+    };
+
+    // Parse the slack report format (--slack or --slack=json), if asked for one
+    let slack = match parse_slack_from_args() {
+        Ok(slack) => slack,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            process::exit(1);
+        }
+    };
+
+    // Parse the day window (--start/--end HH:MM), if given
+    let (day_start, day_end) = match (parse_time_from_args("--start"), parse_time_from_args("--end")) {
+        (Ok(start), Ok(end)) => (start, end),
+        (Err(e), _) | (_, Err(e)) => {
+            eprintln!("Error: {}", e);
+            process::exit(1);
+        }
+    };
+
+    let options = ScheduleOptions {
+        strategy,
+        day_start,
+        day_end,
+        days,
+        // Repeat the schedule every day (or every horizon)
+        cyclic: env::args().any(|arg| arg == "--cyclic"),
+    };
+
+    // Check a hand-written schedule instead of generating one
+    if let Some(schedule_path) = parse_check_from_args() {
+        let input = parse_input_from_args();
+        match check_schedule_file(input.as_deref(), &schedule_path, &options) {
+            Ok(violations) if violations.is_empty() => {
+                println!("Schedule satisfies every constraint");
+            }
+            Ok(violations) => {
+                println!("Schedule violates {} constraint(s):", violations.len());
+                for violation in &violations {
+                    println!("  - {}", violation);
+                }
+                process::exit(1);
+            }
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                process::exit(1);
+            }
+        }
+        return;
+    }
+
+    // Use the table from --input if given, otherwise the built-in example
+    let input = parse_input_from_args();
+    let result = match (alternatives, slack, input) {
+        (Some(count), _, input) => schedule_alternatives(input.as_deref(), count, &options),
+        (None, Some(format), input) => schedule_slack(input.as_deref(), format, &options),
+        (None, None, Some(path)) => schedule_from_file(&path, &options),
+        (None, None, None) => example(&options),
+    };
+
+    match result {
+        // Keep a JSON report alone on stdout
+        Ok(_) if alternatives.is_none() && slack == Some(SlackFormat::Json) => {}
         Ok(_) => println!("Successfully generated schedule!"),
         Err(e) => {
             eprintln!("Error: {}", e);
@@ -24,19 +106,20 @@ fn main() {
     }
 }
 
-/// Parses command line arguments to determine the schedule strategy
-fn parse_strategy_from_args() -> ScheduleStrategy {
+/// Parses command line arguments to determine the schedule strategy, if one was given
+fn parse_strategy_from_args() -> Option<ScheduleStrategy> {
     let args: Vec<String> = env::args().collect();
 
     for (i, arg) in args.iter().enumerate() {
         if arg == "--strategy" || arg == "-s" {
             if i + 1 < args.len() {
-                return match args[i + 1].to_lowercase().as_str() {
+                return Some(match args[i + 1].to_lowercase().as_str() {
                     "earliest" => ScheduleStrategy::Earliest,
                     "latest" => ScheduleStrategy::Latest,
                     "centered" => ScheduleStrategy::Centered,
                     "justified" => ScheduleStrategy::Justified,
                     "spread" | "maximumspread" => ScheduleStrategy::MaximumSpread,
+                    "preferred" => ScheduleStrategy::Preferred,
                     _ => {
                         eprintln!(
                             "Warning: Unknown strategy '{}', defaulting to Centered",
@@ -45,13 +128,125 @@ fn parse_strategy_from_args() -> ScheduleStrategy {
                         eprintln!("Run with --help for a list of available strategies");
                         ScheduleStrategy::Centered
                     }
-                };
+                });
+            }
+        }
+    }
+
+    // No strategy specified: the caller decides the default (Centered, or the spec's strategy)
+    None
+}
+
+/// Parses command line arguments to find the entity table file, if any
+fn parse_input_from_args() -> Option<PathBuf> {
+    let args: Vec<String> = env::args().collect();
+
+    for (i, arg) in args.iter().enumerate() {
+        if arg == "--input" || arg == "-i" {
+            if i + 1 < args.len() {
+                return Some(PathBuf::from(&args[i + 1]));
             }
+            eprintln!("Warning: {} given without a file, using the built-in example", arg);
         }
     }
 
-    // Default to Centered if no strategy specified
-    ScheduleStrategy::Centered
+    None
+}
+
+/// Parses command line arguments to find a schedule file to check, if any
+fn parse_check_from_args() -> Option<PathBuf> {
+    let args: Vec<String> = env::args().collect();
+
+    for (i, arg) in args.iter().enumerate() {
+        if arg == "--check" || arg == "-c" {
+            if i + 1 < args.len() {
+                return Some(PathBuf::from(&args[i + 1]));
+            }
+            eprintln!("Warning: {} given without a file, generating a schedule instead", arg);
+        }
+    }
+
+    None
+}
+
+/// Parses command line arguments to find the number of days to schedule, if given
+fn parse_days_from_args() -> Result<Option<usize>, String> {
+    let args: Vec<String> = env::args().collect();
+
+    for (i, arg) in args.iter().enumerate() {
+        if arg == "--days" {
+            let value = args
+                .get(i + 1)
+                .ok_or_else(|| "--days needs a number of days".to_string())?;
+            return match value.parse::<usize>() {
+                Ok(days) if days >= 1 => Ok(Some(days)),
+                _ => Err(format!("Invalid number of days '{}'", value)),
+            };
+        }
+    }
+
+    Ok(None)
+}
+
+/// Parses command line arguments to find how many alternative schedules to show, if given
+fn parse_alternatives_from_args() -> Result<Option<usize>, String> {
+    let args: Vec<String> = env::args().collect();
+
+    for (i, arg) in args.iter().enumerate() {
+        if arg == "--alternatives" || arg == "-a" {
+            let value = args
+                .get(i + 1)
+                .ok_or_else(|| format!("{} needs a number of schedules", arg))?;
+            return match value.parse::<usize>() {
+                Ok(count) if count >= 1 => Ok(Some(count)),
+                _ => Err(format!("Invalid number of schedules '{}'", value)),
+            };
+        }
+    }
+
+    Ok(None)
+}
+
+/// Parses `--slack` (a table) or `--slack=FORMAT` (`table` or `json`), if present
+fn parse_slack_from_args() -> Result<Option<SlackFormat>, String> {
+    for arg in env::args() {
+        let format = match arg.as_str() {
+            "--slack" => "table",
+            _ => match arg.strip_prefix("--slack=") {
+                Some(format) => format,
+                None => continue,
+            },
+        };
+        return match format.to_lowercase().as_str() {
+            "table" => Ok(Some(SlackFormat::Table)),
+            "json" => Ok(Some(SlackFormat::Json)),
+            _ => Err(format!("Unknown slack format '{}' (table or json)", format)),
+        };
+    }
+
+    Ok(None)
+}
+
+/// Parses a time of day given as `--start HH:MM` or `--start=HH:MM`, if present
+fn parse_time_from_args(flag: &str) -> Result<Option<i64>, String> {
+    let args: Vec<String> = env::args().collect();
+    let prefix = format!("{}=", flag);
+
+    for (i, arg) in args.iter().enumerate() {
+        let value = if arg == flag {
+            args.get(i + 1)
+                .ok_or_else(|| format!("{} needs a time (HH:MM)", flag))?
+        } else if let Some(value) = arg.strip_prefix(&prefix) {
+            value
+        } else {
+            continue;
+        };
+        return parse_time_of_day(value)
+            .map(Some)
+            .map_err(|e| format!("{}: {}", flag, e));
+    }
+
+    Ok(None)
 }
 
 /// Prints the command-line usage information
@@ -63,6 +258,14 @@ fn print_usage() {
     println!("    -h, --help                  Display this help message");
     println!("    -d, --debug                 Enable debug output");
     println!("    -s, --strategy STRATEGY     Set the schedule extraction strategy");
+    println!("    -i, --input FILE            Read a CSV/TSV entity table or a JSON/YAML/TOML spec");
+    println!("        --start HH:MM           Start of the day window (default 00:00)");
+    println!("        --end HH:MM             End of the day window (default 24:00)");
+    println!("        --days N                Schedule N days instead of one");
+    println!("        --cyclic                Keep spacing across the wrap to the next day");
+    println!("    -a, --alternatives N        Show up to N schedules, as different as possible");
+    println!("        --slack[=json]          Also show how far each time can move (or only that, as JSON)");
+    println!("    -c, --check SCHEDULE        Check a schedule file against the constraints instead");
     println!("\nSTRATEGIES:");
     println!("    earliest       Schedule all events at their earliest possible time");
     println!("    latest         Schedule all events at their latest possible time");
@@ -71,7 +274,15 @@ fn print_usage() {
     );
     println!("    justified      Distribute events evenly across the feasible time span");
     println!("    spread         Maximize the spacing between events (similar to justified)");
+    println!("    preferred      Keep events as close to their preferred times as possible");
     println!("\nEXAMPLES:");
     println!("    generate_schedule --strategy earliest --debug");
     println!("    generate_schedule -s justified");
+    println!("    generate_schedule --input data/medication.csv");
+    println!("    generate_schedule --input data/medication.yaml");
+    println!("    generate_schedule --input data/medication.yaml --days 3");
+    println!("    generate_schedule --input data/medication.csv --start 07:00 --end 22:00");
+    println!("    generate_schedule --input data/medication.csv --check data/schedule.txt");
+    println!("    generate_schedule --input data/medication.yaml --alternatives 3");
+    println!("    generate_schedule --input data/medication.yaml --slack");
 }
@@ -0,0 +1,11 @@
+use crate::error::ScheduleError;
+use std::path::Path;
+
+// Read a CSV or TSV file into rows of cells, ready for `parse_from_table`. The reader is the
+// grammar crate's, shared with the MILP generator: the delimiter is picked from the file
+// extension (.tsv/.tab => tab, otherwise comma), and cells follow standard CSV quoting, so a
+// constraint array is written as e.g.
+//   Antepsin,med,tablet,null,3,3x daily,"[""≥6h apart"", ""≥1h before food""]",null
+pub fn read_table(path: &Path) -> Result<Vec<Vec<String>>, ScheduleError> {
+    schedule_grammar::read_table(path).map_err(ScheduleError::Input)
+}
@@ -1,2 +1,5 @@
 // Parser module exports
+pub mod delimited;
+pub mod schedule_file;
+pub mod spec;
 pub mod table_parser;
@@ -0,0 +1,93 @@
+use crate::compiler::constraints::daily_bounds::MINUTES_PER_DAY;
+use crate::error::ScheduleError;
+use crate::parser::spec::parse_time_of_day;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+// Read a schedule to check from a file, see `parse_schedule`
+pub fn read_schedule(path: &Path) -> Result<HashMap<String, i32>, ScheduleError> {
+    let text = fs::read_to_string(path).map_err(|e| {
+        ScheduleError::Input(format!("Could not open {}: {}", path.display(), e))
+    })?;
+
+    parse_schedule(&text).map_err(|e| match e {
+        ScheduleError::Input(message) => {
+            ScheduleError::Input(format!("{}: {}", path.display(), message))
+        }
+        other => other,
+    })
+}
+
+// Parse a schedule written one clock per line, in the form the generator prints it:
+//
+//   Daily Schedule:
+//     Antepsin_1: 08:00
+//     Antepsin_2: 14:00
+//
+// Over several days, a "Day N:" line starts each day's times, or a time is written as
+// "day N HH:MM". Other header lines, blank lines and `#` comments are skipped, and the
+// "By Entity:" section of the generator's output ends the schedule.
+pub fn parse_schedule(text: &str) -> Result<HashMap<String, i32>, ScheduleError> {
+    let mut schedule = HashMap::new();
+    let mut day = 1;
+
+    for (index, line) in text.lines().enumerate() {
+        let error = |message: String| ScheduleError::Input(format!("line {}: {}", index + 1, message));
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if line == "By Entity:" {
+            break;
+        }
+
+        // A header: "Day N:" starts a day, anything else ("Daily Schedule:") is skipped
+        if let Some(header) = line.strip_suffix(':') {
+            if let Some(number) = day_number(header.trim()) {
+                day = number.map_err(error)?;
+            }
+            continue;
+        }
+
+        let (name, value) = line
+            .split_once(": ")
+            .ok_or_else(|| error(format!("Expected 'clock: HH:MM', got '{}'", line)))?;
+        let (name, value) = (name.trim(), value.trim());
+
+        let (clock_day, time) = match value.split_once(' ') {
+            Some((prefix, rest)) if prefix.eq_ignore_ascii_case("day") => {
+                let (number, time) = rest
+                    .trim()
+                    .split_once(' ')
+                    .ok_or_else(|| error(format!("Expected 'day N HH:MM', got '{}'", value)))?;
+                let number = day_number(&format!("day {}", number)).unwrap().map_err(error)?;
+                (number, time.trim())
+            }
+            _ => (day, value),
+        };
+        let minutes = parse_time_of_day(time).map_err(|e| error(e.to_string()))?;
+
+        let time = (clock_day - 1) * MINUTES_PER_DAY + minutes;
+        if schedule.insert(name.to_string(), time as i32).is_some() {
+            return Err(error(format!("{} is given more than one time", name)));
+        }
+    }
+
+    if schedule.is_empty() {
+        return Err(ScheduleError::Input("The schedule has no times".to_string()));
+    }
+    Ok(schedule)
+}
+
+// The day of a "Day N" header, if the text is one
+fn day_number(text: &str) -> Option<Result<i64, String>> {
+    let (word, number) = text.split_once(' ')?;
+    if !word.eq_ignore_ascii_case("day") {
+        return None;
+    }
+    Some(match number.trim().parse::<i64>() {
+        Ok(day) if day >= 1 => Ok(day),
+        _ => Err(format!("Invalid day '{}'", number.trim())),
+    })
+}
@@ -0,0 +1,371 @@
+use crate::compiler::config::{CompilerConfig, Preference, ANCHOR_WEIGHT};
+use crate::compiler::reference_resolution::name_key;
+use crate::error::ScheduleError;
+use crate::extractor::schedule_extractor::ScheduleStrategy;
+use crate::types::constraints::{CategoryConstraint, ConstraintExpression};
+use crate::types::entity::Entity;
+use crate::types::frequency::{Frequency, Weekday};
+use crate::types::window::{check_windows, WindowSpec};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+// The spec version this build understands
+pub const SPEC_VERSION: u32 = 1;
+
+// A complete schedule specification, loadable from JSON, YAML or TOML.
+//
+// Constraints, frequencies and category constraints may be written either as DSL shorthand
+// ("≥1h before food", "2x daily", "med ≥30m apart from food") or in their structured
+// (serialized) form, e.g. {"time_value": 1, "time_unit": "Hour", ...}.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ScheduleSpec {
+    pub version: u32,
+    #[serde(default)]
+    pub day: DayWindow,
+    // Number of days to schedule (default 1)
+    #[serde(default)]
+    pub days: Option<usize>,
+    // Whether the schedule repeats, so spacing holds from the last instance to the next day's first
+    #[serde(default)]
+    pub cyclic: bool,
+    // Weekday of day 1, for weekday frequencies such as "Mon/Wed/Fri" (default Monday)
+    #[serde(default)]
+    pub first_weekday: Option<Weekday>,
+    #[serde(default)]
+    pub strategy: Option<ScheduleStrategy>,
+    pub entities: Vec<EntitySpec>,
+    #[serde(default)]
+    pub category_constraints: Vec<CategoryConstraintSpec>,
+    // Windows replacing the day window for every entity of a category, or for one entity
+    #[serde(default)]
+    pub category_windows: BTreeMap<String, DayWindow>,
+    #[serde(default)]
+    pub entity_windows: BTreeMap<String, DayWindow>,
+    // Preferred times per entity, for the preferred strategy
+    #[serde(default)]
+    pub preferences: BTreeMap<String, PreferenceSpec>,
+}
+
+// The part of the day that may be scheduled, as "HH:MM" strings ("24:00" is the end of the day)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DayWindow {
+    pub start: String,
+    pub end: String,
+}
+
+impl DayWindow {
+    // The window in minutes from midnight; `field` names it in errors
+    pub fn minutes(&self, field: &str) -> Result<(i64, i64), ScheduleError> {
+        let start =
+            parse_time_of_day(&self.start).map_err(|e| e.in_field(format!("{}.start", field)))?;
+        let end = parse_time_of_day(&self.end).map_err(|e| e.in_field(format!("{}.end", field)))?;
+        if end <= start {
+            return Err(ScheduleError::parse(
+                format!("{}-{}", self.start, self.end),
+                format!("Window end {} must be after start {}", self.end, self.start),
+            )
+            .in_field(field));
+        }
+        Ok((start, end))
+    }
+}
+
+impl Default for DayWindow {
+    fn default() -> Self {
+        DayWindow {
+            start: "00:00".to_string(),
+            end: "24:00".to_string(),
+        }
+    }
+}
+
+// Preferred times of day as "HH:MM" strings, and the cost per minute away from them
+// (default 1)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PreferenceSpec {
+    pub times: Vec<String>,
+    #[serde(default)]
+    pub weight: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EntitySpec {
+    pub name: String,
+    pub category: String,
+    #[serde(default)]
+    pub unit: String,
+    #[serde(default)]
+    pub amount: Option<f64>,
+    #[serde(default)]
+    pub split: Option<i32>,
+    pub frequency: FrequencySpec,
+    #[serde(default)]
+    pub constraints: Vec<ConstraintSpec>,
+    #[serde(default)]
+    pub windows: Vec<WindowSpecEntry>,
+    #[serde(default)]
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum FrequencySpec {
+    Shorthand(String),
+    Structured(Frequency),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ConstraintSpec {
+    Shorthand(String),
+    Structured(ConstraintExpression),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum WindowSpecEntry {
+    Shorthand(String),
+    Structured(WindowSpec),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum CategoryConstraintSpec {
+    Shorthand(String),
+    Structured(CategoryConstraint),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SpecFormat {
+    Json,
+    Yaml,
+    Toml,
+}
+
+impl SpecFormat {
+    // Pick the format from a file extension, or None if it is not a spec file
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_lowercase().as_str() {
+            "json" => Some(SpecFormat::Json),
+            "yaml" | "yml" => Some(SpecFormat::Yaml),
+            "toml" => Some(SpecFormat::Toml),
+            _ => None,
+        }
+    }
+}
+
+impl ScheduleSpec {
+    pub fn from_path(path: &Path) -> Result<Self, ScheduleError> {
+        let format = SpecFormat::from_path(path).ok_or_else(|| {
+            ScheduleError::Input(format!(
+                "{}: unknown spec format (expected .json, .yaml, .yml or .toml)",
+                path.display()
+            ))
+        })?;
+        let text = fs::read_to_string(path).map_err(|e| {
+            ScheduleError::Input(format!("Could not read {}: {}", path.display(), e))
+        })?;
+
+        Self::from_str(&text, format).map_err(|e| match e {
+            ScheduleError::Input(message) => {
+                ScheduleError::Input(format!("{}: {}", path.display(), message))
+            }
+            other => other,
+        })
+    }
+
+    pub fn from_str(text: &str, format: SpecFormat) -> Result<Self, ScheduleError> {
+        let spec: ScheduleSpec = match format {
+            SpecFormat::Json => serde_json::from_str(text).map_err(|e| e.to_string()),
+            SpecFormat::Yaml => serde_yaml::from_str(text).map_err(|e| e.to_string()),
+            SpecFormat::Toml => toml::from_str(text).map_err(|e| e.to_string()),
+        }
+        .map_err(ScheduleError::Input)?;
+
+        if spec.version != SPEC_VERSION {
+            return Err(ScheduleError::Input(format!(
+                "Unsupported spec version {} (this build reads version {})",
+                spec.version, SPEC_VERSION
+            )));
+        }
+
+        Ok(spec)
+    }
+
+    // Resolve the shorthand forms into entities
+    pub fn entities(&self) -> Result<Vec<Entity>, ScheduleError> {
+        self.entities
+            .iter()
+            .map(|entity| {
+                entity
+                    .to_entity()
+                    .map_err(|e| e.in_field(format!("Entity '{}'", entity.name)))
+            })
+            .collect()
+    }
+
+    // The category constraints, each naming categories of the spec's entities (regardless of
+    // case, as in constraints)
+    pub fn category_constraints(&self) -> Result<Vec<CategoryConstraint>, ScheduleError> {
+        let is_category = |name: &str| {
+            self.entities
+                .iter()
+                .any(|entity| name_key(&entity.category) == name_key(name))
+        };
+        self.category_constraints
+            .iter()
+            .map(|constraint| {
+                let constraint = match constraint {
+                    CategoryConstraintSpec::Shorthand(s) => CategoryConstraint::parse(s)?,
+                    CategoryConstraintSpec::Structured(c) => c.clone(),
+                };
+                for category in [&constraint.from_category, &constraint.to_category] {
+                    if !is_category(category) {
+                        return Err(ScheduleError::UnresolvedReference {
+                            reference: format!("category_constraints.{}", category),
+                        });
+                    }
+                }
+                Ok(constraint)
+            })
+            .collect()
+    }
+
+    // The day window in minutes from midnight
+    pub fn day_window(&self) -> Result<(i64, i64), ScheduleError> {
+        self.day.minutes("day")
+    }
+
+    // Compiler settings given by the spec: day, category and entity windows, horizon, cyclic,
+    // first weekday, preferences
+    pub fn compiler_config(&self) -> Result<CompilerConfig, ScheduleError> {
+        let mut config = CompilerConfig {
+            day_window: self.day_window()?,
+            cyclic: self.cyclic,
+            ..CompilerConfig::default()
+        };
+        if let Some(days) = self.days {
+            config.horizon_days = days.max(1);
+        }
+        if let Some(weekday) = self.first_weekday {
+            config.first_weekday = weekday;
+        }
+
+        // A window or preference for a name the spec doesn't have would otherwise be ignored
+        let unresolved = |field: String| ScheduleError::UnresolvedReference { reference: field };
+        let is_entity = |name: &String| self.entities.iter().any(|entity| &entity.name == name);
+        let is_category =
+            |name: &String| self.entities.iter().any(|entity| &entity.category == name);
+
+        for (category, window) in &self.category_windows {
+            let field = format!("category_windows.{}", category);
+            if !is_category(category) {
+                return Err(unresolved(field));
+            }
+            config
+                .category_windows
+                .insert(category.clone(), window.minutes(&field)?);
+        }
+        for (entity, window) in &self.entity_windows {
+            let field = format!("entity_windows.{}", entity);
+            if !is_entity(entity) {
+                return Err(unresolved(field));
+            }
+            config
+                .entity_windows
+                .insert(entity.clone(), window.minutes(&field)?);
+        }
+
+        for (entity, preference) in &self.preferences {
+            let field = format!("preferences.{}", entity);
+            if !is_entity(entity) {
+                return Err(unresolved(field));
+            }
+            let mut times = preference
+                .times
+                .iter()
+                .map(|time| parse_time_of_day(time).map_err(|e| e.in_field(field.clone())))
+                .collect::<Result<Vec<_>, _>>()?;
+            times.sort();
+            config.preferences.insert(
+                entity.clone(),
+                Preference {
+                    times,
+                    weight: preference.weight.unwrap_or(ANCHOR_WEIGHT),
+                },
+            );
+        }
+
+        Ok(config)
+    }
+}
+
+impl EntitySpec {
+    pub fn to_entity(&self) -> Result<Entity, ScheduleError> {
+        let frequency = match &self.frequency {
+            FrequencySpec::Shorthand(s) => Frequency::from_str(s)?,
+            FrequencySpec::Structured(f) => {
+                f.validate()?;
+                f.clone()
+            }
+        };
+
+        let constraints = self
+            .constraints
+            .iter()
+            .map(|constraint| match constraint {
+                ConstraintSpec::Shorthand(s) => ConstraintExpression::parse(s),
+                ConstraintSpec::Structured(c) => Ok(c.clone()),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let windows = self
+            .windows
+            .iter()
+            .map(|window| match window {
+                WindowSpecEntry::Shorthand(s) => WindowSpec::parse(s),
+                WindowSpecEntry::Structured(w) => Ok(*w),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        check_windows(&windows, &frequency)?;
+
+        Ok(Entity {
+            name: self.name.clone(),
+            category: self.category.clone(),
+            unit: self.unit.clone(),
+            amount: self.amount,
+            split: self.split,
+            frequency,
+            min_spacing: Some(1),
+            constraints,
+            windows,
+            note: self.note.clone(),
+            source_row: None,
+        })
+    }
+}
+
+// Convert "HH:MM" to minutes from midnight, allowing "24:00" for the end of the day
+pub fn parse_time_of_day(hhmm: &str) -> Result<i64, ScheduleError> {
+    let error = |message: String| ScheduleError::parse(hhmm, message);
+
+    let (h, m) = hhmm
+        .trim()
+        .split_once(':')
+        .ok_or_else(|| error(format!("Not in HH:MM format: {}", hhmm)))?;
+    let hours: i64 = h.parse().map_err(|_| error(format!("Bad hour in {}", hhmm)))?;
+    let minutes: i64 = m.parse().map_err(|_| error(format!("Bad minute in {}", hhmm)))?;
+
+    if !(0..=59).contains(&minutes) || !(0..=24).contains(&hours) || (hours == 24 && minutes > 0)
+    {
+        return Err(error(format!("Time out of range: {}", hhmm)));
+    }
+
+    Ok(hours * 60 + minutes)
+}
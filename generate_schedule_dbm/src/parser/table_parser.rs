@@ -1,14 +1,93 @@
+use crate::error::{ParseError, ScheduleError};
+use crate::types::constraints::{CategoryConstraint, ConstraintExpression};
 use crate::types::entity::Entity;
-use regex::Regex;
+use crate::types::frequency::Frequency;
+use crate::types::window::{check_windows, WindowSpec};
+use schedule_grammar::parse_string_array;
 
-// Function to parse from the tabular format shown in the example
-pub fn parse_from_table(rows: Vec<Vec<&str>>) -> Result<Vec<Entity>, String> {
+const COLUMN_NAMES: [&str; 8] = [
+    "Entity",
+    "Category",
+    "Unit",
+    "Amount",
+    "Split",
+    "Frequency",
+    "Constraints",
+    "Note",
+];
+
+// A row starting with this ends the entity rows; each row after it holds one category
+// constraint in its first cell ("med ≥30m apart from food")
+const CATEGORY_BLOCK: &str = "Category Constraints";
+
+// Attach a table position to an error (rows and columns are 1-based, the header is row 1)
+fn cell_error(row: usize, column: usize, error: ScheduleError) -> ScheduleError {
+    error.at_cell(row, column + 1, COLUMN_NAMES[column])
+}
+
+// The index of the row opening the category constraints block, if the table has one
+fn category_block_start(rows: &[Vec<&str>]) -> Option<usize> {
+    rows.iter().position(|row| {
+        row.first()
+            .is_some_and(|cell| cell.trim().eq_ignore_ascii_case(CATEGORY_BLOCK))
+    })
+}
+
+// Parse the category constraints block at the end of a table:
+//
+//   Category Constraints
+//   med ≥30m apart from food
+//   care ≤4h after food
+//
+// Blank rows are skipped. A table without the block has no category constraints.
+pub fn parse_category_constraints(
+    rows: &[Vec<&str>],
+) -> Result<Vec<CategoryConstraint>, ScheduleError> {
+    let Some(start) = category_block_start(rows) else {
+        return Ok(Vec::new());
+    };
+
+    rows.iter()
+        .enumerate()
+        .skip(start + 1)
+        .filter(|(_, row)| row.iter().any(|cell| !cell.trim().is_empty()))
+        .map(|(index, row)| {
+            CategoryConstraint::parse(row[0]).map_err(|e| e.at_cell(index + 1, 1, CATEGORY_BLOCK))
+        })
+        .collect()
+}
+
+// Function to parse from the tabular format shown in the example. A "Windows" column (as in
+// the MILP generator's table, before Note) is optional and found by its header. The entity
+// rows end at a category constraints block (see `parse_category_constraints`).
+pub fn parse_from_table(mut rows: Vec<Vec<&str>>) -> Result<Vec<Entity>, ScheduleError> {
     let mut entities = Vec::new();
+    if let Some(start) = category_block_start(&rows) {
+        rows.truncate(start);
+    }
+
+    let header = rows.first().cloned().unwrap_or_default();
+    let find_column = |name: &str| {
+        header
+            .iter()
+            .position(|cell| cell.trim().eq_ignore_ascii_case(name))
+    };
+    let windows_column = find_column("Windows");
+    let note_column = find_column("Note").unwrap_or(if windows_column == Some(7) { 8 } else { 7 });
 
     // Skip header row
-    for row in rows.iter().skip(1) {
+    for (index, row) in rows.iter().enumerate().skip(1) {
+        let row_number = index + 1;
+
         if row.len() < 7 {
-            return Err("Row has insufficient columns".to_string());
+            return Err(ScheduleError::Parse(ParseError {
+                row: Some(row_number),
+                column: None,
+                field: None,
+                expression: row.join(","),
+                message: format!("expected at least 7 columns, got {}", row.len()),
+                span: None,
+            }));
         }
 
         let name = row[0];
@@ -18,59 +97,70 @@ pub fn parse_from_table(rows: Vec<Vec<&str>>) -> Result<Vec<Entity>, String> {
         // Parse amount (float or null)
         let amount = match row[3] {
             "null" => None,
-            s => Some(
-                s.parse::<f64>()
-                    .map_err(|_| "Invalid amount format".to_string())?,
-            ),
+            s => Some(s.parse::<f64>().map_err(|_| {
+                let error = ScheduleError::parse(s, format!("Invalid amount format '{}'", s));
+                cell_error(row_number, 3, error)
+            })?),
         };
 
         // Parse split (int or null)
         let split = match row[4] {
             "null" => None,
-            s => Some(
-                s.parse::<i32>()
-                    .map_err(|_| "Invalid split format".to_string())?,
-            ),
+            s => Some(s.parse::<i32>().map_err(|_| {
+                let error = ScheduleError::parse(s, format!("Invalid split format '{}'", s));
+                cell_error(row_number, 4, error)
+            })?),
         };
 
-        let frequency = row[5];
+        let frequency =
+            Frequency::from_str(row[5]).map_err(|e| cell_error(row_number, 5, e))?;
 
         // Parse min_spacing (int or null)
         let min_spacing = Some(1);
 
         // Parse constraints array (from string to vec)
-        let constraints_str = row[6].trim();
-        let constraints = if constraints_str == "[]" {
-            Vec::new()
-        } else {
-            // Extract strings between quotes inside the array
-            let re = Regex::new(r#""([^"]+)""#).unwrap();
-            re.captures_iter(constraints_str)
-                .map(|cap| cap[1].to_string())
-                .collect::<Vec<String>>()
+        let constraints = parse_string_array(row[6])
+            .map_err(|e| cell_error(row_number, 6, e.into()))?
+            .iter()
+            .map(|s| ConstraintExpression::parse(s))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| cell_error(row_number, 6, e))?;
+
+        // Parse windows, e.g. ["08:00", "18:00-20:00"] (optional column)
+        let windows = match windows_column.and_then(|column| row.get(column).map(|cell| (column, cell))) {
+            Some((column, cell)) => parse_string_array(cell)
+                .map_err(ScheduleError::from)
+                .and_then(|items| items.iter().map(|s| WindowSpec::parse(s)).collect())
+                .and_then(|windows: Vec<WindowSpec>| {
+                    check_windows(&windows, &frequency).map(|_| windows)
+                })
+                .map_err(|e| e.at_cell(row_number, column + 1, "Windows"))?,
+            None => Vec::new(),
         };
 
         // Parse note (optional field, default to None if not present)
-        let note = if row.len() > 7 {
-            match row[7] {
-                "null" => None,
-                s => Some(s),
+        let note = if row.len() > note_column {
+            match row[note_column] {
+                "null" | "" => None,
+                s => Some(s.to_string()),
             }
         } else {
             None
         };
 
-        entities.push(Entity::new(
-            name,
-            category,
-            unit,
+        entities.push(Entity {
+            name: name.to_string(),
+            category: category.to_string(),
+            unit: unit.to_string(),
             amount,
             split,
             frequency,
             min_spacing,
-            constraints.iter().map(|s| s.as_str()).collect(),
+            constraints,
+            windows,
             note,
-        )?);
+            source_row: Some(row_number),
+        });
     }
 
     Ok(entities)
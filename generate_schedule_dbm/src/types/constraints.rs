@@ -1,5 +1,7 @@
-use regex::Regex;
+use schedule_grammar as grammar;
 use serde::{Deserialize, Serialize};
+use std::fmt;
+use crate::error::ScheduleError;
 use crate::types::time_unit::TimeUnit;
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -8,102 +10,123 @@ pub enum ConstraintType {
     After,     // Target must be scheduled after reference
     ApartFrom, // Target must be separated from reference (both before and after)
     Apart,     // Used within recurring instances of the same entity
+    With,      // Target must coincide with an instance of the reference, within the time value
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct ConstraintExpression {
     pub time_value: u32,
     pub time_unit: TimeUnit,
     pub constraint_type: ConstraintType,
     pub reference: ConstraintReference,
+    // How `time_value` bounds the gap (a lower bound unless stated otherwise)
+    #[serde(default)]
+    pub bound: GapBound,
+    // Which reference instances each instance is held against (all of them unless stated)
+    #[serde(default)]
+    pub pairing: Pairing,
+}
+
+// How a constraint's time value bounds the gap between the clocks it relates
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+pub enum GapBound {
+    #[default]
+    AtLeast, // "≥1h before food": the gap is at least the time value
+    AtMost,  // "≤30m after food", "within 30m after food": at most the time value
+    Between(u32), // "between 1h and 2h after food": from the time value up to this one
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+// Which instances of the reference a before/after constraint relates each instance to
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+pub enum Pairing {
+    #[default]
+    All,      // "≥2h after food": every reference instance on the same day
+    Nearest,  // "≥2h after nearest food": the nearest reference instance on that side
+    Matching, // "≥2h after its food": the reference instance with the same index in the day
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum ConstraintReference {
     Unresolved(String), // A specific entity by name or all in a category (resolved later)
     WithinGroup,        // For 'apart' constraints within recurring instances
 }
 
-impl ConstraintExpression {
-    pub fn parse(expr: &str) -> Result<Self, String> {
-        // Clean up the input string
-        let expr = expr.trim();
-
-        // Regular expressions for different constraint patterns
-        let before_re = Regex::new(r"^≥(\d+)([hm])\s+before\s+(.+)$").unwrap();
-        let after_re = Regex::new(r"^≥(\d+)([hm])\s+after\s+(.+)$").unwrap();
-        let apart_from_re = Regex::new(r"^≥(\d+)([hm])\s+apart\s+from\s+(.+)$").unwrap();
-        let apart_re = Regex::new(r"^≥(\d+)([hm])\s+apart$").unwrap();
-
-        if let Some(caps) = before_re.captures(expr) {
-            let time_value: u32 = caps[1]
-                .parse()
-                .map_err(|_| "Invalid time value".to_string())?;
-            let time_unit = TimeUnit::from_str(&caps[2])?;
-            let reference_str = parse_reference(&caps[3])?;
-
-            Ok(ConstraintExpression {
-                time_value,
-                time_unit,
-                constraint_type: ConstraintType::Before,
-                reference: ConstraintReference::Unresolved(reference_str),
-            })
-        } else if let Some(caps) = after_re.captures(expr) {
-            let time_value: u32 = caps[1]
-                .parse()
-                .map_err(|_| "Invalid time value".to_string())?;
-            let time_unit = TimeUnit::from_str(&caps[2])?;
-            let reference_str = parse_reference(&caps[3])?;
-
-            Ok(ConstraintExpression {
-                time_value,
-                time_unit,
-                constraint_type: ConstraintType::After,
-                reference: ConstraintReference::Unresolved(reference_str),
-            })
-        } else if let Some(caps) = apart_from_re.captures(expr) {
-            let time_value: u32 = caps[1]
-                .parse()
-                .map_err(|_| "Invalid time value".to_string())?;
-            let time_unit = TimeUnit::from_str(&caps[2])?;
-            let reference_str = parse_reference(&caps[3])?;
-
-            Ok(ConstraintExpression {
-                time_value,
-                time_unit,
-                constraint_type: ConstraintType::ApartFrom,
-                reference: ConstraintReference::Unresolved(reference_str),
-            })
-        } else if let Some(caps) = apart_re.captures(expr) {
-            let time_value: u32 = caps[1]
-                .parse()
-                .map_err(|_| "Invalid time value".to_string())?;
-            let time_unit = TimeUnit::from_str(&caps[2])?;
-
-            Ok(ConstraintExpression {
-                time_value,
-                time_unit,
-                constraint_type: ConstraintType::Apart,
-                reference: ConstraintReference::WithinGroup,
-            })
-        } else {
-            Err(format!("Could not parse constraint expression: {}", expr))
+// The DSL bound as stored: (time value, unit, bound). A range given in mixed units is stored in
+// minutes.
+fn gap_of(bound: grammar::Bound) -> (u32, TimeUnit, GapBound) {
+    match bound {
+        grammar::Bound::AtLeast(duration) => (duration.value, duration.unit.into(), GapBound::AtLeast),
+        grammar::Bound::AtMost(duration) => (duration.value, duration.unit.into(), GapBound::AtMost),
+        grammar::Bound::Between(low, high) if low.unit == high.unit => {
+            (low.value, low.unit.into(), GapBound::Between(high.value))
+        }
+        grammar::Bound::Between(low, high) => {
+            (low.minutes(), TimeUnit::Minute, GapBound::Between(high.minutes()))
         }
     }
 }
 
-fn parse_reference(reference: &str) -> Result<String, String> {
-    Ok(reference.trim().to_string())
+fn constraint_type_of(relation: grammar::Relation) -> ConstraintType {
+    match relation {
+        grammar::Relation::Before => ConstraintType::Before,
+        grammar::Relation::After => ConstraintType::After,
+        grammar::Relation::ApartFrom => ConstraintType::ApartFrom,
+        grammar::Relation::Apart => ConstraintType::Apart,
+        grammar::Relation::With => ConstraintType::With,
+    }
+}
+
+impl ConstraintExpression {
+    // "≥1h before food", "≤12h apart", "between 1h and 2h after its food", "with food" (see
+    // the schedule_grammar crate for the full grammar)
+    pub fn parse(expr: &str) -> Result<Self, ScheduleError> {
+        let constraint = grammar::parse_constraint(expr.trim())?;
+        let (time_value, time_unit, bound) = gap_of(constraint.bound);
+
+        Ok(ConstraintExpression {
+            time_value,
+            time_unit,
+            constraint_type: constraint_type_of(constraint.relation),
+            // Stored in its canonical form, so that the same reference written differently
+            // ("(food)", "food") compares equal
+            reference: match constraint.reference {
+                Some(reference) => ConstraintReference::Unresolved(reference.to_string()),
+                None => ConstraintReference::WithinGroup,
+            },
+            bound,
+            pairing: match constraint.pairing {
+                grammar::Pairing::All => Pairing::All,
+                grammar::Pairing::Nearest => Pairing::Nearest,
+                grammar::Pairing::Matching => Pairing::Matching,
+            },
+        })
+    }
+
+    // The allowed gap in minutes, as (minimum, maximum if any)
+    pub fn gap_minutes(&self) -> (i64, Option<i64>) {
+        gap_minutes(self.time_value, &self.time_unit, self.bound)
+    }
+}
+
+fn gap_minutes(time_value: u32, time_unit: &TimeUnit, bound: GapBound) -> (i64, Option<i64>) {
+    let minutes = |value: u32| time_unit.to_minutes(value) as i64;
+    match bound {
+        GapBound::AtLeast => (minutes(time_value), None),
+        GapBound::AtMost => (0, Some(minutes(time_value))),
+        GapBound::Between(high) => (minutes(time_value), Some(minutes(high))),
+    }
 }
 
 // New struct for category-level constraints
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct CategoryConstraint {
     pub from_category: String,
     pub to_category: String,
     pub constraint_type: ConstraintType,
     pub time_value: u32,
     pub time_unit: TimeUnit,
+    #[serde(default)]
+    pub bound: GapBound,
 }
 
 impl CategoryConstraint {
@@ -120,66 +143,104 @@ impl CategoryConstraint {
             constraint_type,
             time_value,
             time_unit,
+            bound: GapBound::AtLeast,
         }
     }
 
-    // Parse from a string format like "Category1 >= 2h before Category2"
-    pub fn parse(expr: &str) -> Result<Self, String> {
-        // Clean up the input string
-        let expr = expr.trim();
-
-        // Regular expressions for different constraint patterns
-        let cat_before_re = Regex::new(r"^([^\s]+)\s+≥(\d+)([hm])\s+before\s+([^\s]+)$").unwrap();
-        let cat_after_re = Regex::new(r"^([^\s]+)\s+≥(\d+)([hm])\s+after\s+([^\s]+)$").unwrap();
-        let cat_apart_from_re = Regex::new(r"^([^\s]+)\s+≥(\d+)([hm])\s+apart\s+from\s+([^\s]+)$").unwrap();
-
-        if let Some(caps) = cat_before_re.captures(expr) {
-            let from_category = caps[1].trim().to_string();
-            let time_value: u32 = caps[2]
-                .parse()
-                .map_err(|_| "Invalid time value".to_string())?;
-            let time_unit = TimeUnit::from_str(&caps[3])?;
-            let to_category = caps[4].trim().to_string();
-
-            Ok(CategoryConstraint {
-                from_category,
-                to_category,
-                constraint_type: ConstraintType::Before,
-                time_value,
-                time_unit,
-            })
-        } else if let Some(caps) = cat_after_re.captures(expr) {
-            let from_category = caps[1].trim().to_string();
-            let time_value: u32 = caps[2]
-                .parse()
-                .map_err(|_| "Invalid time value".to_string())?;
-            let time_unit = TimeUnit::from_str(&caps[3])?;
-            let to_category = caps[4].trim().to_string();
-
-            Ok(CategoryConstraint {
-                from_category,
-                to_category,
-                constraint_type: ConstraintType::After,
-                time_value,
-                time_unit,
-            })
-        } else if let Some(caps) = cat_apart_from_re.captures(expr) {
-            let from_category = caps[1].trim().to_string();
-            let time_value: u32 = caps[2]
-                .parse()
-                .map_err(|_| "Invalid time value".to_string())?;
-            let time_unit = TimeUnit::from_str(&caps[3])?;
-            let to_category = caps[4].trim().to_string();
-
-            Ok(CategoryConstraint {
-                from_category,
-                to_category,
-                constraint_type: ConstraintType::ApartFrom,
-                time_value,
-                time_unit,
-            })
-        } else {
-            Err(format!("Could not parse category constraint expression: {}", expr))
+    // Parse from a string format like "Category1 ≥2h before Category2" (or with "≤", "within"
+    // or "between" bounds, as for entity constraints)
+    pub fn parse(expr: &str) -> Result<Self, ScheduleError> {
+        let constraint = grammar::parse_category_constraint(expr.trim())?;
+        let (time_value, time_unit, bound) = gap_of(constraint.bound);
+
+        Ok(CategoryConstraint {
+            from_category: constraint.from,
+            to_category: constraint.to,
+            constraint_type: constraint_type_of(constraint.relation),
+            time_value,
+            time_unit,
+            bound,
+        })
+    }
+
+    // The allowed gap in minutes, as (minimum, maximum if any)
+    pub fn gap_minutes(&self) -> (i64, Option<i64>) {
+        gap_minutes(self.time_value, &self.time_unit, self.bound)
+    }
+}
+
+impl fmt::Display for ConstraintType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConstraintType::Before => write!(f, "before"),
+            ConstraintType::After => write!(f, "after"),
+            ConstraintType::ApartFrom => write!(f, "apart from"),
+            ConstraintType::Apart => write!(f, "apart"),
+            ConstraintType::With => write!(f, "with"),
+        }
+    }
+}
+
+// The bound in DSL form: "≥1h", "≤30m" or "between 1h and 2h"
+fn write_gap(
+    f: &mut fmt::Formatter<'_>,
+    time_value: u32,
+    time_unit: &TimeUnit,
+    bound: GapBound,
+) -> fmt::Result {
+    match bound {
+        GapBound::AtLeast => write!(f, "≥{}{}", time_value, time_unit),
+        GapBound::AtMost => write!(f, "≤{}{}", time_value, time_unit),
+        GapBound::Between(high) => write!(
+            f,
+            "between {}{} and {}{}",
+            time_value, time_unit, high, time_unit
+        ),
+    }
+}
+
+// The reference in its canonical form, or as stored if it doesn't parse (a structured spec
+// entry), so that it reads back as the same reference whatever it was written as
+fn canonical_reference(reference: &str) -> String {
+    grammar::Reference::parse(reference)
+        .map(|reference| reference.to_string())
+        .unwrap_or_else(|_| reference.to_string())
+}
+
+// Written back in the canonical DSL form, e.g. "≥1h before food", "≥2h after its food" or
+// "≥6h apart", which parses to an equal expression
+impl fmt::Display for ConstraintExpression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let (ConstraintType::With, ConstraintReference::Unresolved(reference)) =
+            (&self.constraint_type, &self.reference)
+        {
+            // Plain "with" parses to a zero tolerance in minutes
+            let reference = canonical_reference(reference);
+            return match (self.time_value, &self.time_unit) {
+                (0, TimeUnit::Minute) => write!(f, "with {}", reference),
+                _ => write!(f, "within {}{} of {}", self.time_value, self.time_unit, reference),
+            };
         }
+        write_gap(f, self.time_value, &self.time_unit, self.bound)?;
+        write!(f, " {}", self.constraint_type)?;
+        match self.pairing {
+            Pairing::All => {}
+            Pairing::Nearest => write!(f, " nearest")?,
+            Pairing::Matching => write!(f, " its")?,
+        }
+        match &self.reference {
+            ConstraintReference::Unresolved(reference) => {
+                write!(f, " {}", canonical_reference(reference))
+            }
+            ConstraintReference::WithinGroup => Ok(()),
+        }
+    }
+}
+
+impl fmt::Display for CategoryConstraint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ", self.from_category)?;
+        write_gap(f, self.time_value, &self.time_unit, self.bound)?;
+        write!(f, " {} {}", self.constraint_type, self.to_category)
     }
 }
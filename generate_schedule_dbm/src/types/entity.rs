@@ -1,5 +1,7 @@
+use crate::error::ScheduleError;
 use crate::types::constraints::ConstraintExpression;
 use crate::types::frequency::Frequency;
+use crate::types::window::WindowSpec;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -13,7 +15,13 @@ pub struct Entity {
     // Optional minimum spacing (in minutes) between instances
     pub min_spacing: Option<i32>,
     pub constraints: Vec<ConstraintExpression>,
+    // Anchors (preferred times) and ranges (hard windows) for the instances
+    #[serde(default)]
+    pub windows: Vec<WindowSpec>,
     pub note: Option<String>,
+    // Table row the entity was read from (1-based, header is row 1), for error reporting
+    #[serde(default)]
+    pub source_row: Option<usize>,
 }
 
 impl Entity {
@@ -27,7 +35,7 @@ impl Entity {
         min_spacing: Option<i32>,
         constraints: Vec<&str>,
         note: Option<&str>,
-    ) -> Result<Self, String> {
+    ) -> Result<Self, ScheduleError> {
         let frequency = Frequency::from_str(frequency_str)?;
 
         let constraint_expressions = constraints
@@ -44,7 +52,9 @@ impl Entity {
             frequency,
             min_spacing,
             constraints: constraint_expressions,
+            windows: Vec::new(),
             note: note.map(|s| s.to_string()),
+            source_row: None,
         })
     }
 }
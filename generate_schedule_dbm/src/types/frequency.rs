@@ -1,49 +1,368 @@
-use regex::Regex;
+use crate::error::ScheduleError;
+use schedule_grammar as grammar;
 use serde::{Deserialize, Serialize};
+use std::fmt;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum Frequency {
-    Daily,               // Once daily (aliases: "1x daily", "1x /d", "1x /1d")
-    TwiceDaily,          // Twice daily (aliases: "2x daily", "2x /d", "2x /1d")
-    ThreeTimesDaily,     // Three times daily (aliases: "3x daily", "3x /d", "3x /1d")
-    EveryXHours(u8),     // Every X hours
-    Custom(Vec<String>), // For custom time specifications
+    TimesPerDay(u32),              // N times a day ("daily", "2x daily", "3 times a day")
+    EveryXHours(u32),              // Every X hours, kept X hours apart (see `instances_per_day`)
+    EveryXMinutes(u32),            // Every X minutes ("every 90 minutes"), kept X minutes apart
+    EveryXDays(u32),               // Once on every Xth day ("every 2 days", "every other day")
+    OnWeekdays(Vec<Weekday>),      // Once on each listed weekday ("Mon/Wed/Fri", "weekdays")
+    TimesPerWeek(u32),             // N times a week on evenly spread days ("3x weekly")
+    Weekly,                        // Once a week (aliases: "1x weekly", "once weekly")
+    AsNeeded { max_per_day: u32 }, // PRN, up to N a day ("as needed, max 3 daily"), unscheduled
+    Custom(Vec<String>),           // Fixed clock times ("at 08:00, 14:00, 20:00 ±30m")
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Weekday {
+    #[serde(alias = "monday")]
+    Mon,
+    #[serde(alias = "tuesday")]
+    Tue,
+    #[serde(alias = "wednesday")]
+    Wed,
+    #[serde(alias = "thursday")]
+    Thu,
+    #[serde(alias = "friday")]
+    Fri,
+    #[serde(alias = "saturday")]
+    Sat,
+    #[serde(alias = "sunday")]
+    Sun,
+}
+
+impl Weekday {
+    pub const ALL: [Weekday; 7] = [
+        Weekday::Mon,
+        Weekday::Tue,
+        Weekday::Wed,
+        Weekday::Thu,
+        Weekday::Fri,
+        Weekday::Sat,
+        Weekday::Sun,
+    ];
+
+    // "mon", "Tues", "wednesday", ... (any prefix of the name of at least three letters)
+    pub fn parse(s: &str) -> Result<Self, ScheduleError> {
+        grammar::Weekday::parse(s)
+            .map(|day| Weekday::ALL[day.index()])
+            .ok_or_else(|| ScheduleError::parse(s, format!("Unknown weekday: {}", s)))
+    }
+
+    fn full_name(&self) -> &'static str {
+        match self {
+            Weekday::Mon => "monday",
+            Weekday::Tue => "tuesday",
+            Weekday::Wed => "wednesday",
+            Weekday::Thu => "thursday",
+            Weekday::Fri => "friday",
+            Weekday::Sat => "saturday",
+            Weekday::Sun => "sunday",
+        }
+    }
+
+    // The weekday `days` days after this one
+    pub fn plus_days(self, days: usize) -> Weekday {
+        let index = Weekday::ALL.iter().position(|&day| day == self).unwrap_or(0);
+        Weekday::ALL[(index + days) % 7]
+    }
+}
+
+impl fmt::Display for Weekday {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = self.full_name();
+        write!(f, "{}{}", name[..1].to_uppercase(), &name[1..3])
+    }
 }
 
 impl Frequency {
-    pub fn from_str(freq_str: &str) -> Result<Self, String> {
-        // Normalize the string (lowercase, remove extra spaces)
-        let freq_str = freq_str.trim().to_lowercase();
-
-        // Regular expressions for matching different formats
-        let daily_re = Regex::new(r"^(daily|1x\s*daily|1x\s*/d|1x\s*/1d)$").unwrap();
-        let twice_re = Regex::new(r"^(twice\s*daily|2x\s*daily|2x\s*/d|2x\s*/1d)$").unwrap();
-        let thrice_re = Regex::new(r"^(thrice\s*daily|3x\s*daily|3x\s*/d|3x\s*/1d)$").unwrap();
-        let every_hours_re = Regex::new(r"^every\s*(\d+)\s*hours?$").unwrap();
-
-        if daily_re.is_match(&freq_str) {
-            Ok(Frequency::Daily)
-        } else if twice_re.is_match(&freq_str) {
-            Ok(Frequency::TwiceDaily)
-        } else if thrice_re.is_match(&freq_str) {
-            Ok(Frequency::ThreeTimesDaily)
-        } else if let Some(caps) = every_hours_re.captures(&freq_str) {
-            let hours: u8 = caps[1]
-                .parse()
-                .map_err(|_| "Invalid hour format".to_string())?;
-            Ok(Frequency::EveryXHours(hours))
-        } else {
-            Err(format!("Unrecognized frequency format: {}", freq_str))
+    // "daily", "2x daily", "every 8 hours", "Mon/Wed/Fri", "as needed, max 3 daily",
+    // "at 08:00, 20:00 ±30m", ... (see the schedule_grammar crate for the full grammar)
+    pub fn from_str(freq_str: &str) -> Result<Self, ScheduleError> {
+        let freq_str = freq_str.trim();
+        let invalid = |message: String| ScheduleError::parse(freq_str, message);
+
+        let frequency = match grammar::parse_frequency(freq_str)? {
+            grammar::Frequency::TimesPerDay(n) => Frequency::TimesPerDay(n),
+            grammar::Frequency::EveryHours(hours) => Frequency::EveryXHours(hours),
+            grammar::Frequency::EveryMinutes(minutes) => Frequency::EveryXMinutes(minutes),
+            grammar::Frequency::EveryDays(days) => Frequency::EveryXDays(days),
+            grammar::Frequency::OnWeekdays(days) => Frequency::OnWeekdays(
+                days.into_iter().map(|day| Weekday::ALL[day.index()]).collect(),
+            ),
+            grammar::Frequency::TimesPerWeek(1) => Frequency::Weekly,
+            grammar::Frequency::TimesPerWeek(n) => Frequency::TimesPerWeek(n),
+            grammar::Frequency::AsNeeded {
+                max_per_day: Some(max_per_day),
+            } => Frequency::AsNeeded { max_per_day },
+            grammar::Frequency::AsNeeded { max_per_day: None } => {
+                return Err(invalid(format!(
+                    "'{}' needs a maximum, e.g. 'as needed, max 3 daily'",
+                    freq_str
+                )))
+            }
+            grammar::Frequency::At(times) => Frequency::Custom(canonical_fixed_times(
+                freq_str,
+                times
+                    .into_iter()
+                    .map(|time| {
+                        (
+                            time.minutes as i64,
+                            time.tolerance.map(|tolerance| tolerance.minutes() as i64),
+                        )
+                    })
+                    .collect(),
+            )?),
+        };
+        frequency.validate()?;
+        Ok(frequency)
+    }
+
+    // Check counts and intervals of a frequency built without the parser (a structured spec
+    // entry), which would otherwise divide by zero or allocate nothing
+    pub fn validate(&self) -> Result<(), ScheduleError> {
+        let zero = match self {
+            Frequency::TimesPerDay(0)
+            | Frequency::EveryXMinutes(0)
+            | Frequency::EveryXDays(0)
+            | Frequency::TimesPerWeek(0)
+            | Frequency::AsNeeded { max_per_day: 0 } => true,
+            Frequency::EveryXHours(hours) => *hours == 0,
+            Frequency::OnWeekdays(days) => days.is_empty(),
+            Frequency::Custom(times) => times.is_empty(),
+            _ => false,
+        };
+        if zero {
+            return Err(ScheduleError::parse(
+                self.to_string(),
+                format!(
+                    "'{}' has no instances: counts and intervals must be at least 1",
+                    self
+                ),
+            ));
+        }
+
+        match self {
+            Frequency::TimesPerWeek(n) if *n > 7 => Err(ScheduleError::parse(
+                self.to_string(),
+                format!(
+                    "{}x weekly is more than once a day; use a daily frequency instead",
+                    n
+                ),
+            )),
+            Frequency::Custom(_) => self.fixed_times().transpose().map(|_| ()),
+            _ => Ok(()),
         }
     }
 
+    // Over more than one day, an interval must divide the day, so that its instances come at the
+    // same times every day. "every 5 hours" would drift by an hour a day, which neither the
+    // instances of a day nor a spacing kept across midnight can follow exactly.
+    pub fn check_horizon(&self, days: usize) -> Result<(), ScheduleError> {
+        let interval = match self {
+            Frequency::EveryXHours(hours) => *hours as i64 * 60,
+            Frequency::EveryXMinutes(minutes) => *minutes as i64,
+            _ => return Ok(()),
+        };
+        if days <= 1 || (interval > 0 && (24 * 60) % interval == 0) {
+            return Ok(());
+        }
+
+        let instead = if interval % (24 * 60) == 0 {
+            format!("'every {} days'", interval / (24 * 60))
+        } else {
+            "an interval that divides 24 hours ('every 6 hours', 'every 90 minutes') or a count \
+             ('5x daily')"
+                .to_string()
+        };
+        Err(ScheduleError::parse(
+            self.to_string(),
+            format!(
+                "'{}' doesn't divide the day, so its times would drift from one day to the next; \
+                 over {} days use {}",
+                self, days, instead
+            ),
+        ))
+    }
+
     pub fn get_instances_per_day(&self) -> usize {
+        self.instance_days(1, Weekday::Mon, 1440).len()
+    }
+
+    // Instances on each day that has any, or None if the instances are spread over the whole
+    // horizon: hour intervals that don't divide the day ("every 5 hours", "every 36 hours")
+    // keep their rhythm across midnight instead of restarting every day
+    pub fn instances_per_day(&self) -> Option<usize> {
+        match self {
+            Frequency::TimesPerDay(n) => Some(*n as usize),
+            Frequency::EveryXHours(hours) if *hours > 0 && 24 % *hours == 0 => {
+                Some(24 / *hours as usize)
+            }
+            Frequency::EveryXMinutes(minutes) => Some(1439 / (*minutes).max(1) as usize + 1),
+            Frequency::EveryXDays(_) | Frequency::OnWeekdays(_) | Frequency::TimesPerWeek(_) => {
+                Some(1)
+            }
+            Frequency::AsNeeded { .. } => Some(0),
+            Frequency::EveryXHours(_) | Frequency::Weekly => None,
+            Frequency::Custom(times) => Some(times.len()),
+        }
+    }
+
+    // The (time, tolerance) of each instance of the day for fixed clock times, in minutes,
+    // earliest first; None for other frequencies
+    pub fn fixed_times(&self) -> Option<Result<Vec<(i64, i64)>, ScheduleError>> {
+        match self {
+            Frequency::Custom(times) => Some(
+                times
+                    .iter()
+                    .map(|time| {
+                        let (minutes, tolerance) = parse_fixed_time(time)?;
+                        Ok((minutes, tolerance.unwrap_or(0)))
+                    })
+                    .collect::<Result<Vec<_>, ScheduleError>>()
+                    .map(|mut times| {
+                        times.sort();
+                        times
+                    }),
+            ),
+            _ => None,
+        }
+    }
+
+    // Minimum gap, in minutes, between consecutive instances implied by the frequency itself
+    // (None when only the entity's own spacing applies)
+    pub fn implied_spacing(&self) -> Option<i64> {
+        match self {
+            Frequency::EveryXHours(hours) => Some(*hours as i64 * 60),
+            Frequency::EveryXMinutes(minutes) => Some(*minutes as i64),
+            Frequency::Weekly => Some(7 * 24 * 60),
+            _ => None,
+        }
+    }
+
+    // The day (1-based) of each instance in a horizon of `days` days, in instance order; None
+    // for instances spread over the horizon, which may land on any day. `first_weekday` is the
+    // weekday of day 1, and `window_minutes` the length of the entity's window, which sets how
+    // many minute intervals fit in a day.
+    pub fn instance_days(
+        &self,
+        days: usize,
+        first_weekday: Weekday,
+        window_minutes: i64,
+    ) -> Vec<Option<usize>> {
+        let on_days = |scheduled: &dyn Fn(usize) -> bool| -> Vec<Option<usize>> {
+            (1..=days).filter(|&day| scheduled(day)).map(Some).collect()
+        };
+
+        match self {
+            Frequency::EveryXDays(interval) => on_days(&|day| (day - 1) % (*interval).max(1) as usize == 0),
+            Frequency::OnWeekdays(weekdays) => {
+                on_days(&|day| weekdays.contains(&first_weekday.plus_days(day - 1)))
+            }
+            Frequency::TimesPerWeek(n) => {
+                // Evenly spread over each week of the horizon: days 1, 3 and 5 for 3x weekly
+                let n = *n as usize;
+                let week_days: Vec<usize> = (0..n).map(|k| k * 7 / n).collect();
+                on_days(&|day| week_days.contains(&((day - 1) % 7)))
+            }
+            Frequency::EveryXHours(hours) if self.instances_per_day().is_none() => {
+                vec![None; (days * 24 / (*hours).max(1) as usize).max(1)]
+            }
+            Frequency::Weekly => vec![None; days.div_ceil(7)],
+            // Taken when needed, so there is nothing to schedule: the maximum and the entity's
+            // constraints are listed with the schedule instead
+            Frequency::AsNeeded { .. } => Vec::new(),
+            Frequency::EveryXMinutes(minutes) => {
+                // Both ends of the window, so 07:00-23:00 every 90 minutes is 07:00, ..., 22:00
+                let minutes = (*minutes).max(1) as i64;
+                let per_day = ((window_minutes - 1).max(0) / minutes + 1) as usize;
+                (1..=days)
+                    .flat_map(|day| std::iter::repeat_n(Some(day), per_day))
+                    .collect()
+            }
+            _ => {
+                let per_day = self.instances_per_day().unwrap_or(1);
+                (1..=days)
+                    .flat_map(|day| std::iter::repeat_n(Some(day), per_day))
+                    .collect()
+            }
+        }
+    }
+}
+
+// The times of "at 08:00, 14:00, 20:00 ±30m", as (minutes, tolerance): a tolerance on the last
+// time only applies to all of them. Returns the canonical entries ("08:00±30m"), earliest first.
+fn canonical_fixed_times(
+    text: &str,
+    mut times: Vec<(i64, Option<i64>)>,
+) -> Result<Vec<String>, ScheduleError> {
+    let with_tolerance = times.iter().filter(|(_, tolerance)| tolerance.is_some()).count();
+    if with_tolerance == 1 && times.len() > 1 {
+        if let Some(&(_, Some(shared))) = times.last() {
+            for (_, tolerance) in times.iter_mut() {
+                *tolerance = Some(shared);
+            }
+        }
+    }
+
+    times.sort();
+    if let Some(pair) = times.windows(2).find(|pair| pair[0].0 == pair[1].0) {
+        return Err(ScheduleError::parse(
+            text,
+            format!("{} is listed twice", format_fixed_time(pair[0].0, None)),
+        ));
+    }
+
+    // Write every tolerance out once any time has one, so the entries read back the same
+    let any_tolerance = times.iter().any(|(_, tolerance)| tolerance.is_some());
+    Ok(times
+        .into_iter()
+        .map(|(minutes, tolerance)| {
+            format_fixed_time(minutes, tolerance.or(any_tolerance.then_some(0)))
+        })
+        .collect())
+}
+
+// "08:00" or "08:00 ±30m" (also "+/-"), as minutes from midnight and a tolerance in minutes
+fn parse_fixed_time(entry: &str) -> Result<(i64, Option<i64>), ScheduleError> {
+    let time = grammar::parse_fixed_time(entry.trim())?;
+    Ok((
+        time.minutes as i64,
+        time.tolerance.map(|tolerance| tolerance.minutes() as i64),
+    ))
+}
+
+fn format_fixed_time(minutes: i64, tolerance: Option<i64>) -> String {
+    let time = format!("{:02}:{:02}", minutes / 60, minutes % 60);
+    match tolerance {
+        Some(tolerance) => format!("{}±{}m", time, tolerance),
+        None => time,
+    }
+}
+
+// Written back in the canonical DSL form, which parses to the same frequency
+impl fmt::Display for Frequency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Frequency::Daily => 1,
-            Frequency::TwiceDaily => 2,
-            Frequency::ThreeTimesDaily => 3,
-            Frequency::EveryXHours(hours) => 24 / *hours as usize,
-            Frequency::Custom(times) => times.len(),
+            Frequency::TimesPerDay(1) => write!(f, "daily"),
+            Frequency::TimesPerDay(n) => write!(f, "{}x daily", n),
+            Frequency::EveryXHours(hours) => write!(f, "every {} hours", hours),
+            Frequency::EveryXMinutes(minutes) => write!(f, "every {} minutes", minutes),
+            Frequency::EveryXDays(days) => write!(f, "every {} days", days),
+            Frequency::OnWeekdays(days) => {
+                let names: Vec<String> = days.iter().map(|day| day.to_string()).collect();
+                write!(f, "{}", names.join("/"))
+            }
+            Frequency::TimesPerWeek(n) => write!(f, "{}x weekly", n),
+            Frequency::Weekly => write!(f, "weekly"),
+            Frequency::AsNeeded { max_per_day } => {
+                write!(f, "as needed, max {} daily", max_per_day)
+            }
+            Frequency::Custom(times) => write!(f, "at {}", times.join(", ")),
         }
     }
 }
@@ -3,3 +3,4 @@ pub mod frequency;
 pub mod entity;
 pub mod time_unit;
 pub mod constraints;
+pub mod window;
@@ -1,4 +1,6 @@
+use crate::error::ScheduleError;
 use serde::{Deserialize, Serialize};
+use std::fmt;
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum TimeUnit {
@@ -7,11 +9,11 @@ pub enum TimeUnit {
 }
 
 impl TimeUnit {
-    pub fn from_str(s: &str) -> Result<Self, String> {
+    pub fn from_str(s: &str) -> Result<Self, ScheduleError> {
         match s.to_lowercase().as_str() {
             "m" | "min" | "minute" | "minutes" => Ok(TimeUnit::Minute),
             "h" | "hr" | "hour" | "hours" => Ok(TimeUnit::Hour),
-            _ => Err(format!("Unknown time unit: {}", s)),
+            _ => Err(ScheduleError::parse(s, format!("Unknown time unit: {}", s))),
         }
     }
 
@@ -22,3 +24,22 @@ impl TimeUnit {
         }
     }
 }
+
+impl From<schedule_grammar::Unit> for TimeUnit {
+    fn from(unit: schedule_grammar::Unit) -> Self {
+        match unit {
+            schedule_grammar::Unit::Minute => TimeUnit::Minute,
+            schedule_grammar::Unit::Hour => TimeUnit::Hour,
+        }
+    }
+}
+
+// The DSL suffix ("h" or "m")
+impl fmt::Display for TimeUnit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TimeUnit::Minute => write!(f, "m"),
+            TimeUnit::Hour => write!(f, "h"),
+        }
+    }
+}
@@ -0,0 +1,82 @@
+use crate::error::ScheduleError;
+use crate::parser::spec::parse_time_of_day;
+use crate::types::frequency::Frequency;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+// A time window for an entity's instances, in minutes from midnight (same shape as the MILP
+// generator's `domain::WindowSpec`):
+//   - Anchor: a preferred time, used by extraction as a target ("08:00")
+//   - Range: a hard window the instance must fall in ("18:00-20:00")
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum WindowSpec {
+    Anchor(i64),
+    Range(i64, i64),
+}
+
+impl WindowSpec {
+    // Parse "HH:MM" as an anchor or "HH:MM-HH:MM" as a range
+    pub fn parse(s: &str) -> Result<Self, ScheduleError> {
+        let s = s.trim();
+        match s.split_once('-') {
+            Some((start, end)) => {
+                let start = parse_time_of_day(start)?;
+                let end = parse_time_of_day(end)?;
+                if end < start {
+                    return Err(ScheduleError::parse(
+                        s,
+                        format!("Window range is reversed: {}", s),
+                    ));
+                }
+                Ok(WindowSpec::Range(start, end))
+            }
+            None => Ok(WindowSpec::Anchor(parse_time_of_day(s)?)),
+        }
+    }
+
+    // Start of the window, for ordering windows through the day
+    pub fn start(&self) -> i64 {
+        match *self {
+            WindowSpec::Anchor(anchor) => anchor,
+            WindowSpec::Range(start, _) => start,
+        }
+    }
+}
+
+// Anchors and ranges can be mixed only with one window per instance of the day, where
+// instance k uses window k; otherwise it would be unclear which instances must use a range
+pub fn check_windows(windows: &[WindowSpec], frequency: &Frequency) -> Result<(), ScheduleError> {
+    let per_day = frequency.instances_per_day();
+    let has_anchor = windows.iter().any(|w| matches!(w, WindowSpec::Anchor(_)));
+    let has_range = windows.iter().any(|w| matches!(w, WindowSpec::Range(..)));
+
+    if has_anchor && has_range && per_day != Some(windows.len()) {
+        let listed = windows
+            .iter()
+            .map(|window| window.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        return Err(ScheduleError::parse(
+            listed.as_str(),
+            format!(
+                "{} windows for {} instances a day ({}); anchors and ranges can only be mixed \
+                 with one window per instance",
+                windows.len(),
+                per_day.map_or("a varying number of".to_string(), |n| n.to_string()),
+                listed
+            ),
+        ));
+    }
+
+    Ok(())
+}
+
+impl fmt::Display for WindowSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let hhmm = |minutes: i64| format!("{:02}:{:02}", minutes / 60, minutes % 60);
+        match *self {
+            WindowSpec::Anchor(anchor) => write!(f, "{}", hhmm(anchor)),
+            WindowSpec::Range(start, end) => write!(f, "{}-{}", hhmm(start), hhmm(end)),
+        }
+    }
+}
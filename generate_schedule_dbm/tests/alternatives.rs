@@ -0,0 +1,79 @@
+// Several feasible schedules, as different from one another as the zones allow
+
+use generate_schedule::{parse_from_table, ScheduleStrategy, TimeConstraintCompiler};
+
+const HEADER: [&str; 8] = [
+    "Entity",
+    "Category",
+    "Unit",
+    "Amount",
+    "Split",
+    "Frequency",
+    "Constraints",
+    "Note",
+];
+
+// A compiled compiler for (entity, frequency, constraints) rows, with the day window 08:00-11:00
+fn compiled(rows: &[(&str, &str, &str)]) -> TimeConstraintCompiler {
+    let mut table = vec![HEADER.to_vec()];
+    for &(name, frequency, constraints) in rows {
+        table.push(vec![name, "med", "tablet", "null", "null", frequency, constraints, "null"]);
+    }
+    let mut compiler = TimeConstraintCompiler::new(parse_from_table(table).unwrap());
+    compiler.set_day_window(8 * 60, 11 * 60);
+    compiler.compile().unwrap();
+    compiler
+}
+
+#[test]
+fn alternatives_cover_each_ordering() {
+    let compiler = compiled(&[("A", "daily", "[\"≥2h apart from B\"]"), ("B", "daily", "[]")]);
+    let alternatives = compiler.alternatives(3, ScheduleStrategy::Earliest).unwrap();
+    assert_eq!(alternatives.len(), 3);
+
+    // The first is the strategy's own schedule, the next the other ordering
+    assert_eq!(
+        alternatives[0].schedule,
+        compiler.finalize_schedule(ScheduleStrategy::Earliest).unwrap()
+    );
+    let mut orderings = vec![alternatives[0].ordering.as_str(), alternatives[1].ordering.as_str()];
+    orderings.sort();
+    assert_eq!(orderings, vec!["A_1 < B_1", "B_1 < A_1"]);
+
+    // Each is feasible, and no two are the same
+    for (index, alternative) in alternatives.iter().enumerate() {
+        assert_eq!(compiler.verify(&alternative.schedule).unwrap(), Vec::new());
+        assert!(alternatives[..index]
+            .iter()
+            .all(|other| other.schedule != alternative.schedule));
+    }
+}
+
+#[test]
+fn fewer_alternatives_when_there_are_fewer_schedules() {
+    // A fixed time leaves one schedule
+    let compiler = compiled(&[("A", "at 09:00", "[]")]);
+    let alternatives = compiler.alternatives(3, ScheduleStrategy::Earliest).unwrap();
+    assert_eq!(alternatives.len(), 1);
+    assert_eq!(alternatives[0].schedule["A_1"], 9 * 60);
+
+    assert!(compiler.alternatives(0, ScheduleStrategy::Earliest).unwrap().is_empty());
+}
+
+#[test]
+fn alternatives_are_the_same_on_every_run() {
+    // Each compiler hashes its maps differently, so this would vary if the disjunctions came in
+    // hash order
+    let alternatives: Vec<_> = (0..8)
+        .map(|_| {
+            compiled(&[
+                ("A", "daily", "[\"≥1h apart from B\", \"≥1h apart from C\"]"),
+                ("B", "daily", "[\"≥1h apart from C\"]"),
+                ("C", "daily", "[]"),
+            ])
+            .alternatives(4, ScheduleStrategy::Earliest)
+            .unwrap()
+        })
+        .collect();
+    assert!(alternatives.windows(2).all(|pair| pair[0] == pair[1]));
+}
@@ -0,0 +1,116 @@
+// The explanation of an infeasible schedule: the smallest set of source constraints that
+// can't all hold, named as the user wrote them
+
+use generate_schedule::{
+    parse_category_constraints, parse_from_table, ConstraintOrigin, ScheduleError,
+    TimeConstraintCompiler,
+};
+
+const HEADER: [&str; 8] = [
+    "Entity",
+    "Category",
+    "Unit",
+    "Amount",
+    "Split",
+    "Frequency",
+    "Constraints",
+    "Note",
+];
+
+// A compiler for (entity, frequency, constraints) rows of meds and the category constraints,
+// with the day window 08:00-12:00
+fn compiler(rows: &[(&str, &str, &str)], category_constraints: &[&str]) -> TimeConstraintCompiler {
+    let mut table = vec![HEADER.to_vec()];
+    for &(name, frequency, constraints) in rows {
+        table.push(vec![name, "med", "tablet", "null", "null", frequency, constraints, "null"]);
+    }
+    table.push(vec!["Category Constraints"]);
+    for &constraint in category_constraints {
+        table.push(vec![constraint]);
+    }
+
+    let category_constraints = parse_category_constraints(&table).unwrap();
+    let mut compiler = TimeConstraintCompiler::new(parse_from_table(table).unwrap());
+    compiler.set_category_constraints(category_constraints);
+    compiler.set_day_window(8 * 60, 12 * 60);
+    compiler
+}
+
+// The conflict compile() reports, which must be minimal
+fn conflict(mut compiler: TimeConstraintCompiler) -> (Vec<ConstraintOrigin>, String) {
+    match compiler.compile() {
+        Err(ScheduleError::Infeasible(infeasibility)) => {
+            assert!(infeasibility.minimal);
+            let message = infeasibility.to_string();
+            (infeasibility.conflict, message)
+        }
+        other => panic!("expected a conflict, got {:?}", other.map(|_| ())),
+    }
+}
+
+#[test]
+fn conflict_names_only_the_constraints_involved() {
+    // Two doses 10h apart don't fit a 4h day; B's rule and A's frequency play no part
+    let (conflict, message) = conflict(compiler(
+        &[("A", "2x daily", "[\"≥10h apart\"]"), ("B", "daily", "[\"≥1h after A\"]")],
+        &[],
+    ));
+
+    assert_eq!(conflict.len(), 2, "{}", message);
+    assert!(conflict.contains(&ConstraintOrigin::DayWindow {
+        start: 8 * 60,
+        end: 12 * 60
+    }));
+    assert!(conflict.contains(&ConstraintOrigin::Entity {
+        entity: "A".to_string(),
+        row: Some(2),
+        expression: "≥10h apart".to_string(),
+    }));
+    assert!(message.starts_with("Schedule is not feasible; these constraints conflict:"));
+    assert!(message.contains("\n  - A (row 2): ≥10h apart"), "{}", message);
+    assert!(!message.contains("B (row 3)"), "{}", message);
+}
+
+#[test]
+fn conflict_through_disjunctions() {
+    // Three meds pairwise 2h apart need 4h between the first and the last, which no ordering
+    // fits in 4h less a minute; the search tries them all before it says so
+    let mut compiler = compiler(
+        &[("A", "daily", "[]"), ("B", "daily", "[]"), ("C", "daily", "[]")],
+        &["med ≥2h apart from med"],
+    );
+    compiler.set_day_window(8 * 60, 12 * 60 - 1);
+    let (conflict, message) = conflict(compiler);
+
+    assert_eq!(
+        conflict,
+        vec![
+            ConstraintOrigin::DayWindow {
+                start: 8 * 60,
+                end: 12 * 60 - 1
+            },
+            ConstraintOrigin::Category {
+                expression: "med ≥2h apart from med".to_string()
+            },
+        ],
+        "{}",
+        message
+    );
+}
+
+#[test]
+fn conflict_is_the_same_on_every_run() {
+    // Either dose rule conflicts with the day window on its own; which one is reported depends
+    // on the order of the constraint groups, which mustn't follow hash order
+    let conflicts: Vec<_> = (0..8)
+        .map(|_| {
+            let rows = [
+                ("A", "2x daily", "[\"≥10h apart\"]"),
+                ("B", "2x daily", "[\"≥10h apart\"]"),
+                ("C", "2x daily", "[\"≥10h apart\"]"),
+            ];
+            conflict(compiler(&rows, &[])).0
+        })
+        .collect();
+    assert!(conflicts.windows(2).all(|pair| pair[0] == pair[1]));
+}
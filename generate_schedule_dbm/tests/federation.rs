@@ -0,0 +1,98 @@
+// The disjunction search: both orderings of an "apart from" pair, a deterministic result, and
+// a clear error when the search budget runs out
+
+use clock_zones::Zone;
+use generate_schedule::{
+    parse_category_constraints, parse_from_table, ScheduleError, TimeConstraintCompiler,
+};
+
+const HEADER: [&str; 8] = [
+    "Entity",
+    "Category",
+    "Unit",
+    "Amount",
+    "Split",
+    "Frequency",
+    "Constraints",
+    "Note",
+];
+
+// A compiler for once-daily meds in a 08:00-11:00 window, each ≥2h apart from the others
+fn meds_apart(names: &[&str]) -> TimeConstraintCompiler {
+    let mut rows = vec![HEADER.to_vec()];
+    for &name in names {
+        rows.push(vec![name, "med", "tablet", "null", "null", "1x daily", "[]", "null"]);
+    }
+    rows.push(vec!["Category Constraints"]);
+    rows.push(vec!["med ≥2h apart from med"]);
+
+    let category_constraints = parse_category_constraints(&rows).unwrap();
+    let mut compiler = TimeConstraintCompiler::new(parse_from_table(rows).unwrap());
+    compiler.set_category_constraints(category_constraints);
+    compiler.set_day_window(8 * 60, 11 * 60);
+    compiler
+}
+
+#[test]
+fn apart_from_keeps_a_zone_for_each_ordering() {
+    let mut compiler = meds_apart(&["A", "B"]);
+    compiler.compile().unwrap();
+    assert_eq!(compiler.federation.len(), 2);
+
+    // One zone has A at the start of the window and B at the end, the other the reverse
+    let a = compiler.clocks["A_1"].variable;
+    let b = compiler.clocks["B_1"].variable;
+    let mut orderings: Vec<(Option<i64>, Option<i64>)> = compiler
+        .federation
+        .iter()
+        .map(|zone| (zone.get_upper_bound(a), zone.get_upper_bound(b)))
+        .collect();
+    orderings.sort();
+    assert_eq!(orderings, vec![(Some(9 * 60), Some(11 * 60)), (Some(11 * 60), Some(9 * 60))]);
+
+    let schedule = compiler.extract_schedule().unwrap();
+    assert!((schedule["A_1"] - schedule["B_1"]).abs() >= 120);
+}
+
+#[test]
+fn search_is_the_same_on_every_run() {
+    // Each compiler hashes its maps differently, so this would vary if the search followed
+    // hash order
+    let schedules: Vec<_> = (0..8)
+        .map(|_| {
+            let mut compiler = meds_apart(&["A", "B", "C", "D"]);
+            compiler.set_day_window(6 * 60, 23 * 60);
+            compiler.set_max_zones(1);
+            compiler.compile().unwrap();
+            compiler.extract_schedule().unwrap()
+        })
+        .collect();
+    assert!(schedules.windows(2).all(|pair| pair[0] == pair[1]));
+}
+
+#[test]
+fn max_zones_caps_the_federation() {
+    let mut compiler = meds_apart(&["A", "B", "C"]);
+    compiler.set_day_window(6 * 60, 23 * 60);
+    compiler.set_max_zones(4);
+    compiler.compile().unwrap();
+    assert_eq!(compiler.federation.len(), 4);
+}
+
+#[test]
+fn search_budget_gives_a_clear_error() {
+    let mut compiler = meds_apart(&["A", "B", "C", "D"]);
+    compiler.set_day_window(6 * 60, 23 * 60);
+    compiler.set_search_budget(1);
+    match compiler.compile() {
+        Err(ScheduleError::SearchLimit { disjunctions, .. }) => assert_eq!(disjunctions, 6),
+        other => panic!("expected a search limit error, got {:?}", other.map(|_| ())),
+    }
+}
+
+#[test]
+fn no_ordering_fits_is_infeasible() {
+    // Three meds 2h apart need 4h, the window has 3h
+    let mut compiler = meds_apart(&["A", "B", "C"]);
+    assert!(matches!(compiler.compile(), Err(ScheduleError::Infeasible(_))));
+}
@@ -0,0 +1,72 @@
+// The instances each kind of frequency allocates
+
+use generate_schedule::{parse_from_table, TimeConstraintCompiler};
+
+const HEADER: [&str; 8] = [
+    "Entity",
+    "Category",
+    "Unit",
+    "Amount",
+    "Split",
+    "Frequency",
+    "Constraints",
+    "Note",
+];
+
+// A compiler for (entity, frequency, constraints) rows
+fn compiler(rows: &[(&str, &str, &str)]) -> TimeConstraintCompiler {
+    let mut table = vec![HEADER.to_vec()];
+    for &(name, frequency, constraints) in rows {
+        table.push(vec![name, "med", "tablet", "null", "null", frequency, constraints, "null"]);
+    }
+    TimeConstraintCompiler::new(parse_from_table(table).unwrap())
+}
+
+#[test]
+fn as_needed_is_not_scheduled() {
+    // Three doses 8h apart wouldn't fit a 08:00-20:00 day if they were required
+    let mut compiler = compiler(&[
+        ("Ibuprofen", "prn, max 3 daily", "[\"≥8h apart\"]"),
+        ("Antacid", "daily", "[\"≥1h after Ibuprofen\"]"),
+    ]);
+    compiler.set_day_window(8 * 60, 20 * 60);
+    compiler.compile().unwrap();
+
+    let schedule = compiler.extract_schedule().unwrap();
+    assert_eq!(schedule.keys().collect::<Vec<_>>(), vec!["Antacid_1"]);
+
+    let printed = compiler.format_schedule(&schedule);
+    assert!(
+        printed.contains("As Needed:\n  Ibuprofen (med): up to 3 a day, ≥8h apart\n"),
+        "{}",
+        printed
+    );
+}
+
+#[test]
+fn intervals_must_divide_a_multi_day_horizon() {
+    let error = |frequency: &str| {
+        let mut compiler = compiler(&[("A", frequency, "[]")]);
+        compiler.set_horizon_days(3);
+        compiler.compile().unwrap_err().to_string()
+    };
+    for frequency in ["every 5 hours", "every 36 hours", "every 100 minutes"] {
+        let error = error(frequency);
+        assert!(error.contains("doesn't divide the day"), "{}: {}", frequency, error);
+    }
+    assert!(error("every 48 hours").contains("use 'every 2 days'"));
+
+    // Within one day the interval is a spacing
+    let mut single_day = compiler(&[("A", "every 5 hours", "[]")]);
+    single_day.compile().unwrap();
+    let mut times: Vec<i32> = single_day.extract_schedule().unwrap().into_values().collect();
+    times.sort();
+    assert_eq!(times.len(), 4);
+    assert!(times.windows(2).all(|pair| pair[1] - pair[0] >= 5 * 60));
+
+    // One that divides the day is fine over any horizon
+    let mut three_days = compiler(&[("A", "every 8 hours", "[]")]);
+    three_days.set_horizon_days(3);
+    three_days.compile().unwrap();
+    assert_eq!(three_days.clocks.len(), 9);
+}
@@ -0,0 +1,65 @@
+// Which reference instances "with" and "within X of" pair each instance with
+
+use generate_schedule::{parse_from_table, ScheduleStrategy, TimeConstraintCompiler};
+
+const HEADER: [&str; 8] = [
+    "Entity",
+    "Category",
+    "Unit",
+    "Amount",
+    "Split",
+    "Frequency",
+    "Constraints",
+    "Note",
+];
+
+// A compiler for (entity, category, frequency, constraints) rows, with the day window 08:00-20:00
+fn compiler(rows: &[(&str, &str, &str, &str)]) -> TimeConstraintCompiler {
+    let mut table = vec![HEADER.to_vec()];
+    for &(name, category, frequency, constraints) in rows {
+        table.push(vec![name, category, "unit", "null", "null", frequency, constraints, "null"]);
+    }
+    let mut compiler = TimeConstraintCompiler::new(parse_from_table(table).unwrap());
+    compiler.set_day_window(8 * 60, 20 * 60);
+    compiler
+}
+
+#[test]
+fn instances_pair_in_order() {
+    // Both doses could be within an hour of the second meal, but the first goes with the first
+    let mut compiler = compiler(&[
+        ("Kibble", "food", "2x daily", "[\"≥10h apart\"]"),
+        ("Pro-Kolin", "med", "2x daily", "[\"within 1h of food\"]"),
+    ]);
+    compiler.set_horizon_days(2);
+    compiler.compile().unwrap();
+
+    // As many doses as meals leaves one candidate each, so nothing to search
+    assert_eq!(compiler.federation.len(), 1);
+
+    let schedule = compiler.finalize_schedule(ScheduleStrategy::Latest).unwrap();
+    for day in 0..2 {
+        for k in 1..=2 {
+            let dose = schedule[&format!("Pro-Kolin_{}", 2 * day + k)];
+            let meal = schedule[&format!("Kibble_{}", 2 * day + k)];
+            assert!((dose - meal).abs() <= 60, "dose {} of day {}", k, day + 1);
+        }
+    }
+}
+
+#[test]
+fn fewer_instances_pair_with_any_in_reach() {
+    // One dose and two meals: the dose may go with either, a zone for each
+    let mut compiler = compiler(&[
+        ("Kibble", "food", "2x daily", "[\"≥10h apart\"]"),
+        ("Pro-Kolin", "med", "daily", "[\"with food\"]"),
+    ]);
+    compiler.compile().unwrap();
+    assert_eq!(compiler.federation.len(), 2);
+
+    let schedule = compiler.extract_schedule().unwrap();
+    assert!(
+        schedule["Pro-Kolin_1"] == schedule["Kibble_1"]
+            || schedule["Pro-Kolin_1"] == schedule["Kibble_2"]
+    );
+}
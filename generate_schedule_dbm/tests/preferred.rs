@@ -0,0 +1,57 @@
+// The preferred strategy: the schedule nearest the preferred times, and which preferred time each
+// instance of a day is given
+
+use generate_schedule::{parse_from_table, ScheduleStrategy, TimeConstraintCompiler};
+
+const HEADER: [&str; 8] = [
+    "Entity",
+    "Category",
+    "Unit",
+    "Amount",
+    "Split",
+    "Frequency",
+    "Constraints",
+    "Note",
+];
+
+// A compiler for (entity, frequency, constraints) rows
+fn compiler(rows: &[(&str, &str, &str)]) -> TimeConstraintCompiler {
+    let mut table = vec![HEADER.to_vec()];
+    for &(name, frequency, constraints) in rows {
+        table.push(vec![name, "med", "tablet", "null", "null", frequency, constraints, "null"]);
+    }
+    TimeConstraintCompiler::new(parse_from_table(table).unwrap())
+}
+
+#[test]
+fn preferred_schedule_is_the_weighted_optimum() {
+    // B wants 09:30 three times as much as A wants 09:00, and must be an hour after A: moving A
+    // to 08:30 costs 30, moving B to 10:00 would cost 90
+    let mut compiler = compiler(&[("A", "daily", "[]"), ("B", "daily", "[\"≥1h after A\"]")]);
+    compiler.set_preference("A", vec![9 * 60], 1);
+    compiler.set_preference("B", vec![9 * 60 + 30], 3);
+    compiler.compile().unwrap();
+
+    let schedule = compiler.finalize_schedule(ScheduleStrategy::Preferred).unwrap();
+    assert_eq!((schedule["A_1"], schedule["B_1"]), (8 * 60 + 30, 9 * 60 + 30));
+}
+
+#[test]
+fn preferred_times_restart_each_day() {
+    // Every 90 minutes in 08:00-11:00 is two instances a day, not the sixteen a whole day holds
+    let mut compiler = compiler(&[("A", "every 90 minutes", "[]")]);
+    compiler.set_entity_window("A", 8 * 60, 11 * 60);
+    compiler.set_horizon_days(2);
+    compiler.set_preference("A", vec![8 * 60 + 30, 10 * 60 + 30], 1);
+    compiler.compile().unwrap();
+
+    let preferences = compiler.clock_preferences();
+    let day = 24 * 60;
+    assert_eq!(preferences["A_1"], (8 * 60 + 30, 1));
+    assert_eq!(preferences["A_2"], (10 * 60 + 30, 1));
+    assert_eq!(preferences["A_3"], (day + 8 * 60 + 30, 1));
+    assert_eq!(preferences["A_4"], (day + 10 * 60 + 30, 1));
+
+    let schedule = compiler.finalize_schedule(ScheduleStrategy::Preferred).unwrap();
+    assert_eq!(schedule["A_3"] as i64, day + 8 * 60 + 30);
+}
@@ -0,0 +1,107 @@
+// What the names in a constraint resolve to
+
+use generate_schedule::{
+    parse_category_constraints, parse_from_table, ScheduleError, ScheduleStrategy,
+    TimeConstraintCompiler,
+};
+
+const HEADER: [&str; 8] = [
+    "Entity",
+    "Category",
+    "Unit",
+    "Amount",
+    "Split",
+    "Frequency",
+    "Constraints",
+    "Note",
+];
+
+// A compiler for (entity, category, constraints) rows of once-daily entities, with the day window
+// 08:00-20:00
+fn compiler(
+    rows: &[(&str, &str, &str)],
+    category_constraints: &[&str],
+) -> TimeConstraintCompiler {
+    let mut table = vec![HEADER.to_vec()];
+    for &(name, category, constraints) in rows {
+        table.push(vec![name, category, "unit", "null", "null", "daily", constraints, "null"]);
+    }
+    table.push(vec!["Category Constraints"]);
+    for &constraint in category_constraints {
+        table.push(vec![constraint]);
+    }
+
+    let category_constraints = parse_category_constraints(&table).unwrap();
+    let mut compiler = TimeConstraintCompiler::new(parse_from_table(table).unwrap());
+    compiler.set_category_constraints(category_constraints);
+    compiler.set_day_window(8 * 60, 20 * 60);
+    compiler
+}
+
+#[test]
+fn categories_match_regardless_of_case() {
+    let mut compiler = compiler(
+        &[
+            ("Kibble", "food", "[]"),
+            ("Treat", "Food", "[]"),
+            ("Antepsin", "med", "[\"≥2h after FOOD\"]"),
+        ],
+        &[],
+    );
+    compiler.compile().unwrap();
+    let schedule = compiler.extract_schedule().unwrap();
+    assert!(schedule["Antepsin_1"] - schedule["Kibble_1"] >= 120);
+    assert!(schedule["Antepsin_1"] - schedule["Treat_1"] >= 120);
+}
+
+#[test]
+fn category_constraints_match_regardless_of_case() {
+    let mut compiler = compiler(
+        &[("Kibble", "food", "[]"), ("Antepsin", "med", "[]")],
+        &["Med ≥10h after Food"],
+    );
+    compiler.compile().unwrap();
+    let schedule = compiler.extract_schedule().unwrap();
+    assert!(schedule["Antepsin_1"] - schedule["Kibble_1"] >= 600);
+}
+
+#[test]
+fn entities_match_before_categories() {
+    // "kibble" is the entity Kibble, not the category of the same name
+    let mut compiler = compiler(
+        &[
+            ("Kibble", "food", "[]"),
+            ("Chew", "kibble", "[]"),
+            ("Antepsin", "med", "[\"≥11h after kibble\"]"),
+        ],
+        &[],
+    );
+    compiler.compile().unwrap();
+
+    // As late as possible: only Kibble is held back by Antepsin
+    let schedule = compiler.finalize_schedule(ScheduleStrategy::Latest).unwrap();
+    assert_eq!(schedule["Kibble_1"], 9 * 60);
+    assert_eq!(schedule["Chew_1"], 20 * 60);
+}
+
+#[test]
+fn unknown_names_are_errors() {
+    let mut entity_rule = compiler(
+        &[("Kibble", "food", "[]"), ("Antepsin", "med", "[\"≥2h after food or snack\"]")],
+        &[],
+    );
+    match entity_rule.compile() {
+        Err(ScheduleError::UnresolvedReference { reference }) => assert_eq!(reference, "snack"),
+        other => panic!("expected an unresolved reference, got {:?}", other.map(|_| ())),
+    }
+
+    // In a category constraint too, rather than dropping the constraint
+    let mut category_rule = compiler(
+        &[("Kibble", "food", "[]"), ("Antepsin", "med", "[]")],
+        &["meds ≥30m apart from food"],
+    );
+    match category_rule.compile() {
+        Err(ScheduleError::UnresolvedReference { reference }) => assert_eq!(reference, "meds"),
+        other => panic!("expected an unresolved reference, got {:?}", other.map(|_| ())),
+    }
+}
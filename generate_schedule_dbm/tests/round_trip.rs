@@ -0,0 +1,245 @@
+// The canonical DSL text written by `Display` parses back to the value it was written from,
+// and so does a schedule printed by `format_schedule`
+
+use generate_schedule::{
+    parse_schedule, CategoryConstraint, ConstraintExpression, ConstraintReference,
+    ConstraintType, Frequency, GapBound, Pairing, Reference, ScheduleSpec,
+    TimeConstraintCompiler, TimeUnit, Weekday,
+};
+use proptest::prelude::*;
+use std::path::Path;
+
+// Entity or category names, including ones that need quoting in a reference
+fn name() -> impl Strategy<Value = String> {
+    prop_oneof![
+        4 => "[A-Za-z][A-Za-z0-9-]{0,8}( [A-Za-z][A-Za-z0-9-]{0,8}){0,2}",
+        1 => prop::sample::select(vec![
+            "Chicken and rice",
+            "Salt or pepper",
+            "Pro-Kolin",
+            "Vitamin B12",
+            "its food",
+            "Nearest meal",
+            "the matching dose",
+            "Fish (raw)",
+        ])
+        .prop_map(String::from),
+    ]
+}
+
+// A reference as the parser builds it: "or" and "and" hold at least two operands and don't
+// directly nest the same operator, which would be written (and read back) flattened
+fn reference() -> impl Strategy<Value = Reference> {
+    name().prop_map(Reference::Name).prop_recursive(3, 12, 3, |inner| {
+        prop_oneof![
+            prop::collection::vec(inner.clone(), 2..4).prop_map(|parts| Reference::Or(
+                parts
+                    .into_iter()
+                    .flat_map(|part| match part {
+                        Reference::Or(nested) => nested,
+                        part => vec![part],
+                    })
+                    .collect()
+            )),
+            prop::collection::vec(inner.clone(), 2..4).prop_map(|parts| Reference::And(
+                parts
+                    .into_iter()
+                    .flat_map(|part| match part {
+                        Reference::And(nested) => nested,
+                        part => vec![part],
+                    })
+                    .collect()
+            )),
+            (inner.clone(), inner)
+                .prop_map(|(base, excluded)| Reference::Except(Box::new(base), Box::new(excluded))),
+        ]
+    })
+}
+
+fn time_unit() -> impl Strategy<Value = TimeUnit> {
+    prop_oneof![Just(TimeUnit::Minute), Just(TimeUnit::Hour)]
+}
+
+// A time value with its bound; a range's upper end is never below its lower one
+fn gap() -> impl Strategy<Value = (u32, TimeUnit, GapBound)> {
+    (0..2000u32, time_unit(), 0..3u8, 0..2000u32).prop_map(|(value, unit, kind, extra)| {
+        let bound = match kind {
+            0 => GapBound::AtLeast,
+            1 => GapBound::AtMost,
+            _ => GapBound::Between(value + extra),
+        };
+        (value, unit, bound)
+    })
+}
+
+fn pairing() -> impl Strategy<Value = Pairing> {
+    prop_oneof![Just(Pairing::All), Just(Pairing::Nearest), Just(Pairing::Matching)]
+}
+
+// Constraint expressions in the forms the DSL can write: "apart" has no reference, only
+// before/after pair instances, and "with" bounds the gap from above
+fn constraint_expression() -> impl Strategy<Value = ConstraintExpression> {
+    let constraint_type = prop_oneof![
+        Just(ConstraintType::Before),
+        Just(ConstraintType::After),
+        Just(ConstraintType::ApartFrom),
+        Just(ConstraintType::Apart),
+        Just(ConstraintType::With),
+    ];
+    (constraint_type, gap(), pairing(), reference()).prop_map(
+        |(constraint_type, (time_value, time_unit, bound), pairing, reference)| {
+            let reference = ConstraintReference::Unresolved(reference.to_string());
+            let (bound, pairing, reference) = match constraint_type {
+                ConstraintType::Before | ConstraintType::After => (bound, pairing, reference),
+                ConstraintType::ApartFrom => (bound, Pairing::All, reference),
+                ConstraintType::Apart => (bound, Pairing::All, ConstraintReference::WithinGroup),
+                ConstraintType::With => (GapBound::AtMost, Pairing::All, reference),
+            };
+            ConstraintExpression {
+                time_value,
+                time_unit,
+                constraint_type,
+                reference,
+                bound,
+                pairing,
+            }
+        },
+    )
+}
+
+// Category names read up to the bound, so they can't contain a word that starts one
+fn category() -> impl Strategy<Value = String> {
+    "[a-z][a-z0-9-]{0,8}( [a-z][a-z0-9-]{0,8}){0,2}".prop_filter(
+        "a bound word would end the category",
+        |name| {
+            !name
+                .split(' ')
+                .any(|word| matches!(word, "within" | "between" | "at" | "no"))
+        },
+    )
+}
+
+fn category_constraint() -> impl Strategy<Value = CategoryConstraint> {
+    let constraint_type = prop_oneof![
+        Just(ConstraintType::Before),
+        Just(ConstraintType::After),
+        Just(ConstraintType::ApartFrom),
+    ];
+    (category(), category(), constraint_type, gap()).prop_map(
+        |(from_category, to_category, constraint_type, (time_value, time_unit, bound))| {
+            CategoryConstraint {
+                from_category,
+                to_category,
+                constraint_type,
+                time_value,
+                time_unit,
+                bound,
+            }
+        },
+    )
+}
+
+// Fixed times as the parser stores them: distinct, earliest first, and either all or none
+// with a tolerance
+fn fixed_times() -> impl Strategy<Value = Vec<String>> {
+    (
+        prop::collection::btree_set(0..1440u32, 1..5),
+        prop::option::of(0..240u32),
+    )
+        .prop_map(|(times, tolerance)| {
+            times
+                .into_iter()
+                .map(|minutes| {
+                    let time = format!("{:02}:{:02}", minutes / 60, minutes % 60);
+                    match tolerance {
+                        Some(tolerance) => format!("{}±{}m", time, tolerance),
+                        None => time,
+                    }
+                })
+                .collect()
+        })
+}
+
+// Frequencies in the variant the parser reads them as (so "1x weekly" is `Weekly`)
+fn frequency() -> impl Strategy<Value = Frequency> {
+    prop_oneof![
+        (1..100u32).prop_map(Frequency::TimesPerDay),
+        (1..1000u32).prop_map(Frequency::EveryXHours),
+        (1..2000u32).prop_map(Frequency::EveryXMinutes),
+        (2..60u32).prop_map(Frequency::EveryXDays),
+        prop::sample::subsequence(Weekday::ALL.to_vec(), 1..=7).prop_map(Frequency::OnWeekdays),
+        (2..=7u32).prop_map(Frequency::TimesPerWeek),
+        Just(Frequency::Weekly),
+        (1..50u32).prop_map(|max_per_day| Frequency::AsNeeded { max_per_day }),
+        fixed_times().prop_map(Frequency::Custom),
+    ]
+}
+
+proptest! {
+    #[test]
+    fn reference_round_trips(reference in reference()) {
+        let text = reference.to_string();
+        prop_assert_eq!(Reference::parse(&text).map_err(|e| e.annotated()), Ok(reference), "{}", text);
+    }
+
+    #[test]
+    fn constraint_expression_round_trips(constraint in constraint_expression()) {
+        let text = constraint.to_string();
+        let parsed = ConstraintExpression::parse(&text).map_err(|e| e.to_string());
+        prop_assert_eq!(parsed, Ok(constraint), "{}", text);
+    }
+
+    #[test]
+    fn category_constraint_round_trips(constraint in category_constraint()) {
+        let text = constraint.to_string();
+        let parsed = CategoryConstraint::parse(&text).map_err(|e| e.to_string());
+        prop_assert_eq!(parsed, Ok(constraint), "{}", text);
+    }
+
+    #[test]
+    fn frequency_round_trips(frequency in frequency()) {
+        let text = frequency.to_string();
+        prop_assert_eq!(Frequency::from_str(&text).map_err(|e| e.to_string()), Ok(frequency), "{}", text);
+    }
+
+    // Whatever form an expression is written in, its canonical text reads back the same
+    #[test]
+    fn written_constraints_are_canonical(
+        text in prop::sample::select(vec![
+            "at least 1h30m before food",
+            ">=2h after the nearest food",
+            "within 15 minutes of (food or snack)",
+            "with food and med except \"Chicken and rice\"",
+            "between 60m and 2h after its food",
+            "no more than 12 hours apart",
+            "≥1h after \"its food\"",
+        ])
+    ) {
+        let constraint = ConstraintExpression::parse(text).unwrap();
+        let canonical = constraint.to_string();
+        prop_assert_eq!(ConstraintExpression::parse(&canonical).unwrap(), constraint);
+        prop_assert_eq!(ConstraintExpression::parse(&canonical).unwrap().to_string(), canonical);
+    }
+}
+
+// The README's `--input data/medication.yaml --days 3` example: it compiles, and the schedule it
+// prints reads back as the same times, none of which break a constraint
+#[test]
+fn three_day_example_schedule_round_trips() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("data/medication.yaml");
+    let spec = ScheduleSpec::from_path(&path).unwrap();
+    let mut config = spec.compiler_config().unwrap();
+    config.horizon_days = 3;
+
+    let mut compiler = TimeConstraintCompiler::with_config(spec.entities().unwrap(), config);
+    let category_constraints = spec.category_constraints().unwrap();
+    if !category_constraints.is_empty() {
+        compiler.set_category_constraints(category_constraints);
+    }
+    compiler.compile().unwrap();
+
+    let schedule = compiler.extract_schedule().unwrap();
+    let printed = compiler.format_schedule(&schedule);
+    assert_eq!(parse_schedule(&printed).unwrap(), schedule);
+    assert_eq!(compiler.verify(&schedule).unwrap(), Vec::new());
+}
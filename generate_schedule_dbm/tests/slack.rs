@@ -0,0 +1,79 @@
+// How far each instance of a given schedule can move, and which can't move at all
+
+use generate_schedule::{parse_from_table, ScheduleError, TimeConstraintCompiler};
+use std::collections::HashMap;
+
+const HEADER: [&str; 8] = [
+    "Entity",
+    "Category",
+    "Unit",
+    "Amount",
+    "Split",
+    "Frequency",
+    "Constraints",
+    "Note",
+];
+
+// A compiled compiler for (entity, constraints) rows of once-daily meds, with the day window
+// 08:00-12:00
+fn compiled(rows: &[(&str, &str)]) -> TimeConstraintCompiler {
+    let mut table = vec![HEADER.to_vec()];
+    for &(name, constraints) in rows {
+        table.push(vec![name, "med", "tablet", "null", "null", "daily", constraints, "null"]);
+    }
+    let mut compiler = TimeConstraintCompiler::new(parse_from_table(table).unwrap());
+    compiler.set_day_window(8 * 60, 12 * 60);
+    compiler.compile().unwrap();
+    compiler
+}
+
+// A schedule of (clock id, hour) pairs
+fn schedule(times: &[(&str, i32)]) -> HashMap<String, i32> {
+    times
+        .iter()
+        .map(|&(clock_id, hour)| (clock_id.to_string(), hour * 60))
+        .collect()
+}
+
+#[test]
+fn instance_held_on_both_sides_is_rigid() {
+    // A can't go before the day starts, nor later while B stays 2h after it
+    let compiler = compiled(&[("A", "[]"), ("B", "[\"≥2h after A\"]")]);
+    let schedule = schedule(&[("A_1", 8), ("B_1", 10)]);
+    let report = compiler.slack(&schedule).unwrap();
+
+    let (a, b) = (&report[0], &report[1]);
+    assert_eq!((a.clock.as_str(), b.clock.as_str()), ("A_1", "B_1"));
+    assert!(a.is_rigid());
+    assert_eq!((a.earlier(), a.later()), (0, 0));
+    assert_eq!((a.free_earliest, a.free_latest), (8 * 60, 10 * 60));
+    assert!(!b.is_rigid());
+    assert_eq!((b.earlier(), b.later()), (0, 120));
+
+    let table = compiler.format_slack(&report);
+    let line = |clock: &str| table.lines().find(|line| line.contains(clock)).unwrap().to_string();
+    assert!(line("A_1").ends_with("rigid"), "{}", table);
+    assert!(!line("B_1").ends_with("rigid"), "{}", table);
+}
+
+#[test]
+fn slack_follows_the_schedules_own_ordering() {
+    // Whichever side of A the search put B on first, this schedule has B first
+    let compiler = compiled(&[("A", "[\"≥2h apart from B\"]"), ("B", "[]")]);
+    let report = compiler.slack(&schedule(&[("A_1", 10), ("B_1", 8)])).unwrap();
+
+    let (b, a) = (&report[0], &report[1]);
+    assert!(b.is_rigid());
+    assert_eq!((a.earliest, a.latest), (10 * 60, 12 * 60));
+}
+
+#[test]
+fn schedule_that_breaks_a_constraint_has_no_slack() {
+    let compiler = compiled(&[("A", "[]"), ("B", "[\"≥2h after A\"]")]);
+    match compiler.slack(&schedule(&[("A_1", 9), ("B_1", 10)])) {
+        Err(ScheduleError::Input(message)) => {
+            assert!(message.starts_with("The schedule has no slack"), "{}", message)
+        }
+        other => panic!("expected an input error, got {:?}", other),
+    }
+}
@@ -0,0 +1,80 @@
+// The settings a spec gives the compiler
+
+use generate_schedule::{ScheduleError, ScheduleSpec, SpecFormat};
+
+// A spec with one med and one meal, followed by `extra`
+fn spec(extra: &str) -> Result<ScheduleSpec, ScheduleError> {
+    let text = format!(
+        "version: 1
+entities:
+  - {{ name: Gabapentin, category: med, unit: ml, frequency: 2x daily }}
+  - {{ name: Kibble, category: food, unit: meal, frequency: 2x daily }}
+{}",
+        extra
+    );
+    ScheduleSpec::from_str(&text, SpecFormat::Yaml)
+}
+
+#[test]
+fn windows_and_preferences_apply_to_their_names() {
+    let config = spec(
+        "category_windows:
+  food: { start: \"08:00\", end: \"19:00\" }
+entity_windows:
+  Gabapentin: { start: \"09:00\", end: \"21:00\" }
+preferences:
+  Gabapentin: { times: [\"20:00\", \"09:00\"], weight: 2 }",
+    )
+    .unwrap()
+    .compiler_config()
+    .unwrap();
+
+    assert_eq!(config.window_for("Kibble", "food"), (8 * 60, 19 * 60));
+    assert_eq!(config.window_for("Gabapentin", "med"), (9 * 60, 21 * 60));
+    assert_eq!(config.preferences["Gabapentin"].times, vec![9 * 60, 20 * 60]);
+    assert_eq!(config.preferences["Gabapentin"].weight, 2);
+}
+
+#[test]
+fn unknown_names_are_unresolved() {
+    for (extra, field) in [
+        (
+            "entity_windows:\n  Gabapentine: { start: \"09:00\", end: \"21:00\" }",
+            "entity_windows.Gabapentine",
+        ),
+        (
+            "category_windows:\n  meds: { start: \"09:00\", end: \"21:00\" }",
+            "category_windows.meds",
+        ),
+        // A category isn't an entity
+        (
+            "entity_windows:\n  food: { start: \"09:00\", end: \"21:00\" }",
+            "entity_windows.food",
+        ),
+        ("preferences:\n  Kibbles: { times: [\"08:00\"] }", "preferences.Kibbles"),
+    ] {
+        match spec(extra).unwrap().compiler_config() {
+            Err(ScheduleError::UnresolvedReference { reference }) => assert_eq!(reference, field),
+            other => panic!("expected {} to be unresolved, got {:?}", field, other.map(|_| ())),
+        }
+    }
+}
+
+#[test]
+fn category_constraints_name_known_categories() {
+    let constraints = spec("category_constraints:\n  - Med ≥30m apart from food")
+        .unwrap()
+        .category_constraints()
+        .unwrap();
+    assert_eq!(constraints.len(), 1);
+
+    match spec("category_constraints:\n  - meds ≥30m apart from food")
+        .unwrap()
+        .category_constraints()
+    {
+        Err(ScheduleError::UnresolvedReference { reference }) => {
+            assert_eq!(reference, "category_constraints.meds")
+        }
+        other => panic!("expected meds to be unresolved, got {:?}", other.map(|_| ())),
+    }
+}
@@ -0,0 +1,99 @@
+// Checking a given schedule against the constraints, disjunctions included
+
+use generate_schedule::{
+    parse_category_constraints, parse_from_table, ConstraintOrigin, TimeConstraintCompiler,
+};
+use std::collections::HashMap;
+
+const HEADER: [&str; 8] = [
+    "Entity",
+    "Category",
+    "Unit",
+    "Amount",
+    "Split",
+    "Frequency",
+    "Constraints",
+    "Note",
+];
+
+// A compiled compiler for (entity, constraints) rows of once-daily meds and the category
+// constraints, with the day window 08:00-20:00
+fn compiled(rows: &[(&str, &str)], category_constraints: &[&str]) -> TimeConstraintCompiler {
+    let mut table = vec![HEADER.to_vec()];
+    for &(name, constraints) in rows {
+        table.push(vec![name, "med", "tablet", "null", "null", "daily", constraints, "null"]);
+    }
+    table.push(vec!["Category Constraints"]);
+    for &constraint in category_constraints {
+        table.push(vec![constraint]);
+    }
+
+    let category_constraints = parse_category_constraints(&table).unwrap();
+    let mut compiler = TimeConstraintCompiler::new(parse_from_table(table).unwrap());
+    compiler.set_category_constraints(category_constraints);
+    compiler.set_day_window(8 * 60, 20 * 60);
+    compiler.compile().unwrap();
+    compiler
+}
+
+// A schedule of (clock id, hour, minute)
+fn schedule(times: &[(&str, i32, i32)]) -> HashMap<String, i32> {
+    times
+        .iter()
+        .map(|&(clock_id, hour, minute)| (clock_id.to_string(), hour * 60 + minute))
+        .collect()
+}
+
+#[test]
+fn broken_disjunction_is_off_by_the_nearest_alternative() {
+    let compiler = compiled(&[("A", "[\"≥2h apart from B\"]"), ("B", "[]")], &[]);
+
+    // 1h30m apart: B is 30 minutes short of 2h after A, and 3h30m short of 2h before it
+    let violations = compiler.verify(&schedule(&[("A_1", 10, 0), ("B_1", 11, 30)])).unwrap();
+    assert_eq!(violations.len(), 1);
+    assert_eq!(violations[0].minutes, 30);
+    assert_eq!(
+        violations[0].origin,
+        ConstraintOrigin::Entity {
+            entity: "A".to_string(),
+            row: Some(2),
+            expression: "≥2h apart from B".to_string(),
+        }
+    );
+    assert_eq!(
+        violations[0].to_string(),
+        "A (row 2): ≥2h apart from B: A_1 must be ≥2h0m apart from B_1 (off by 0h30m)"
+    );
+}
+
+#[test]
+fn either_ordering_of_a_disjunction_holds() {
+    // The zone keeps the ordering the search found first; the other one is just as valid
+    let compiler = compiled(&[("A", "[\"≥2h apart from B\"]"), ("B", "[]")], &[]);
+    for times in [[("A_1", 8, 0), ("B_1", 10, 0)], [("A_1", 10, 0), ("B_1", 8, 0)]] {
+        assert_eq!(compiler.verify(&schedule(&times)).unwrap(), Vec::new());
+    }
+}
+
+#[test]
+fn pair_constrained_twice_is_reported_once() {
+    // B is one of the meds, so "B and med" relates A to B twice
+    let compiler = compiled(&[("A", "[\"≥2h apart from B and med\"]"), ("B", "[]")], &[]);
+    let violations = compiler.verify(&schedule(&[("A_1", 10, 0), ("B_1", 11, 0)])).unwrap();
+    assert_eq!(violations.len(), 1);
+    assert_eq!(violations[0].minutes, 60);
+}
+
+#[test]
+fn category_apart_from_itself_relates_each_pair_once() {
+    let compiler = compiled(&[("A", "[]"), ("B", "[]"), ("C", "[]")], &["med ≥2h apart from med"]);
+    let violations = compiler
+        .verify(&schedule(&[("A_1", 10, 0), ("B_1", 11, 0), ("C_1", 11, 30)]))
+        .unwrap();
+    let minutes: Vec<i64> = violations.iter().map(|violation| violation.minutes).collect();
+    assert_eq!(minutes, vec![60, 30, 90]);
+    assert!(violations.iter().all(|violation| violation.origin
+        == ConstraintOrigin::Category {
+            expression: "med ≥2h apart from med".to_string()
+        }));
+}
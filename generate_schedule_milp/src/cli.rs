@@ -15,6 +15,9 @@ pub struct ScheduleConfig {
 
     // New field for global windows
     pub global_windows: Vec<WindowSpec>,
+
+    // Optional CSV/TSV file with the entity table (otherwise the built-in sample is used)
+    pub input: Option<String>,
 }
 
 impl Default for ScheduleConfig {
@@ -24,6 +27,7 @@ impl Default for ScheduleConfig {
             day_end_minutes: 22 * 60,
             strategy: ScheduleStrategy::Earliest,
             global_windows: Vec::new(),
+            input: None,
         }
     }
 }
@@ -61,6 +65,13 @@ pub fn parse_config_from_args() -> ScheduleConfig {
             });
     }
 
+    // 4) Input table: --input=path.csv, --input path.csv or -i path.csv, as in the DBM generator
+    config.input = args.iter().enumerate().find_map(|(i, a)| {
+        a.strip_prefix("--input=")
+            .map(|p| p.to_string())
+            .or_else(|| if a == "--input" || a == "-i" { args.get(i + 1).cloned() } else { None })
+    });
+
     config
 }
 
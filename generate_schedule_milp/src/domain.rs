@@ -1,4 +1,5 @@
 use good_lp::variable::Variable;
+use schedule_grammar as grammar;
 
 #[derive(Debug, Clone)]
 pub enum ConstraintType {
@@ -11,41 +12,53 @@ pub enum ConstraintType {
 #[derive(Debug, Clone)]
 pub enum ConstraintRef {
     WithinGroup,
+    /// Entity and category names, in the canonical text of the reference ("food or snack").
     Unresolved(String),
 }
 
 #[derive(Debug, Clone)]
 pub struct ConstraintExpr {
-    pub time_hours: u32,
+    /// Lower bound on the gap in minutes (0 for "≤Xh" / "within Xh").
+    pub min_minutes: u32,
+    /// Upper bound on the gap in minutes, for "≤Xh", "within Xh" and "between Xh and Yh".
+    pub max_minutes: Option<u32>,
     pub ctype: ConstraintType,
     pub cref: ConstraintRef,
 }
 
 #[derive(Debug, Clone)]
 pub enum Frequency {
-    Daily,
-    TwiceDaily,
-    ThreeTimesDaily,
+    /// N times a day, e.g. "daily", "2x daily" or "5 times a day".
+    TimesPerDay(u32),
+    /// Kept the given number of hours apart; as many as fit in the day.
     EveryXHours(u32),
 }
 
 impl Frequency {
-    pub fn from_str(s: &str) -> Self {
-        let lower = s.to_lowercase();
-        match () {
-            _ if lower.contains("3x") => Self::ThreeTimesDaily,
-            _ if lower.contains("2x") => Self::TwiceDaily,
-            _ if lower.contains("1x") => Self::Daily,
-            _ => Self::EveryXHours(8),
+    /// Parse a frequency cell with the grammar shared with the DBM generator, keeping the
+    /// frequencies this generator supports: "daily", "2x daily", "N times a day", "every N hours".
+    pub fn from_str(s: &str) -> Result<Self, String> {
+        match grammar::parse_frequency(s).map_err(|e| e.annotated())? {
+            grammar::Frequency::TimesPerDay(n) => Ok(Self::TimesPerDay(n)),
+            grammar::Frequency::EveryHours(hours) => Ok(Self::EveryXHours(hours)),
+            _ => Err(format!("'{}' is not supported by the MILP generator", s.trim())),
         }
     }
 
+    /// Instances in the (single) day. An interval that doesn't divide the day gets as many
+    /// instances as fit ("every 5 hours" is 4), and one longer than a day gets one.
     pub fn instances_per_day(&self) -> usize {
         match self {
-            Self::Daily => 1,
-            Self::TwiceDaily => 2,
-            Self::ThreeTimesDaily => 3,
-            Self::EveryXHours(h) => 24 / (*h as usize),
+            Self::TimesPerDay(n) => *n as usize,
+            Self::EveryXHours(h) => (24 / (*h).max(1) as usize).max(1),
+        }
+    }
+
+    /// Minimum gap in minutes between consecutive instances that the frequency implies.
+    pub fn implied_spacing(&self) -> Option<f64> {
+        match self {
+            Self::EveryXHours(h) => Some(*h as f64 * 60.0),
+            _ => None,
         }
     }
 }
@@ -75,7 +88,16 @@ pub struct Entity {
     pub windows: Vec<WindowSpec>,
 }
 
-#[derive(Clone)]
+/// A rule between two categories from the table's "Category Constraints" block, e.g.
+/// "med ≥30m apart from food": `constraint` holds for every entity of `from_category`, and its
+/// reference names the other category.
+#[derive(Debug, Clone)]
+pub struct CategoryConstraint {
+    pub from_category: String,
+    pub constraint: ConstraintExpr,
+}
+
+#[derive(Clone, PartialEq)]
 pub struct ClockVar {
     pub entity_name: String,
     pub instance: usize,
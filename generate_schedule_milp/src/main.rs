@@ -7,14 +7,16 @@ use crate::domain::{
     ClockVar, ConstraintType, ConstraintRef, c2str,
     WindowSpec, Entity, // needed to match on WindowSpec
 };
-use crate::parse::parse_from_table;
+use crate::parse::{parse_category_constraints, parse_from_table, read_table};
 
 use good_lp::{
     variables, variable, constraint, default_solver,
     SolverModel, Solution, Expression, Constraint, Variable
 };
+use schedule_grammar as grammar;
 use std::collections::{HashMap, HashSet};
 use std::error::Error;
+use std::path::Path;
 use std::time::Instant;
 
 // Custom structure to track penalty variables for better reporting
@@ -85,7 +87,7 @@ fn main() -> Result<(), Box<dyn Error>> {
         ],
         vec![
             "Pro-Kolin",
-            "med",
+            "supplement",
             "ml",
             "3.0",
             "null",
@@ -105,10 +107,37 @@ fn main() -> Result<(), Box<dyn Error>> {
             "[\"08:00\", \"18:00-20:00\"]", // has 1 anchor & 1 range
             "some note",
         ],
+        // Rules for whole categories follow the entities
+        vec!["Category Constraints"],
+        vec!["med ≥30m apart from food"],
     ];
 
-    // Parse table data
-    let entities = parse_from_table(table_data)?;
+    // Parse table data, from --input if given
+    let parse_table = |rows: Vec<Vec<&str>>| -> Result<_, String> {
+        let category_constraints = parse_category_constraints(&rows)?;
+        Ok((parse_from_table(rows)?, category_constraints))
+    };
+    let parsed = match &config.input {
+        Some(path) => {
+            println!("Reading entity table from {}", path);
+            read_table(Path::new(path)).and_then(|rows| {
+                parse_table(
+                    rows.iter()
+                        .map(|row| row.iter().map(|cell| cell.as_str()).collect())
+                        .collect(),
+                )
+            })
+        }
+        None => parse_table(table_data),
+    };
+    // Printed as is, so the underline under a bad constraint or frequency lines up
+    let (entities, category_constraints) = match parsed {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
 
     // Create a map of window info for better reporting
     let entity_windows = create_window_info_map(&entities);
@@ -168,8 +197,8 @@ fn main() -> Result<(), Box<dyn Error>> {
         list.sort_by_key(|c| c.instance);
     }
 
-    // Helper to resolve references: either an entity name or a category
-    let resolve_ref = |rstr: &str| -> Vec<ClockVar> {
+    // Helper to resolve names: either an entity name or a category
+    let resolve_name = |rstr: &str| -> Vec<ClockVar> {
         let mut out = Vec::new();
         for e in &entities {
             if e.name.eq_ignore_ascii_case(rstr) {
@@ -191,6 +220,24 @@ fn main() -> Result<(), Box<dyn Error>> {
         out
     };
 
+    // References combine names with or/and/except (see `schedule_grammar::Reference`). The
+    // constraints here hold against every instance alike, so the sets it stands for are merged.
+    let resolve_ref = |rstr: &str| -> Vec<ClockVar> {
+        let Ok(reference) = grammar::Reference::parse(rstr) else {
+            return Vec::new();
+        };
+        let sets = reference
+            .resolve(&mut |name| Ok::<_, ()>(resolve_name(name)))
+            .unwrap_or_default();
+        let mut out: Vec<ClockVar> = Vec::new();
+        for cv in sets.into_iter().flatten() {
+            if !out.contains(&cv) {
+                out.push(cv);
+            }
+        }
+        out
+    };
+
     let big_m = 1440.0;
 
     // (1) Apply "apart/before/after" constraints
@@ -203,9 +250,15 @@ fn main() -> Result<(), Box<dyn Error>> {
         let mut ba_map: HashMap<String, (Option<f64>, Option<f64>)> = HashMap::new();
         let mut apart_intervals = Vec::new();
         let mut apart_from_list = Vec::new();
+        let mut bounded_list = Vec::new();
 
         for cexpr in &e.constraints {
-            let tv_min = (cexpr.time_hours as f64) * 60.0;
+            let tv_min = cexpr.min_minutes as f64;
+            // Constraints with an upper bound are linear on their own (see (d))
+            if let Some(max_minutes) = cexpr.max_minutes {
+                bounded_list.push((cexpr.ctype.clone(), cexpr.cref.clone(), tv_min, Some(max_minutes as f64)));
+                continue;
+            }
             match cexpr.ctype {
                 ConstraintType::Apart => {
                     apart_intervals.push(tv_min);
@@ -230,6 +283,26 @@ fn main() -> Result<(), Box<dyn Error>> {
             }
         }
 
+        // Category constraints hold for each entity of the category. As in the DBM generator,
+        // a before and an after rule stay separate instead of merging into a disjunction (c).
+        for cc in category_constraints.iter().filter(|cc| cc.from_category == e.category) {
+            let cexpr = &cc.constraint;
+            let tv_min = cexpr.min_minutes as f64;
+            match (&cexpr.ctype, &cexpr.cref, cexpr.max_minutes) {
+                (ConstraintType::ApartFrom, ConstraintRef::Unresolved(r), None) => {
+                    apart_from_list.push((tv_min, r.clone()));
+                }
+                (ctype, cref, max_minutes) => {
+                    bounded_list.push((ctype.clone(), cref.clone(), tv_min, max_minutes.map(|max| max as f64)));
+                }
+            }
+        }
+
+        // Interval frequencies ("every 8 hours") keep their instances that far apart
+        if let Some(spacing) = e.frequency.implied_spacing() {
+            apart_intervals.push(spacing);
+        }
+
         // (a) "apart" for consecutive instances
         for tv in apart_intervals {
             for w in eclocks.windows(2) {
@@ -244,7 +317,8 @@ fn main() -> Result<(), Box<dyn Error>> {
         for (tv, refname) in apart_from_list {
             let rvars = resolve_ref(&refname);
             for c_e in eclocks {
-                for c_r in &rvars {
+                // A reference that includes the entity ("med" for a med) skips its own clock
+                for c_r in rvars.iter().filter(|c_r| c_r.var != c_e.var) {
                     let b = builder.add(variable().binary());
                     let d1 = format!("(ApartFrom) {} - {} >= {} - bigM*(1-b)",
                         c2str(c_r), c2str(c_e), tv);
@@ -272,7 +346,7 @@ fn main() -> Result<(), Box<dyn Error>> {
                 (Some(bv), Some(av)) => {
                     // "≥bv before" OR "≥av after" disjunction
                     for c_e in eclocks {
-                        for c_r in &rvars {
+                        for c_r in rvars.iter().filter(|c_r| c_r.var != c_e.var) {
                             let b = builder.add(variable().binary());
                             let d1 = format!("(Before|After) {} - {} >= {} - M*(1-b)",
                                 c2str(c_r), c2str(c_e), bv);
@@ -295,7 +369,7 @@ fn main() -> Result<(), Box<dyn Error>> {
                 (Some(bv), None) => {
                     // only "before"
                     for c_e in eclocks {
-                        for c_r in &rvars {
+                        for c_r in rvars.iter().filter(|c_r| c_r.var != c_e.var) {
                             let d = format!("(Before) {} - {} >= {}", c2str(c_r), c2str(c_e), bv);
                             add_constraint(&d,
                                 constraint!(c_r.var - c_e.var >= bv),
@@ -308,7 +382,7 @@ fn main() -> Result<(), Box<dyn Error>> {
                 (None, Some(av)) => {
                     // only "after"
                     for c_e in eclocks {
-                        for c_r in &rvars {
+                        for c_r in rvars.iter().filter(|c_r| c_r.var != c_e.var) {
                             let d = format!("(After) {} - {} >= {}", c2str(c_e), c2str(c_r), av);
                             add_constraint(&d,
                                 constraint!(c_e.var - c_r.var >= av),
@@ -321,6 +395,60 @@ fn main() -> Result<(), Box<dyn Error>> {
                 (None, None) => {}
             }
         }
+
+        // (d) upper-bounded gaps: "≤Xh apart", "within Xh after R", "between Xh and Yh before R"
+        // (and the category before/after rules, which may have no upper bound)
+        for (ctype, cref, lo, hi) in bounded_list {
+            match (&ctype, cref, hi) {
+                (ConstraintType::Apart, _, Some(hi)) => {
+                    for w in eclocks.windows(2) {
+                        let (c1, c2) = (&w[0], &w[1]);
+                        let desc = format!("(Apart) {} <= {} - {} <= {}", lo, c2str(c2), c2str(c1), hi);
+                        add_constraint(&desc, constraint!(c2.var - c1.var >= lo), &mut constraints, debug_enabled);
+                        add_constraint(&desc, constraint!(c2.var - c1.var <= hi), &mut constraints, debug_enabled);
+                    }
+                }
+                (ConstraintType::Before, ConstraintRef::Unresolved(r), hi)
+                | (ConstraintType::After, ConstraintRef::Unresolved(r), hi) => {
+                    let before = matches!(ctype, ConstraintType::Before);
+                    for c_e in eclocks {
+                        for c_r in resolve_ref(&r).iter().filter(|c_r| c_r.var != c_e.var) {
+                            // The gap runs from the earlier clock to the later one
+                            let (later, earlier) = if before { (c_r, c_e) } else { (c_e, c_r) };
+                            let desc = format!("({}) {} <= {} - {} <= {}",
+                                if before { "Before" } else { "After" }, lo, c2str(later), c2str(earlier),
+                                hi.map_or("∞".to_string(), |hi| hi.to_string()));
+                            add_constraint(&desc, constraint!(later.var - earlier.var >= lo), &mut constraints, debug_enabled);
+                            if let Some(hi) = hi {
+                                add_constraint(&desc, constraint!(later.var - earlier.var <= hi), &mut constraints, debug_enabled);
+                            }
+                        }
+                    }
+                }
+                (ConstraintType::ApartFrom, ConstraintRef::Unresolved(r), Some(hi)) => {
+                    for c_e in eclocks {
+                        for c_r in resolve_ref(&r).iter().filter(|c_r| c_r.var != c_e.var) {
+                            if lo <= 0.0 {
+                                // Within hi of each other, in either order
+                                let desc = format!("(ApartFrom) |{} - {}| <= {}", c2str(c_e), c2str(c_r), hi);
+                                add_constraint(&desc, constraint!(c_e.var - c_r.var <= hi), &mut constraints, debug_enabled);
+                                add_constraint(&desc, constraint!(c_r.var - c_e.var <= hi), &mut constraints, debug_enabled);
+                                continue;
+                            }
+                            // Between lo and hi on one side or the other => big-M disjunction
+                            let b = builder.add(variable().binary());
+                            let d1 = format!("(ApartFrom) {} <= {} - {} <= {} unless b", lo, c2str(c_r), c2str(c_e), hi);
+                            add_constraint(&d1, constraint!(c_r.var - c_e.var >= lo - big_m*(1.0 - b)), &mut constraints, debug_enabled);
+                            add_constraint(&d1, constraint!(c_r.var - c_e.var <= hi + big_m*(1.0 - b)), &mut constraints, debug_enabled);
+                            let d2 = format!("(ApartFrom) {} <= {} - {} <= {} if b", lo, c2str(c_e), c2str(c_r), hi);
+                            add_constraint(&d2, constraint!(c_e.var - c_r.var >= lo - big_m*b), &mut constraints, debug_enabled);
+                            add_constraint(&d2, constraint!(c_e.var - c_r.var <= hi + big_m*b), &mut constraints, debug_enabled);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
     }
 
     // (2) SOFT penalty for window preferences
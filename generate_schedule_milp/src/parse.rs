@@ -1,8 +1,65 @@
 use crate::domain::{
-    Entity, Frequency, ConstraintExpr, ConstraintType, ConstraintRef,
+    CategoryConstraint, Entity, Frequency, ConstraintExpr, ConstraintType, ConstraintRef,
     WindowSpec, // newly introduced in domain.rs
 };
-use regex::Regex;
+use schedule_grammar as grammar;
+
+/// Column names of the table, used to point parse errors at a cell.
+const COLUMN_NAMES: [&str; 9] = [
+    "Entity", "Category", "Unit", "Amount", "Split", "Frequency", "Constraints", "Windows", "Note",
+];
+
+/// Read a CSV or TSV file (chosen by extension) into rows of cells, with the reader the DBM
+/// generator uses. Cells use standard CSV quoting, so a constraint array is written as
+/// `"[""≥6h apart"", ""≥1h before food""]"`.
+pub use grammar::read_table;
+
+/// A row starting with this ends the entity rows; each row after it holds one category
+/// constraint in its first cell, as in the DBM generator's table.
+const CATEGORY_BLOCK: &str = "Category Constraints";
+
+/// Prefix an error with its (1-based) row and column; the header is row 1.
+fn cell_error(row: usize, column: usize, message: String) -> String {
+    format!("Row {}, column {} ({}): {}", row, column + 1, COLUMN_NAMES[column], message)
+}
+
+/// The index of the row opening the category constraints block, if the table has one.
+fn category_block_start(rows: &[Vec<&str>]) -> Option<usize> {
+    rows.iter().position(|row| {
+        row.first().is_some_and(|cell| cell.trim().eq_ignore_ascii_case(CATEGORY_BLOCK))
+    })
+}
+
+/// Parse the category constraints block at the end of the table, e.g.
+///
+/// ```text
+/// Category Constraints
+/// med ≥30m apart from food
+/// ```
+///
+/// Blank rows are skipped; a table without the block has no category constraints.
+pub fn parse_category_constraints(rows: &[Vec<&str>]) -> Result<Vec<CategoryConstraint>, String> {
+    let Some(start) = category_block_start(rows) else {
+        return Ok(Vec::new());
+    };
+
+    rows.iter()
+        .enumerate()
+        .skip(start + 1)
+        .filter(|(_, row)| row.iter().any(|cell| !cell.trim().is_empty()))
+        .map(|(index, row)| {
+            parse_category_constraint(row[0])
+                .map_err(|e| format!("Row {}, column 1 ({}): {}", index + 1, CATEGORY_BLOCK, e))
+        })
+        .collect()
+}
+
+/// The strings of a bracketed array cell, `["≥6h apart", "≥1h before food"]`, read as the DBM
+/// generator reads them: a backslash escapes a quote inside an item (`"≥1h after \"Chicken and
+/// rice\""`), and an empty or `null` cell is an empty array.
+fn string_array(cell: &str) -> Result<Vec<String>, String> {
+    grammar::parse_string_array(cell).map_err(|e| e.annotated())
+}
 
 /// Parse the table into a list of `Entity`.
 /// Now expects a table with at least 9 columns:
@@ -16,49 +73,46 @@ use regex::Regex;
 ///   [7]: Windows   (new)
 ///   [8]: Note
 ///
+/// The entity rows end at a category constraints block (see `parse_category_constraints`).
 /// Returns an error if rows have fewer than 9 columns.
-pub fn parse_from_table(rows: Vec<Vec<&str>>) -> Result<Vec<Entity>, String> {
-    // For parsing the constraints text (JSON-like array of strings),
-    // we reuse the same approach with a regex capturing anything in quotes.
-    let re = Regex::new(r#""([^"]+)""#).unwrap();
+pub fn parse_from_table(mut rows: Vec<Vec<&str>>) -> Result<Vec<Entity>, String> {
+    if let Some(start) = category_block_start(&rows) {
+        rows.truncate(start);
+    }
 
     rows.into_iter()
+        .enumerate()
         .skip(1) // skip header row
-        .map(|row| {
+        .map(|(index, row)| {
+            let row_number = index + 1;
+
             // we now expect at least 9 columns
             if row.len() < 9 {
                 return Err(format!(
-                    "Bad row data: expected at least 9 columns, got {}",
+                    "Row {}: expected at least 9 columns, got {}",
+                    row_number,
                     row.len()
                 ));
             }
 
             // (1) parse constraints
-            let constraints_str = row[6].trim();
-            let cexprs = match constraints_str {
-                "" | "[]" => Vec::new(),
-                _ => re
-                    .captures_iter(constraints_str)
-                    .map(|cap| parse_one_constraint(cap[1].trim()))
-                    .collect::<Result<Vec<_>, _>>()?,
-            };
+            let cexprs = string_array(row[6])
+                .and_then(|items| items.iter().map(|s| parse_one_constraint(s.trim())).collect())
+                .map_err(|e| cell_error(row_number, 6, e))?;
 
             // (2) parse windows
-            let windows_str = row[7].trim();
-            let wspecs = match windows_str {
-                "" | "[]" => Vec::new(),
-                _ => {
-                    re.captures_iter(windows_str)
-                        .map(|cap| parse_one_window(cap[1].trim()))
-                        .collect::<Result<Vec<_>, _>>()?
-                }
-            };
-
-            // (3) build the entity
+            let wspecs = string_array(row[7])
+                .and_then(|items| items.iter().map(|s| parse_one_window(s.trim())).collect())
+                .map_err(|e| cell_error(row_number, 7, e))?;
+
+            // (3) parse the frequency
+            let frequency = Frequency::from_str(row[5]).map_err(|e| cell_error(row_number, 5, e))?;
+
+            // (4) build the entity
             Ok(Entity {
                 name: row[0].to_string(),
                 category: row[1].to_string(),
-                frequency: Frequency::from_str(row[5]),
+                frequency,
                 constraints: cexprs,
                 windows: wspecs, // new field in Entity
             })
@@ -69,35 +123,76 @@ pub fn parse_from_table(rows: Vec<Vec<&str>>) -> Result<Vec<Entity>, String> {
 /// Parse a single constraint snippet, e.g. "≥8h apart", "≥1h before food", etc.
 ///
 /// For example, the string "≥6h apart" is recognized as:
-///   - time_hours = 6
+///   - min_minutes = 360
 ///   - ctype = ConstraintType::Apart
 ///   - cref = ConstraintRef::WithinGroup (since "apart" was recognized)
+///
+/// The grammar is shared with the DBM generator, so durations may be in minutes or compound
+/// ("90m", "1h30m") and upper bounds take the same forms: "≤12h apart", "within 2h after food",
+/// "between 1h and 2h after food". References combine names as in the DBM generator ("food or
+/// snack", "med except Pardale"); every constraint here holds against each instance they name.
+/// Forms this generator can't encode ("with food", "after its food") are rejected.
 pub fn parse_one_constraint(s: &str) -> Result<ConstraintExpr, String> {
-    let patterns = &[
-        (r"^≥(\d+)h\s+apart$",              ConstraintType::Apart,     true),
-        (r"^≥(\d+)h\s+before\s+(.+)$",      ConstraintType::Before,    false),
-        (r"^≥(\d+)h\s+after\s+(.+)$",       ConstraintType::After,     false),
-        (r"^≥(\d+)h\s+apart\s+from\s+(.+)$",ConstraintType::ApartFrom, false),
-    ];
-
-    patterns
-        .iter()
-        .find_map(|(pattern, ctype, is_within_group)| {
-            Regex::new(pattern).unwrap().captures(s).map(|cap| {
-                let hrs: u32 = cap[1].parse().map_err(|_| "Bad hr".to_string())?;
-                let cref = if *is_within_group {
-                    ConstraintRef::WithinGroup
-                } else {
-                    ConstraintRef::Unresolved(cap[2].trim().to_string())
-                };
-                Ok(ConstraintExpr {
-                    time_hours: hrs,
-                    ctype: ctype.clone(),
-                    cref,
-                })
-            })
-        })
-        .unwrap_or_else(|| Err(format!("Unknown constraint expr: {}", s)))
+    let parsed = grammar::parse_constraint(s).map_err(|e| e.annotated())?;
+    let unsupported = |what: &str| Err(format!("'{}': {} not supported by the MILP generator", s.trim(), what));
+
+    let Some(ctype) = constraint_type_of(parsed.relation) else {
+        return unsupported("\"with\" constraints are");
+    };
+    if parsed.pairing != grammar::Pairing::All {
+        return unsupported("nearest/matching instances are");
+    }
+    let cref = match parsed.reference {
+        None => ConstraintRef::WithinGroup,
+        Some(reference) => ConstraintRef::Unresolved(reference.to_string()),
+    };
+    let (min_minutes, max_minutes) = gap_minutes(parsed.bound);
+
+    Ok(ConstraintExpr {
+        min_minutes,
+        max_minutes,
+        ctype,
+        cref,
+    })
+}
+
+/// Parse a category constraint, e.g. "med ≥30m apart from food" or "care ≤4h after food",
+/// with the grammar of the DBM generator's `CategoryConstraint::parse`.
+fn parse_category_constraint(s: &str) -> Result<CategoryConstraint, String> {
+    let parsed = grammar::parse_category_constraint(s.trim()).map_err(|e| e.annotated())?;
+    let (min_minutes, max_minutes) = gap_minutes(parsed.bound);
+
+    Ok(CategoryConstraint {
+        from_category: parsed.from,
+        constraint: ConstraintExpr {
+            min_minutes,
+            max_minutes,
+            ctype: constraint_type_of(parsed.relation).ok_or_else(|| {
+                format!("'{}': categories are related by before, after or apart from", s.trim())
+            })?,
+            cref: ConstraintRef::Unresolved(grammar::Reference::Name(parsed.to).to_string()),
+        },
+    })
+}
+
+/// The constraint type of a relation, or None for "with", which this generator can't encode.
+fn constraint_type_of(relation: grammar::Relation) -> Option<ConstraintType> {
+    match relation {
+        grammar::Relation::Before => Some(ConstraintType::Before),
+        grammar::Relation::After => Some(ConstraintType::After),
+        grammar::Relation::Apart => Some(ConstraintType::Apart),
+        grammar::Relation::ApartFrom => Some(ConstraintType::ApartFrom),
+        grammar::Relation::With => None,
+    }
+}
+
+/// The allowed gap in minutes, as (minimum, maximum if any).
+fn gap_minutes(bound: grammar::Bound) -> (u32, Option<u32>) {
+    match bound {
+        grammar::Bound::AtLeast(min) => (min.minutes(), None),
+        grammar::Bound::AtMost(max) => (0, Some(max.minutes())),
+        grammar::Bound::Between(min, max) => (min.minutes(), Some(max.minutes())),
+    }
 }
 
 /// Parse a single window snippet, e.g. "08:00" or "12:00-13:00".
@@ -0,0 +1,287 @@
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Unit {
+    Minute,
+    Hour,
+}
+
+// A length of time as written: "2h", "90m", or "1h30m" (kept in minutes)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Duration {
+    pub value: u32,
+    pub unit: Unit,
+}
+
+impl Duration {
+    // None if the length in minutes doesn't fit in a u32 (the parser rejects such durations)
+    pub fn checked_minutes(&self) -> Option<u32> {
+        match self.unit {
+            Unit::Minute => Some(self.value),
+            Unit::Hour => self.value.checked_mul(60),
+        }
+    }
+
+    // Parsed durations always fit; one built by hand that doesn't saturates
+    pub fn minutes(&self) -> u32 {
+        self.checked_minutes().unwrap_or(u32::MAX)
+    }
+}
+
+// How the duration bounds the gap between the clocks a constraint relates
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bound {
+    AtLeast(Duration),           // "≥1h", ">=1h", "at least 1h"
+    AtMost(Duration),            // "≤30m", "<=30m", "within 30m", "no more than 30m", "at most 30m"
+    Between(Duration, Duration), // "between 1h and 2h"
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Relation {
+    Before,
+    After,
+    ApartFrom,
+    Apart, // Between the instances of the entity itself
+    With,  // "with food", "within 15m of food": coinciding with an instance of the reference
+}
+
+// Which reference instances a before/after constraint relates each instance to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Pairing {
+    #[default]
+    All, // "after food"
+    Nearest,  // "after nearest food", "after the nearest food"
+    Matching, // "after its food", "after matching food"
+}
+
+// An entity constraint: "≥1h before food", "≤12h apart", "≥2h after its food", "with food"
+#[derive(Debug, Clone, PartialEq)]
+pub struct Constraint {
+    // "with" is bounded by its tolerance, zero for plain "with"
+    pub bound: Bound,
+    pub relation: Relation,
+    pub pairing: Pairing,
+    // None for "apart"
+    pub reference: Option<Reference>,
+}
+
+// A constraint between two categories: "med ≥30m apart from food"
+#[derive(Debug, Clone, PartialEq)]
+pub struct CategoryConstraint {
+    pub from: String,
+    pub bound: Bound,
+    pub relation: Relation,
+    pub to: String,
+}
+
+// What a constraint is related to: entity or category names, combined with "or", "and",
+// "except" and parentheses, e.g. `(food or snack) except "Chicken and rice"`.
+//
+// "except" binds loosest and "and" tightest, so `food or snack and med except Pardale` reads as
+// `(food or (snack and med)) except Pardale`. A name with spaces is written as is ("Pro Kolin"),
+// unless it contains a keyword or a parenthesis, when it must be quoted.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Reference {
+    Name(String),
+    // Any instance of the operands: one set of clocks holding all of them
+    Or(Vec<Reference>),
+    // Each operand: the constraint holds against every one of them
+    And(Vec<Reference>),
+    // The instances of the first that aren't instances of the second
+    Except(Box<Reference>, Box<Reference>),
+}
+
+pub(crate) const REFERENCE_KEYWORDS: [&str; 3] = ["or", "and", "except"];
+
+// Words that, first after "before" or "after", pick the instances to pair with instead of
+// starting the name: "its food", "the nearest food"
+const PAIRING_WORDS: [&str; 3] = ["its", "nearest", "matching"];
+
+// How often an entity occurs, as written. Counts and intervals are at least 1.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Frequency {
+    TimesPerDay(u32),                        // "daily", "twice a day", "3x daily", "4 times /d"
+    EveryHours(u32),                         // "every 8 hours"
+    EveryMinutes(u32),                       // "every 90 minutes"
+    EveryDays(u32),                          // "every 2 days", "every other day" (never 1)
+    OnWeekdays(Vec<Weekday>),                // "Mon/Wed/Fri", "on weekends", in week order
+    TimesPerWeek(u32),                       // "weekly", "3x weekly"
+    AsNeeded { max_per_day: Option<u32> },   // "as needed", "prn, max 3 daily"
+    At(Vec<FixedTime>),                      // "at 08:00, 20:00 ±30m", as written
+}
+
+// A clock time of a fixed-time frequency, in minutes from midnight, with its tolerance
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FixedTime {
+    pub minutes: u32,
+    pub tolerance: Option<Duration>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Weekday {
+    Mon,
+    Tue,
+    Wed,
+    Thu,
+    Fri,
+    Sat,
+    Sun,
+}
+
+impl Weekday {
+    pub const ALL: [Weekday; 7] = [
+        Weekday::Mon,
+        Weekday::Tue,
+        Weekday::Wed,
+        Weekday::Thu,
+        Weekday::Fri,
+        Weekday::Sat,
+        Weekday::Sun,
+    ];
+
+    // "mon", "Tues", "wednesday", ... (any prefix of the name of at least three letters)
+    pub fn parse(name: &str) -> Option<Self> {
+        let lower = name.trim().to_lowercase();
+        Weekday::ALL
+            .iter()
+            .copied()
+            .find(|day| lower.len() >= 3 && day.full_name().starts_with(&lower))
+    }
+
+    // Position in the week, from 0 for Monday
+    pub fn index(self) -> usize {
+        Weekday::ALL.iter().position(|&day| day == self).unwrap_or(0)
+    }
+
+    pub fn full_name(self) -> &'static str {
+        match self {
+            Weekday::Mon => "monday",
+            Weekday::Tue => "tuesday",
+            Weekday::Wed => "wednesday",
+            Weekday::Thu => "thursday",
+            Weekday::Fri => "friday",
+            Weekday::Sat => "saturday",
+            Weekday::Sun => "sunday",
+        }
+    }
+}
+
+impl Reference {
+    // The sets of items (clocks, say) the reference stands for, given the items of each name:
+    // one set per "and" operand, so that a constraint can hold against each of them. "or" joins
+    // its operands' sets ((A and B) or C is A or C, and B or C) and "except" removes what it
+    // excludes from every set. A constraint that holds against every item alike can use the
+    // items of all the sets together.
+    pub fn resolve<T: Clone + PartialEq, E>(
+        &self,
+        lookup: &mut impl FnMut(&str) -> Result<Vec<T>, E>,
+    ) -> Result<Vec<Vec<T>>, E> {
+        match self {
+            Reference::Name(name) => Ok(vec![lookup(name)?]),
+            Reference::And(parts) => {
+                let mut sets = Vec::new();
+                for part in parts {
+                    sets.extend(part.resolve(lookup)?);
+                }
+                Ok(sets)
+            }
+            Reference::Or(parts) => {
+                let mut sets: Vec<Vec<T>> = vec![Vec::new()];
+                for part in parts {
+                    let part_sets = part.resolve(lookup)?;
+                    sets = sets
+                        .iter()
+                        .flat_map(|set| {
+                            part_sets.iter().map(move |part_set| {
+                                let mut union = set.clone();
+                                for item in part_set {
+                                    if !union.contains(item) {
+                                        union.push(item.clone());
+                                    }
+                                }
+                                union
+                            })
+                        })
+                        .collect();
+                }
+                Ok(sets)
+            }
+            Reference::Except(base, excluded) => {
+                let excluded: Vec<T> = excluded.resolve(lookup)?.concat();
+                Ok(base
+                    .resolve(lookup)?
+                    .into_iter()
+                    .map(|set| set.into_iter().filter(|item| !excluded.contains(item)).collect())
+                    .collect())
+            }
+        }
+    }
+
+    // How tightly the expression binds, for placing parentheses when writing it back
+    fn precedence(&self) -> u8 {
+        match self {
+            Reference::Except(..) => 0,
+            Reference::Or(_) => 1,
+            Reference::And(_) => 2,
+            Reference::Name(_) => 3,
+        }
+    }
+
+    fn write_operand(&self, f: &mut fmt::Formatter<'_>, precedence: u8) -> fmt::Result {
+        if self.precedence() < precedence {
+            write!(f, "({})", self)
+        } else {
+            write!(f, "{}", self)
+        }
+    }
+}
+
+// Written back so that it parses to the same value, quoting names only where needed (a name
+// such as "its food" is quoted too, so it isn't read as pairing after "before")
+impl fmt::Display for Reference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Reference::Name(name) => {
+                let words: Vec<&str> = name.split(' ').collect();
+                let is_pairing = |word: &&str| {
+                    PAIRING_WORDS.iter().any(|pairing| word.eq_ignore_ascii_case(pairing))
+                };
+                let starts_with_pairing = words.first().is_some_and(is_pairing)
+                    || (words.len() > 1
+                        && words[0].eq_ignore_ascii_case("the")
+                        && is_pairing(&words[1]));
+                let bare = !name.is_empty()
+                    && !starts_with_pairing
+                    && words
+                        .iter()
+                        .all(|word| !word.is_empty() && !REFERENCE_KEYWORDS.contains(word))
+                    && !name.chars().any(|c| {
+                        matches!(c, '(' | ')' | '"' | '“' | '”') || (c.is_whitespace() && c != ' ')
+                    });
+                if bare {
+                    write!(f, "{}", name)
+                } else {
+                    write!(f, "\"{}\"", name)
+                }
+            }
+            Reference::Or(parts) | Reference::And(parts) => {
+                let (separator, precedence) = match self {
+                    Reference::Or(_) => (" or ", 2),
+                    _ => (" and ", 3),
+                };
+                for (i, part) in parts.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, "{}", separator)?;
+                    }
+                    part.write_operand(f, precedence)?;
+                }
+                Ok(())
+            }
+            Reference::Except(base, excluded) => {
+                base.write_operand(f, 0)?;
+                write!(f, " except ")?;
+                excluded.write_operand(f, 1)
+            }
+        }
+    }
+}
@@ -0,0 +1,71 @@
+use std::fmt;
+
+// A byte range of the parsed text
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Span { start, end }
+    }
+
+    // The smallest span covering both
+    pub fn to(self, other: Span) -> Span {
+        Span::new(self.start.min(other.start), self.end.max(other.end))
+    }
+}
+
+// Why and where parsing failed
+#[derive(Debug, Clone, PartialEq)]
+pub struct GrammarError {
+    // The text that failed to parse
+    pub source: String,
+    // The part of it the error is about (empty at the end of the text)
+    pub span: Span,
+    pub message: String,
+}
+
+impl GrammarError {
+    pub fn new(source: &str, span: Span, message: impl Into<String>) -> Self {
+        GrammarError {
+            source: source.to_string(),
+            span,
+            message: message.into(),
+        }
+    }
+
+    // The message, followed by the text with the span underlined:
+    //
+    //   Expected 'before', 'after', 'apart' or 'apart from', found 'befor'
+    //     ≥1h befor food
+    //         ^^^^^
+    pub fn annotated(&self) -> String {
+        format!(
+            "{}\n  {}\n  {}",
+            self.message,
+            self.source,
+            underline(&self.source, self.span)
+        )
+    }
+}
+
+// Carets under the span of `source`, counted in characters so they line up with "≥" and "±"
+pub fn underline(source: &str, span: Span) -> String {
+    let start = source.get(..span.start).map_or(0, |s| s.chars().count());
+    let width = source
+        .get(span.start..span.end)
+        .map_or(0, |s| s.chars().count())
+        .max(1);
+    format!("{}{}", " ".repeat(start), "^".repeat(width))
+}
+
+impl fmt::Display for GrammarError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for GrammarError {}
@@ -0,0 +1,246 @@
+use crate::error::{GrammarError, Span};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenKind {
+    Number(u32),
+    // "08:00", as hours and minutes (checked by the parser)
+    Time(u32, u32),
+    // A run of letters and other characters without special meaning ("before", "Pro-Kolin")
+    Word(String),
+    // A name in straight or curly double quotes
+    Quoted(String),
+    AtLeast,   // "≥" or ">="
+    AtMost,    // "≤" or "<="
+    PlusMinus, // "±", "+/-" or "+-"
+    Open,
+    Close,
+    Comma,
+    Semicolon,
+    Slash,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub span: Span,
+}
+
+// Characters that end a word. Digits do too, so "1h30m" and "2x" split into numbers and units;
+// names such as "B12" are read back from the source text, so the split doesn't show.
+fn ends_word(c: char) -> bool {
+    c.is_whitespace()
+        || c.is_ascii_digit()
+        || matches!(c, '(' | ')' | ',' | ';' | '/' | '"' | '“' | '”' | '≥' | '≤' | '±')
+}
+
+pub fn tokenize(source: &str) -> Result<Vec<Token>, GrammarError> {
+    let mut tokens = Vec::new();
+    let mut chars = source.char_indices().peekable();
+
+    while let Some(&(start, c)) = chars.peek() {
+        let rest = &source[start..];
+        let symbol = |kind: TokenKind, len: usize| Token {
+            kind,
+            span: Span::new(start, start + len),
+        };
+
+        let token = if c.is_whitespace() {
+            chars.next();
+            continue;
+        } else if let Some((kind, len)) = [
+            (">=", TokenKind::AtLeast),
+            ("<=", TokenKind::AtMost),
+            ("+/-", TokenKind::PlusMinus),
+            ("+-", TokenKind::PlusMinus),
+        ]
+        .into_iter()
+        .find(|(text, _)| rest.starts_with(text))
+        .map(|(text, kind)| (kind, text.len()))
+        {
+            for _ in 0..len {
+                chars.next();
+            }
+            symbol(kind, len)
+        } else if let Some(kind) = match c {
+            '≥' => Some(TokenKind::AtLeast),
+            '≤' => Some(TokenKind::AtMost),
+            '±' => Some(TokenKind::PlusMinus),
+            '(' => Some(TokenKind::Open),
+            ')' => Some(TokenKind::Close),
+            ',' => Some(TokenKind::Comma),
+            ';' => Some(TokenKind::Semicolon),
+            '/' => Some(TokenKind::Slash),
+            _ => None,
+        } {
+            chars.next();
+            symbol(kind, c.len_utf8())
+        } else if c == '"' || c == '“' {
+            chars.next();
+            let mut name = String::new();
+            let end = loop {
+                match chars.next() {
+                    Some((i, '"')) | Some((i, '”')) => break i + c_len(source, i),
+                    Some((_, c)) => name.push(c),
+                    None => {
+                        return Err(GrammarError::new(
+                            source,
+                            Span::new(start, source.len()),
+                            "Unterminated quote",
+                        ))
+                    }
+                }
+            };
+            Token {
+                kind: TokenKind::Quoted(name),
+                span: Span::new(start, end),
+            }
+        } else if c.is_ascii_digit() {
+            let number = digits(source, &mut chars)?;
+            // "08:00": a colon directly followed by digits makes a time of day
+            let after = &source[number.span.end..];
+            match (number.kind, after.strip_prefix(':')) {
+                (TokenKind::Number(hours), Some(minutes))
+                    if minutes.starts_with(|c: char| c.is_ascii_digit()) =>
+                {
+                    chars.next();
+                    let minutes = digits(source, &mut chars)?;
+                    let TokenKind::Number(minutes_value) = minutes.kind else {
+                        unreachable!()
+                    };
+                    Token {
+                        kind: TokenKind::Time(hours, minutes_value),
+                        span: number.span.to(minutes.span),
+                    }
+                }
+                (kind, _) => Token {
+                    kind,
+                    span: number.span,
+                },
+            }
+        } else {
+            let mut end = start;
+            while let Some(&(i, c)) = chars.peek() {
+                if ends_word(c) || source[i..].starts_with(">=") || source[i..].starts_with("<=") {
+                    break;
+                }
+                end = i + c.len_utf8();
+                chars.next();
+            }
+            Token {
+                kind: TokenKind::Word(source[start..end].to_string()),
+                span: Span::new(start, end),
+            }
+        };
+        tokens.push(token);
+    }
+
+    Ok(tokens)
+}
+
+fn c_len(source: &str, i: usize) -> usize {
+    source[i..].chars().next().map_or(0, char::len_utf8)
+}
+
+fn digits(
+    source: &str,
+    chars: &mut std::iter::Peekable<std::str::CharIndices>,
+) -> Result<Token, GrammarError> {
+    let start = chars.peek().map_or(source.len(), |&(i, _)| i);
+    let mut end = start;
+    while let Some(&(i, c)) = chars.peek() {
+        if !c.is_ascii_digit() {
+            break;
+        }
+        end = i + 1;
+        chars.next();
+    }
+
+    let span = Span::new(start, end);
+    let value = source[start..end]
+        .parse()
+        .map_err(|_| GrammarError::new(source, span, "Number is too large"))?;
+    Ok(Token {
+        kind: TokenKind::Number(value),
+        span,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(source: &str) -> Vec<TokenKind> {
+        tokenize(source)
+            .unwrap()
+            .into_iter()
+            .map(|token| token.kind)
+            .collect()
+    }
+
+    fn word(text: &str) -> TokenKind {
+        TokenKind::Word(text.to_string())
+    }
+
+    #[test]
+    fn symbols_have_unicode_and_ascii_forms() {
+        assert_eq!(kinds("≥"), kinds(">="));
+        assert_eq!(kinds("≤"), kinds("<="));
+        assert_eq!(kinds("±"), kinds("+/-"));
+        assert_eq!(kinds("±"), kinds("+-"));
+        assert_eq!(
+            kinds("≥1h before"),
+            vec![TokenKind::AtLeast, TokenKind::Number(1), word("h"), word("before")]
+        );
+    }
+
+    #[test]
+    fn digits_split_words() {
+        assert_eq!(
+            kinds("1h30m"),
+            vec![TokenKind::Number(1), word("h"), TokenKind::Number(30), word("m")]
+        );
+        assert_eq!(kinds("B12"), vec![word("B"), TokenKind::Number(12)]);
+        // A symbol ends a word too
+        assert_eq!(kinds("food>=1h"), kinds("food ≥ 1 h"));
+    }
+
+    #[test]
+    fn times_of_day() {
+        assert_eq!(
+            kinds("08:00, 20:30"),
+            vec![TokenKind::Time(8, 0), TokenKind::Comma, TokenKind::Time(20, 30)]
+        );
+        // A colon without digits after it isn't part of a time
+        assert_eq!(kinds("8:"), vec![TokenKind::Number(8), word(":")]);
+    }
+
+    #[test]
+    fn quoted_names() {
+        let tokens = tokenize("with \"Chicken and rice\"").unwrap();
+        assert_eq!(tokens[1].kind, TokenKind::Quoted("Chicken and rice".to_string()));
+        assert_eq!(tokens[1].span, Span::new(5, 23));
+
+        assert_eq!(
+            kinds("“Pro (Kolin)”"),
+            vec![TokenKind::Quoted("Pro (Kolin)".to_string())]
+        );
+    }
+
+    #[test]
+    fn spans_are_byte_ranges() {
+        let tokens = tokenize("≥1h").unwrap();
+        let spans: Vec<Span> = tokens.iter().map(|token| token.span).collect();
+        assert_eq!(spans, vec![Span::new(0, 3), Span::new(3, 4), Span::new(4, 5)]);
+    }
+
+    #[test]
+    fn errors_point_at_the_text() {
+        let error = tokenize("with \"food").unwrap_err();
+        assert_eq!(error.message, "Unterminated quote");
+        assert_eq!(error.span, Span::new(5, 10));
+
+        let error = tokenize("≥99999999999m apart").unwrap_err();
+        assert_eq!(error.message, "Number is too large");
+        assert_eq!(error.span, Span::new(3, 14));
+    }
+}
@@ -0,0 +1,45 @@
+// The DSL shared by the schedule generators: entity and category constraints, frequencies and
+// references, read by a tokenizer and recursive descent parser into one AST. Each generator
+// maps the AST onto its own types; errors carry the span of the text they are about. The table
+// the generators read entities from, and the string arrays in its cells, are read here too.
+mod ast;
+mod error;
+mod lexer;
+mod parser;
+mod table;
+
+pub use ast::{
+    Bound, CategoryConstraint, Constraint, Duration, FixedTime, Frequency, Pairing, Reference,
+    Relation, Unit, Weekday,
+};
+pub use error::{underline, GrammarError, Span};
+pub use table::{parse_string_array, read_table};
+
+use parser::Parser;
+
+// "≥1h before food", ">=90m apart", "between 1h and 1h30m after its food", "with food"
+pub fn parse_constraint(text: &str) -> Result<Constraint, GrammarError> {
+    Parser::new(text)?.constraint()
+}
+
+// "med ≥30m apart from food"
+pub fn parse_category_constraint(text: &str) -> Result<CategoryConstraint, GrammarError> {
+    Parser::new(text)?.category_constraint()
+}
+
+// "2x daily", "every 8 hours", "Mon/Wed/Fri", "as needed, max 3 daily", "at 08:00, 20:00 ±30m"
+pub fn parse_frequency(text: &str) -> Result<Frequency, GrammarError> {
+    Parser::new(text)?.frequency()
+}
+
+// "08:00" or "08:00±30m", one time of a fixed-time frequency
+pub fn parse_fixed_time(text: &str) -> Result<FixedTime, GrammarError> {
+    Parser::new(text)?.whole_fixed_time()
+}
+
+impl Reference {
+    // `food or snack`, `med except Gabapentin`, `(food or snack) and "Chicken and rice"`
+    pub fn parse(text: &str) -> Result<Self, GrammarError> {
+        Parser::new(text)?.whole_reference()
+    }
+}
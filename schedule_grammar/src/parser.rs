@@ -0,0 +1,962 @@
+use crate::ast::{
+    Bound, CategoryConstraint, Constraint, Duration, FixedTime, Frequency, Pairing, Reference,
+    Relation, Unit, Weekday, REFERENCE_KEYWORDS,
+};
+use crate::error::{GrammarError, Span};
+use crate::lexer::{tokenize, Token, TokenKind};
+
+// A recursive descent parser over the tokens of one expression. Keywords are matched
+// regardless of case, except the reference operators "or", "and" and "except", which are
+// lowercase so that names can use the capitalised words.
+pub struct Parser<'a> {
+    source: &'a str,
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    pub fn new(source: &'a str) -> Result<Self, GrammarError> {
+        Ok(Parser {
+            source,
+            tokens: tokenize(source)?,
+            position: 0,
+        })
+    }
+
+    // An entity constraint: "≥1h before food", "≤12h apart", "≥2h after its food",
+    // "with food", "within 15m of food"
+    pub fn constraint(&mut self) -> Result<Constraint, GrammarError> {
+        if self.eat_word("with") {
+            let reference = self.reference()?;
+            self.finish()?;
+            return Ok(Constraint {
+                bound: Bound::AtMost(Duration {
+                    value: 0,
+                    unit: Unit::Minute,
+                }),
+                relation: Relation::With,
+                pairing: Pairing::All,
+                reference: Some(reference),
+            });
+        }
+
+        let bound = self.bound()?;
+        if matches!(bound, Bound::AtMost(_)) && self.eat_word("of") {
+            let reference = self.reference()?;
+            self.finish()?;
+            return Ok(Constraint {
+                bound,
+                relation: Relation::With,
+                pairing: Pairing::All,
+                reference: Some(reference),
+            });
+        }
+
+        let relation = self.relation()?;
+        if relation == Relation::Apart {
+            self.finish()?;
+            return Ok(Constraint {
+                bound,
+                relation,
+                pairing: Pairing::All,
+                reference: None,
+            });
+        }
+
+        let pairing_span = self.span_here();
+        let pairing = self.pairing();
+        if pairing != Pairing::All && !matches!(relation, Relation::Before | Relation::After) {
+            return Err(self.error(
+                pairing_span.to(self.previous_span()),
+                "Only before/after constraints can pair instances",
+            ));
+        }
+
+        let reference = self.reference()?;
+        self.finish()?;
+        Ok(Constraint {
+            bound,
+            relation,
+            pairing,
+            reference: Some(reference),
+        })
+    }
+
+    // A category constraint: "med ≥30m apart from food", "care ≤4h after food"
+    pub fn category_constraint(&mut self) -> Result<CategoryConstraint, GrammarError> {
+        let from = self.words_until(|parser| parser.at_bound(), "a category")?;
+        let bound = self.bound()?;
+        let relation_span = self.span_here();
+        let relation = self.relation()?;
+        if !matches!(relation, Relation::Before | Relation::After | Relation::ApartFrom) {
+            return Err(self.error(
+                relation_span,
+                "Expected 'before', 'after' or 'apart from' between categories",
+            ));
+        }
+        let to = self.words_until(|_| false, "a category")?;
+
+        Ok(CategoryConstraint {
+            from,
+            bound,
+            relation,
+            to,
+        })
+    }
+
+    pub fn frequency(&mut self) -> Result<Frequency, GrammarError> {
+        let frequency = self.frequency_form()?;
+        self.finish()?;
+        Ok(frequency)
+    }
+
+    // A reference expression making up the whole text
+    pub fn whole_reference(&mut self) -> Result<Reference, GrammarError> {
+        let reference = self.reference()?;
+        self.finish()?;
+        Ok(reference)
+    }
+
+    // "08:00" or "08:00 ±30m" making up the whole text
+    pub fn whole_fixed_time(&mut self) -> Result<FixedTime, GrammarError> {
+        let time = self.fixed_time()?;
+        self.finish()?;
+        Ok(time)
+    }
+
+    // -- Bounds and durations --
+
+    fn at_bound(&self) -> bool {
+        matches!(
+            self.kind(0),
+            Some(TokenKind::AtLeast) | Some(TokenKind::AtMost)
+        ) || self.is_word(0, "within")
+            || self.is_word(0, "between")
+            || (self.is_word(0, "at") && (self.is_word(1, "least") || self.is_word(1, "most")))
+            || (self.is_word(0, "no") && self.is_word(1, "more") && self.is_word(2, "than"))
+    }
+
+    fn bound(&mut self) -> Result<Bound, GrammarError> {
+        if self.eat(&TokenKind::AtLeast) || self.eat_words(&["at", "least"]) {
+            return Ok(Bound::AtLeast(self.duration()?));
+        }
+        if self.eat(&TokenKind::AtMost)
+            || self.eat_word("within")
+            || self.eat_words(&["no", "more", "than"])
+            || self.eat_words(&["at", "most"])
+        {
+            return Ok(Bound::AtMost(self.duration()?));
+        }
+        if self.eat_word("between") {
+            let start = self.span_here();
+            let low = self.duration()?;
+            self.expect_word("and")?;
+            let high = self.duration()?;
+            if high.minutes() < low.minutes() {
+                return Err(self.error(start.to(self.previous_span()), "Range is reversed"));
+            }
+            return Ok(Bound::Between(low, high));
+        }
+
+        Err(self.expected("a bound such as ≥1h, ≤30m, within 30m or between 1h and 2h"))
+    }
+
+    // "2h", "90 minutes", or "1h30m" (in minutes)
+    fn duration(&mut self) -> Result<Duration, GrammarError> {
+        let start = self.span_here();
+        let mut parts = vec![self.duration_part()?];
+        while matches!(self.kind(0), Some(TokenKind::Number(_)))
+            && matches!(self.kind(1), Some(TokenKind::Word(word)) if unit(word).is_some())
+        {
+            parts.push(self.duration_part()?);
+        }
+
+        let total = parts.iter().try_fold(0u32, |total, part| {
+            part.checked_minutes().and_then(|minutes| total.checked_add(minutes))
+        });
+        let Some(total) = total else {
+            return Err(self.error(start.to(self.previous_span()), "Duration is too long"));
+        };
+
+        Ok(match parts[..] {
+            [single] => single,
+            _ => Duration {
+                value: total,
+                unit: Unit::Minute,
+            },
+        })
+    }
+
+    fn duration_part(&mut self) -> Result<Duration, GrammarError> {
+        let (value, _) = self.number("a duration such as 2h or 30m")?;
+        match self.kind(0) {
+            Some(TokenKind::Word(word)) if unit(word).is_some() => {
+                let unit = unit(word).unwrap();
+                self.position += 1;
+                Ok(Duration { value, unit })
+            }
+            _ => Err(self.expected("a unit ('h' or 'm')")),
+        }
+    }
+
+    fn relation(&mut self) -> Result<Relation, GrammarError> {
+        if self.eat_word("before") {
+            Ok(Relation::Before)
+        } else if self.eat_word("after") {
+            Ok(Relation::After)
+        } else if self.eat_word("apart") {
+            Ok(if self.eat_word("from") {
+                Relation::ApartFrom
+            } else {
+                Relation::Apart
+            })
+        } else {
+            Err(self.expected("'before', 'after', 'apart' or 'apart from'"))
+        }
+    }
+
+    // Like the reference keywords, pairing words count only as whole words: "its0 food" is a name
+    fn pairing(&mut self) -> Pairing {
+        let pairing_word = |parser: &Self, offset: usize, word: &str| {
+            parser.is_word(offset, word) && parser.stands_alone(offset)
+        };
+        if pairing_word(self, 0, "the")
+            && (pairing_word(self, 1, "nearest") || pairing_word(self, 1, "matching"))
+        {
+            self.position += 1;
+        }
+        let pairing = if pairing_word(self, 0, "nearest") {
+            Pairing::Nearest
+        } else if pairing_word(self, 0, "matching") || pairing_word(self, 0, "its") {
+            Pairing::Matching
+        } else {
+            return Pairing::All;
+        };
+        self.position += 1;
+        pairing
+    }
+
+    // -- References --
+
+    fn reference(&mut self) -> Result<Reference, GrammarError> {
+        let mut reference = self.reference_or()?;
+        while self.eat_keyword("except") {
+            let excluded = self.reference_or()?;
+            reference = Reference::Except(Box::new(reference), Box::new(excluded));
+        }
+        Ok(reference)
+    }
+
+    fn reference_or(&mut self) -> Result<Reference, GrammarError> {
+        let mut parts = vec![self.reference_and()?];
+        while self.eat_keyword("or") {
+            parts.push(self.reference_and()?);
+        }
+        Ok(if parts.len() == 1 {
+            parts.remove(0)
+        } else {
+            Reference::Or(parts)
+        })
+    }
+
+    fn reference_and(&mut self) -> Result<Reference, GrammarError> {
+        let mut parts = vec![self.reference_operand()?];
+        while self.eat_keyword("and") {
+            parts.push(self.reference_operand()?);
+        }
+        Ok(if parts.len() == 1 {
+            parts.remove(0)
+        } else {
+            Reference::And(parts)
+        })
+    }
+
+    // A parenthesised group, a quoted name, or the words up to the next keyword
+    fn reference_operand(&mut self) -> Result<Reference, GrammarError> {
+        match self.kind(0) {
+            Some(TokenKind::Open) => {
+                let open = self.span_here();
+                self.position += 1;
+                let reference = self.reference()?;
+                if !self.eat(&TokenKind::Close) {
+                    return Err(self.error(open, "Missing ')' for this '('"));
+                }
+                Ok(reference)
+            }
+            Some(TokenKind::Quoted(name)) => {
+                let name = name.clone();
+                if name.trim().is_empty() {
+                    return Err(self.error(self.span_here(), "Empty name"));
+                }
+                self.position += 1;
+                Ok(Reference::Name(name))
+            }
+            _ => self
+                .words_until(
+                    |parser| {
+                        parser.at_keyword()
+                            || matches!(
+                                parser.kind(0),
+                                Some(TokenKind::Open | TokenKind::Close | TokenKind::Quoted(_))
+                            )
+                    },
+                    "a name",
+                )
+                .map(Reference::Name),
+        }
+    }
+
+    fn at_keyword(&self) -> bool {
+        matches!(self.kind(0), Some(TokenKind::Word(word)) if REFERENCE_KEYWORDS.contains(&word.as_str()))
+            && self.stands_alone(0)
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        let found = matches!(self.kind(0), Some(TokenKind::Word(word)) if word == keyword)
+            && self.stands_alone(0);
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+
+    // The source text of the tokens up to `stop` (or the end), with runs of whitespace as a
+    // single space, so a name reads as written: "Pro-Kolin", "Vitamin B12"
+    fn words_until(
+        &mut self,
+        stop: impl Fn(&Self) -> bool,
+        what: &str,
+    ) -> Result<String, GrammarError> {
+        let start = self.position;
+        while self.position < self.tokens.len() && !stop(self) {
+            self.position += 1;
+        }
+        if self.position == start {
+            return Err(self.expected(what));
+        }
+
+        let span = self.tokens[start].span.to(self.tokens[self.position - 1].span);
+        Ok(self.source[span.start..span.end]
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" "))
+    }
+
+    // -- Frequencies --
+
+    fn frequency_form(&mut self) -> Result<Frequency, GrammarError> {
+        if self.eat_word("daily") {
+            return Ok(Frequency::TimesPerDay(1));
+        }
+        if self.eat_word("weekly") {
+            return Ok(Frequency::TimesPerWeek(1));
+        }
+        for (word, count) in [("once", 1), ("twice", 2), ("thrice", 3)] {
+            if self.eat_word(word) {
+                return self.per_period(count);
+            }
+        }
+        if let Some(TokenKind::Number(_)) = self.kind(0) {
+            let (count, span) = self.number("a count")?;
+            self.positive(count, span)?;
+            if !(self.eat_word("x") || self.eat_word("times")) {
+                return Err(self.expected("'x' or 'times'"));
+            }
+            return self.per_period(count);
+        }
+        if self.eat_word("every") {
+            return self.interval();
+        }
+        if self.eat_words(&["as", "needed"]) || self.eat_word("prn") {
+            return self.as_needed();
+        }
+        if self.eat_word("at") {
+            let mut times = vec![self.fixed_time()?];
+            while self.eat(&TokenKind::Comma) || self.eat_word("and") {
+                times.push(self.fixed_time()?);
+            }
+            return Ok(Frequency::At(times));
+        }
+
+        let on = self.eat_word("on");
+        match self.weekday_list()? {
+            Some(days) => Ok(Frequency::OnWeekdays(days)),
+            None if on => Err(self.expected("a weekday")),
+            None => Err(self.error(
+                Span::new(0, self.source.len()),
+                format!("Unrecognized frequency format: {}", self.source.trim()),
+            )),
+        }
+    }
+
+    // "daily", "a day", "per day", "/d", "/1d" (or the weekly forms) after a count
+    fn per_period(&mut self, count: u32) -> Result<Frequency, GrammarError> {
+        if self.eat_period(&["daily"], &["day"], "d") {
+            Ok(Frequency::TimesPerDay(count))
+        } else if self.eat_period(&["weekly"], &["week"], "w") {
+            Ok(Frequency::TimesPerWeek(count))
+        } else {
+            Err(self.expected("'daily' or 'weekly'"))
+        }
+    }
+
+    fn eat_period(&mut self, adverb: &[&str], noun: &[&str], abbreviation: &str) -> bool {
+        if self.eat_words(adverb)
+            || self.eat_words(&["a", noun[0]])
+            || self.eat_words(&["per", noun[0]])
+        {
+            return true;
+        }
+        let start = self.position;
+        if self.eat(&TokenKind::Slash) {
+            if let Some(TokenKind::Number(1)) = self.kind(0) {
+                self.position += 1;
+            }
+            if self.eat_word(abbreviation) {
+                return true;
+            }
+        }
+        self.position = start;
+        false
+    }
+
+    // After "every": "day", "other day", "8 hours", "90 minutes", "2 days"
+    fn interval(&mut self) -> Result<Frequency, GrammarError> {
+        if self.eat_word("day") {
+            return Ok(Frequency::TimesPerDay(1));
+        }
+        if self.eat_word("other") {
+            self.expect_word("day")?;
+            return Ok(Frequency::EveryDays(2));
+        }
+
+        let (count, span) = self.number("an interval")?;
+        self.positive(count, span)?;
+        let word = match self.kind(0) {
+            Some(TokenKind::Word(word)) => word.to_lowercase(),
+            _ => return Err(self.expected("'hours', 'minutes' or 'days'")),
+        };
+        let frequency = match (unit(&word), word.as_str()) {
+            (Some(Unit::Hour), _) => Frequency::EveryHours(count),
+            (Some(Unit::Minute), _) => Frequency::EveryMinutes(count),
+            (None, "day" | "days") if count == 1 => Frequency::TimesPerDay(1),
+            (None, "day" | "days") => Frequency::EveryDays(count),
+            _ => return Err(self.expected("'hours', 'minutes' or 'days'")),
+        };
+        self.position += 1;
+        Ok(frequency)
+    }
+
+    // After "as needed" or "prn": an optional ", max 3 daily", "(up to 2x a day)"
+    fn as_needed(&mut self) -> Result<Frequency, GrammarError> {
+        let open = self.eat(&TokenKind::Open);
+        if !open {
+            let _ = self.eat(&TokenKind::Comma) || self.eat(&TokenKind::Semicolon);
+        }
+
+        let mut max_per_day = None;
+        if self.eat_word("max") || self.eat_words(&["up", "to"]) {
+            let (count, span) = self.number("a maximum count")?;
+            self.positive(count, span)?;
+            let _ = self.eat_word("x") || self.eat_word("times");
+            self.eat_period(&["daily"], &["day"], "d");
+            max_per_day = Some(count);
+        }
+
+        if open && !self.eat(&TokenKind::Close) {
+            return Err(self.expected("')'"));
+        }
+        Ok(Frequency::AsNeeded { max_per_day })
+    }
+
+    fn fixed_time(&mut self) -> Result<FixedTime, GrammarError> {
+        let span = self.span_here();
+        let (hours, minutes) = match self.kind(0) {
+            Some(&TokenKind::Time(hours, minutes)) => (hours, minutes),
+            _ => return Err(self.expected("a time such as 08:00")),
+        };
+        self.position += 1;
+        if minutes > 59 {
+            return Err(self.error(span, "Time out of range"));
+        }
+        if hours >= 24 {
+            return Err(self.error(span, "A fixed time must be before 24:00"));
+        }
+
+        let tolerance = if self.eat(&TokenKind::PlusMinus) {
+            Some(self.duration()?)
+        } else {
+            None
+        };
+        Ok(FixedTime {
+            minutes: hours * 60 + minutes,
+            tolerance,
+        })
+    }
+
+    // "mon/wed/fri", "tue and thu", "weekdays", "weekends", in week order without duplicates;
+    // None if the text doesn't start with a weekday
+    fn weekday_list(&mut self) -> Result<Option<Vec<Weekday>>, GrammarError> {
+        let mut days = Vec::new();
+        loop {
+            let span = self.span_here();
+            let word = match self.kind(0) {
+                Some(TokenKind::Word(word)) => word.to_lowercase(),
+                _ if days.is_empty() => return Ok(None),
+                _ => return Err(self.expected("a weekday")),
+            };
+            match word.as_str() {
+                "weekdays" => days.extend_from_slice(&Weekday::ALL[..5]),
+                "weekends" | "weekend" => days.extend_from_slice(&Weekday::ALL[5..]),
+                name => match Weekday::parse(name) {
+                    Some(day) => days.push(day),
+                    None if days.is_empty() => return Ok(None),
+                    None => {
+                        return Err(self.error(span, format!("Unknown weekday: {}", name)))
+                    }
+                },
+            }
+            self.position += 1;
+
+            if !(self.eat(&TokenKind::Slash) || self.eat(&TokenKind::Comma) || self.eat_word("and"))
+            {
+                break;
+            }
+        }
+
+        Ok(Some(
+            Weekday::ALL
+                .iter()
+                .copied()
+                .filter(|day| days.contains(day))
+                .collect(),
+        ))
+    }
+
+    fn positive(&self, count: u32, span: Span) -> Result<(), GrammarError> {
+        if count == 0 {
+            return Err(self.error(
+                span,
+                format!(
+                    "'{}' has no instances: counts and intervals must be at least 1",
+                    self.source.trim()
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    // -- Tokens --
+
+    fn kind(&self, offset: usize) -> Option<&TokenKind> {
+        self.tokens.get(self.position + offset).map(|token| &token.kind)
+    }
+
+    // The span of the next token, or an empty one at the end of the text
+    fn span_here(&self) -> Span {
+        match self.tokens.get(self.position) {
+            Some(token) => token.span,
+            None => Span::new(self.source.len(), self.source.len()),
+        }
+    }
+
+    fn previous_span(&self) -> Span {
+        match self.position.checked_sub(1).and_then(|i| self.tokens.get(i)) {
+            Some(token) => token.span,
+            None => Span::new(0, 0),
+        }
+    }
+
+    // Whether the token `offset` ahead is a whole word: the lexer splits digits from letters,
+    // so the "or" of "A0or" is a word of its own, but one that runs into the "0" before it
+    fn stands_alone(&self, offset: usize) -> bool {
+        let index = self.position + offset;
+        let Some(token) = self.tokens.get(index) else {
+            return false;
+        };
+        let joined = |other: Option<&Token>| {
+            other.is_some_and(|other| {
+                (other.span.end == token.span.start || other.span.start == token.span.end)
+                    && matches!(other.kind, TokenKind::Word(_) | TokenKind::Number(_))
+            })
+        };
+        !joined(index.checked_sub(1).and_then(|i| self.tokens.get(i)))
+            && !joined(self.tokens.get(index + 1))
+    }
+
+    fn is_word(&self, offset: usize, expected: &str) -> bool {
+        matches!(self.kind(offset), Some(TokenKind::Word(word)) if word.eq_ignore_ascii_case(expected))
+    }
+
+    fn eat_word(&mut self, expected: &str) -> bool {
+        self.eat_words(&[expected])
+    }
+
+    // Consume the words if they come next, in order
+    fn eat_words(&mut self, expected: &[&str]) -> bool {
+        let found = expected
+            .iter()
+            .enumerate()
+            .all(|(offset, word)| self.is_word(offset, word));
+        if found {
+            self.position += expected.len();
+        }
+        found
+    }
+
+    fn eat(&mut self, kind: &TokenKind) -> bool {
+        let found = self.kind(0) == Some(kind);
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+
+    fn expect_word(&mut self, expected: &str) -> Result<(), GrammarError> {
+        if self.eat_word(expected) {
+            Ok(())
+        } else {
+            Err(self.expected(&format!("'{}'", expected)))
+        }
+    }
+
+    fn number(&mut self, what: &str) -> Result<(u32, Span), GrammarError> {
+        match self.tokens.get(self.position) {
+            Some(&Token {
+                kind: TokenKind::Number(value),
+                span,
+            }) => {
+                self.position += 1;
+                Ok((value, span))
+            }
+            _ => Err(self.expected(what)),
+        }
+    }
+
+    fn finish(&self) -> Result<(), GrammarError> {
+        match self.tokens.get(self.position) {
+            None => Ok(()),
+            Some(_) => Err(self.error(
+                self.span_here().to(self.tokens[self.tokens.len() - 1].span),
+                format!("Unexpected {}", self.found()),
+            )),
+        }
+    }
+
+    fn found(&self) -> String {
+        match self.tokens.get(self.position) {
+            Some(token) => format!("'{}'", &self.source[token.span.start..token.span.end]),
+            None => "the end".to_string(),
+        }
+    }
+
+    fn expected(&self, what: &str) -> GrammarError {
+        self.error(
+            self.span_here(),
+            format!("Expected {}, found {}", what, self.found()),
+        )
+    }
+
+    fn error(&self, span: Span, message: impl Into<String>) -> GrammarError {
+        GrammarError::new(self.source, span, message)
+    }
+}
+
+fn unit(word: &str) -> Option<Unit> {
+    match word.to_lowercase().as_str() {
+        "h" | "hr" | "hrs" | "hour" | "hours" => Some(Unit::Hour),
+        "m" | "min" | "mins" | "minute" | "minutes" => Some(Unit::Minute),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        parse_category_constraint, parse_constraint, parse_frequency, Bound, Constraint,
+        Duration, Frequency, Pairing, Reference, Relation, Span, Unit,
+    };
+
+    fn minutes(value: u32) -> Duration {
+        Duration {
+            value,
+            unit: Unit::Minute,
+        }
+    }
+
+    fn hours(value: u32) -> Duration {
+        Duration {
+            value,
+            unit: Unit::Hour,
+        }
+    }
+
+    fn name(text: &str) -> Reference {
+        Reference::Name(text.to_string())
+    }
+
+    fn reference(text: &str) -> Reference {
+        Reference::parse(text).unwrap()
+    }
+
+    // The error's message and the text its span covers
+    fn error(result: Result<impl std::fmt::Debug, crate::GrammarError>) -> (String, String) {
+        let error = result.unwrap_err();
+        let covered = error.source[error.span.start..error.span.end].to_string();
+        (error.message, covered)
+    }
+
+    #[test]
+    fn bounds() {
+        let parse = |text: &str| parse_constraint(text).unwrap().bound;
+        assert_eq!(parse("≥1h before food"), Bound::AtLeast(hours(1)));
+        assert_eq!(parse(">=1h before food"), Bound::AtLeast(hours(1)));
+        assert_eq!(parse("at least 1 hour before food"), Bound::AtLeast(hours(1)));
+        assert_eq!(parse("≤30m after food"), Bound::AtMost(minutes(30)));
+        assert_eq!(parse("<=30m after food"), Bound::AtMost(minutes(30)));
+        assert_eq!(parse("no more than 30 mins after food"), Bound::AtMost(minutes(30)));
+        assert_eq!(
+            parse("between 1h and 1h30m after food"),
+            Bound::Between(hours(1), minutes(90))
+        );
+    }
+
+    #[test]
+    fn mixed_units_add_up_in_minutes() {
+        let parse = |text: &str| parse_constraint(text).unwrap().bound;
+        assert_eq!(parse("≥1h30m apart"), Bound::AtLeast(minutes(90)));
+        assert_eq!(parse("≥2h 15 min apart"), Bound::AtLeast(minutes(135)));
+        assert_eq!(parse("≥90m apart"), Bound::AtLeast(minutes(90)));
+    }
+
+    #[test]
+    fn relations() {
+        assert_eq!(
+            parse_constraint("≤12h apart"),
+            Ok(Constraint {
+                bound: Bound::AtMost(hours(12)),
+                relation: Relation::Apart,
+                pairing: Pairing::All,
+                reference: None,
+            })
+        );
+        assert_eq!(
+            parse_constraint("≥30m apart from Pro-Kolin"),
+            Ok(Constraint {
+                bound: Bound::AtLeast(minutes(30)),
+                relation: Relation::ApartFrom,
+                pairing: Pairing::All,
+                reference: Some(name("Pro-Kolin")),
+            })
+        );
+    }
+
+    #[test]
+    fn with_and_within() {
+        assert_eq!(
+            parse_constraint("with food"),
+            Ok(Constraint {
+                bound: Bound::AtMost(minutes(0)),
+                relation: Relation::With,
+                pairing: Pairing::All,
+                reference: Some(name("food")),
+            })
+        );
+        assert_eq!(
+            parse_constraint("within 15m of food"),
+            Ok(Constraint {
+                bound: Bound::AtMost(minutes(15)),
+                relation: Relation::With,
+                pairing: Pairing::All,
+                reference: Some(name("food")),
+            })
+        );
+        // Without "of", "within" is a bound like "≤"
+        assert_eq!(
+            parse_constraint("within 2h after food").unwrap().relation,
+            Relation::After
+        );
+    }
+
+    #[test]
+    fn pairing_words() {
+        let pairing = |text: &str| {
+            let constraint = parse_constraint(text).unwrap();
+            (constraint.pairing, constraint.reference.unwrap())
+        };
+        assert_eq!(pairing("≥2h after food"), (Pairing::All, name("food")));
+        assert_eq!(pairing("≥2h after nearest food"), (Pairing::Nearest, name("food")));
+        assert_eq!(pairing("≥2h after the nearest food"), (Pairing::Nearest, name("food")));
+        assert_eq!(pairing("≥2h after its food"), (Pairing::Matching, name("food")));
+        assert_eq!(pairing("≥2h after the matching food"), (Pairing::Matching, name("food")));
+        // A name that merely starts like one
+        assert_eq!(pairing("≥2h after its0 food"), (Pairing::All, name("its0 food")));
+        assert_eq!(pairing("≥2h after \"its food\""), (Pairing::All, name("its food")));
+
+        assert_eq!(
+            error(parse_constraint("≥2h apart from its food")),
+            (
+                "Only before/after constraints can pair instances".to_string(),
+                "its".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn names() {
+        assert_eq!(reference("Vitamin  B12"), name("Vitamin B12"));
+        assert_eq!(reference("\"Chicken and rice\""), name("Chicken and rice"));
+        assert_eq!(reference("“Pro (Kolin)”"), name("Pro (Kolin)"));
+        // Only lowercase operators, and only whole words, combine names
+        assert_eq!(reference("Salt And Pepper"), name("Salt And Pepper"));
+        assert_eq!(reference("A0or"), name("A0or"));
+        assert_eq!(reference("a or0"), name("a or0"));
+    }
+
+    #[test]
+    fn operator_precedence() {
+        assert_eq!(
+            reference("food or snack and med except Pardale"),
+            Reference::Except(
+                Box::new(Reference::Or(vec![
+                    name("food"),
+                    Reference::And(vec![name("snack"), name("med")]),
+                ])),
+                Box::new(name("Pardale")),
+            )
+        );
+        assert_eq!(
+            reference("(food or snack) and med"),
+            Reference::And(vec![Reference::Or(vec![name("food"), name("snack")]), name("med")])
+        );
+        assert_eq!(
+            reference("med except A except B"),
+            Reference::Except(
+                Box::new(Reference::Except(Box::new(name("med")), Box::new(name("A")))),
+                Box::new(name("B")),
+            )
+        );
+    }
+
+    #[test]
+    fn references_display_as_they_parse() {
+        for text in [
+            "food",
+            "Vitamin B12",
+            "food or snack",
+            "food or snack and med",
+            "(food or snack) and med",
+            "med except Gabapentin or Pardale",
+            "med except (Gabapentin and Pardale)",
+            "(med except A) except B",
+            "\"Chicken and rice\" or \"its food\"",
+            "\"the nearest food\"",
+            "A0or and B",
+        ] {
+            let parsed = reference(text);
+            assert_eq!(reference(&parsed.to_string()), parsed, "{}", text);
+        }
+        assert_eq!(reference("(food) or ((snack))").to_string(), "food or snack");
+        assert_eq!(reference("\"Chicken and rice\"").to_string(), "\"Chicken and rice\"");
+        assert_eq!(reference("\"Pro-Kolin\"").to_string(), "Pro-Kolin");
+    }
+
+    #[test]
+    fn category_constraints() {
+        let constraint = parse_category_constraint("med ≥30m apart from food").unwrap();
+        assert_eq!(
+            (constraint.from.as_str(), constraint.relation, constraint.to.as_str()),
+            ("med", Relation::ApartFrom, "food")
+        );
+        assert_eq!(
+            error(parse_category_constraint("med ≥30m apart")),
+            (
+                "Expected 'before', 'after' or 'apart from' between categories".to_string(),
+                "apart".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn frequencies() {
+        assert_eq!(parse_frequency("twice a day"), Ok(Frequency::TimesPerDay(2)));
+        assert_eq!(parse_frequency("3x daily"), Ok(Frequency::TimesPerDay(3)));
+        assert_eq!(parse_frequency("every 8 hours"), Ok(Frequency::EveryHours(8)));
+        assert_eq!(
+            parse_frequency("prn, max 3 daily"),
+            Ok(Frequency::AsNeeded {
+                max_per_day: Some(3)
+            })
+        );
+    }
+
+    #[test]
+    fn error_spans() {
+        assert_eq!(
+            error(parse_constraint("≥1h befor food")),
+            (
+                "Expected 'before', 'after', 'apart' or 'apart from', found 'befor'".to_string(),
+                "befor".to_string()
+            )
+        );
+        assert_eq!(
+            error(parse_constraint("between 2h and 1h after food")),
+            ("Range is reversed".to_string(), "2h and 1h".to_string())
+        );
+        assert_eq!(
+            error(Reference::parse("(food or snack")),
+            ("Missing ')' for this '('".to_string(), "(".to_string())
+        );
+        assert_eq!(
+            error(parse_constraint("with food)")),
+            ("Unexpected ')'".to_string(), ")".to_string())
+        );
+
+        // At the end of the text the span is empty ("≥" is three bytes)
+        let end = parse_constraint("≥1h before").unwrap_err();
+        assert_eq!(end.message, "Expected a name, found the end");
+        assert_eq!(end.span, Span::new(12, 12));
+    }
+
+    #[test]
+    fn durations_too_long_to_count() {
+        assert_eq!(
+            error(parse_constraint("between 1h and 99999999h after food")),
+            ("Duration is too long".to_string(), "99999999h".to_string())
+        );
+        assert_eq!(
+            error(parse_constraint("≥4294967295m1m apart")),
+            ("Duration is too long".to_string(), "4294967295m1m".to_string())
+        );
+        assert_eq!(
+            parse_constraint("≥71582788h apart").unwrap().bound,
+            Bound::AtLeast(hours(71_582_788))
+        );
+    }
+
+    #[test]
+    fn references_resolve_to_sets_of_items() {
+        // Each name stands for the instances of it, numbered
+        fn items(name: &str) -> Result<Vec<u32>, String> {
+            match name {
+                "food" => Ok(vec![1, 2]),
+                "Treat" => Ok(vec![2]),
+                "snack" => Ok(vec![3]),
+                "med" => Ok(vec![4, 5]),
+                _ => Err(name.to_string()),
+            }
+        }
+        let resolve = |text: &str| Reference::parse(text).unwrap().resolve(&mut items);
+
+        assert_eq!(resolve("food"), Ok(vec![vec![1, 2]]));
+        assert_eq!(resolve("food and med"), Ok(vec![vec![1, 2], vec![4, 5]]));
+        assert_eq!(resolve("food or snack or Treat"), Ok(vec![vec![1, 2, 3]]));
+        assert_eq!(
+            resolve("(food and med) or snack"),
+            Ok(vec![vec![1, 2, 3], vec![4, 5, 3]])
+        );
+        assert_eq!(resolve("food or snack except Treat"), Ok(vec![vec![1, 3]]));
+        assert_eq!(resolve("food and med except food"), Ok(vec![vec![], vec![4, 5]]));
+        assert_eq!(resolve("food or kibble"), Err("kibble".to_string()));
+    }
+}
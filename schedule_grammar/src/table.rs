@@ -0,0 +1,163 @@
+// The table both generators read their entities from: a CSV or TSV file of cells, with the
+// constraints and windows of an entity written as an array of strings in one cell
+use crate::error::{GrammarError, Span};
+use std::fs::File;
+use std::path::Path;
+
+// Read a CSV or TSV file into rows of cells. The delimiter is picked from the file extension
+// (.tsv/.tab => tab, otherwise comma). Cells follow standard CSV quoting, so a constraint array
+// is written as e.g.
+//   Antepsin,med,tablet,null,3,3x daily,"[""≥6h apart"", ""≥1h before food""]",null
+// Errors name the file, and the line where there is one.
+pub fn read_table(path: &Path) -> Result<Vec<Vec<String>>, String> {
+    let delimiter = match path.extension().and_then(|ext| ext.to_str()) {
+        Some(ext) if ext.eq_ignore_ascii_case("tsv") || ext.eq_ignore_ascii_case("tab") => b'\t',
+        _ => b',',
+    };
+
+    let file =
+        File::open(path).map_err(|e| format!("Could not open {}: {}", path.display(), e))?;
+
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .has_headers(false)
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(file);
+
+    let mut rows = Vec::new();
+    for record in reader.records() {
+        let record = record.map_err(|e| match e.position() {
+            Some(pos) => format!("{}: line {}: {}", path.display(), pos.line(), e),
+            None => format!("{}: {}", path.display(), e),
+        })?;
+        rows.push(record.iter().map(|cell| cell.to_string()).collect());
+    }
+
+    if rows.is_empty() {
+        return Err(format!("{}: file contains no rows", path.display()));
+    }
+
+    Ok(rows)
+}
+
+// Parse a bracketed array of double-quoted strings, e.g. ["≥6h apart", "≥1h before food"].
+// A backslash escapes the next character inside a string, so an item may hold a quoted name
+// (["≥1h after \"Chicken and rice\""]); an empty or "null" cell is an empty array.
+pub fn parse_string_array(cell: &str) -> Result<Vec<String>, GrammarError> {
+    let cell = cell.trim();
+    if cell.is_empty() || cell == "null" {
+        return Ok(Vec::new());
+    }
+
+    let error = |span: Span, message: String| GrammarError::new(cell, span, message);
+    if !cell.starts_with('[') || !cell.ends_with(']') {
+        return Err(error(
+            Span::new(0, cell.len()),
+            "Expected a bracketed array like [\"...\"]".to_string(),
+        ));
+    }
+
+    let end = cell.len() - 1;
+    let mut items = Vec::new();
+    let mut chars = cell[1..end].char_indices().map(|(i, c)| (i + 1, c)).peekable();
+
+    loop {
+        // An item, or the end of the array
+        while chars.next_if(|&(_, c)| c.is_whitespace()).is_some() {}
+        let start = match chars.next() {
+            None => break,
+            Some((i, '"')) => i,
+            Some((i, c)) => {
+                return Err(error(
+                    Span::new(i, i + c.len_utf8()),
+                    format!("Expected '\"' to start an array item, found '{}'", c),
+                ))
+            }
+        };
+
+        let mut item = String::new();
+        loop {
+            match chars.next() {
+                Some((_, '"')) => break,
+                Some((i, '\\')) => match chars.next() {
+                    Some((_, escaped)) => item.push(escaped),
+                    None => return Err(error(Span::new(i, i + 1), "Dangling escape".to_string())),
+                },
+                Some((_, c)) => item.push(c),
+                None => {
+                    return Err(error(Span::new(start, end), "Unterminated string".to_string()))
+                }
+            }
+        }
+        items.push(item);
+
+        // A separator, or the end of the array
+        while chars.next_if(|&(_, c)| c.is_whitespace()).is_some() {}
+        match chars.next() {
+            None => break,
+            Some((_, ',')) => continue,
+            Some((i, c)) => {
+                return Err(error(
+                    Span::new(i, i + c.len_utf8()),
+                    format!("Expected ',' between array items, found '{}'", c),
+                ))
+            }
+        }
+    }
+
+    Ok(items)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{parse_string_array, Span};
+
+    #[test]
+    fn string_arrays() {
+        assert_eq!(parse_string_array(""), Ok(Vec::new()));
+        assert_eq!(parse_string_array(" null "), Ok(Vec::new()));
+        assert_eq!(parse_string_array("[]"), Ok(Vec::new()));
+        assert_eq!(
+            parse_string_array(r#"[ "≥6h apart" ,"≥1h before food"]"#),
+            Ok(vec!["≥6h apart".to_string(), "≥1h before food".to_string()])
+        );
+        // Escaped quotes stay in the item, where the grammar reads them as a quoted name
+        assert_eq!(
+            parse_string_array(r#"["≥1h after \"Chicken and rice\"", "with \"food\""]"#),
+            Ok(vec![
+                r#"≥1h after "Chicken and rice""#.to_string(),
+                r#"with "food""#.to_string()
+            ])
+        );
+    }
+
+    #[test]
+    fn string_array_errors() {
+        let error = |cell: &str| {
+            let error = parse_string_array(cell).unwrap_err();
+            (error.message, error.source[error.span.start..error.span.end].to_string())
+        };
+        assert_eq!(
+            error("≥6h apart"),
+            ("Expected a bracketed array like [\"...\"]".to_string(), "≥6h apart".to_string())
+        );
+        assert_eq!(
+            error("[≥6h apart]"),
+            ("Expected '\"' to start an array item, found '≥'".to_string(), "≥".to_string())
+        );
+        assert_eq!(
+            error(r#"["≥6h apart" "with food"]"#),
+            ("Expected ',' between array items, found '\"'".to_string(), "\"".to_string())
+        );
+        assert_eq!(
+            error(r#"["≥6h apart]"#),
+            ("Unterminated string".to_string(), "\"≥6h apart".to_string())
+        );
+        assert_eq!(
+            error(r#"["≥6h apart\]"#),
+            ("Dangling escape".to_string(), "\\".to_string())
+        );
+        assert_eq!(parse_string_array("[").unwrap_err().span, Span::new(0, 1));
+    }
+}